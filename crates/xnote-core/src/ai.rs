@@ -1,5 +1,5 @@
 use crate::knowledge::{KnowledgeIndex, SearchOptions};
-use crate::vault::Vault;
+use crate::vault::{NoteSaveSource, Vault};
 use anyhow::{anyhow, Context, Result};
 use serde::Serialize;
 use serde_json::json;
@@ -798,6 +798,13 @@ impl VcpToolRegistry {
             risk: VcpToolRisk::ReadOnly,
             required_args: vec!["query".to_string()],
         });
+        registry.register(VcpToolSpec {
+            name: "xnote.relations.list_types".to_string(),
+            description: "List the relation types this vault allows in note meta, built-in and vault-defined, as JSON."
+                .to_string(),
+            risk: VcpToolRisk::ReadOnly,
+            required_args: Vec::new(),
+        });
         registry.register(VcpToolSpec {
             name: "xnote.vault.write_note".to_string(),
             description: "Write markdown note content to a vault-relative note path.".to_string(),
@@ -1074,10 +1081,18 @@ pub fn execute_vcp_tool_request(
 
             markdown
         }
+        "xnote.relations.list_types" => {
+            let (registry, mode) = vault.relation_type_registry()?;
+            format!(
+                "## xnote.relations.list_types\n\n- `validation`: `{}`\n\n```json\n{}\n```",
+                mode.as_tag(),
+                sanitize_fence_body(registry.export_json()?.as_str())
+            )
+        }
         "xnote.vault.write_note" => {
             let note_path = arg_required(request, "note_path")?;
             let content = arg_required(request, "content")?;
-            vault.write_note(note_path, content)?;
+            vault.write_note_with_source(note_path, content, NoteSaveSource::AiTool)?;
             format!(
                 "## xnote.vault.write_note\n\n- `note_path`: `{}`\n- `result`: `ok`",
                 note_path
@@ -1621,6 +1636,20 @@ note_path:\u{300c}\u{59cb}\u{300d}notes/demo.md\u{300c}\u{672b}\u{300d}\n\
             .to_ascii_lowercase()
             .contains("rust"));
 
+        let types_request = VcpToolRequest {
+            tool_name: "xnote.relations.list_types".to_string(),
+            args: BTreeMap::new(),
+            no_reply: false,
+            mark_history: false,
+        };
+        let types_result =
+            execute_vcp_tool_request(&types_request, &vault, None, &VcpToolPolicy::default())
+                .expect("relation types tool execution");
+        assert!(types_result
+            .payload_markdown
+            .contains("`validation`: `warn`"));
+        assert!(types_result.payload_markdown.contains("\"xnote.explains\""));
+
         let _ = fs::remove_dir_all(&temp_dir);
     }
 
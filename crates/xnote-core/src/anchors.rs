@@ -0,0 +1,732 @@
+//! Heading and block anchors inside notes.
+//!
+//! Headings are addressed by slug (`note#my-heading`, nested as `note#Parent#Child`) and
+//! paragraphs or list items by an Obsidian-style `^block-id` suffix (`note#^block-id`).
+//! Block ids live in the note text, so they keep pointing at the same paragraph while
+//! the surrounding content is edited.
+
+use crate::frontmatter::frontmatter_bounds;
+use crate::knowledge::{markdown_link_target_ranges, wikilink_inner_ranges, KnowledgeIndex};
+use crate::markdown::markdown_heading_level;
+use crate::note_meta::NoteMetaTarget;
+use crate::vault::Vault;
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static BLOCK_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteAnchorKind {
+    Heading,
+    Block,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteAnchor {
+    pub kind: NoteAnchorKind,
+    /// Heading slug (suffixed `-1`, `-2` for repeats) or block id.
+    pub id: String,
+    /// Heading text, or the block id for blocks.
+    pub label: String,
+    /// Heading level; 0 for blocks.
+    pub level: u8,
+    /// Zero-based line of the heading or of the block's first line.
+    pub line: usize,
+    /// Bytes the anchor addresses: a heading's whole section up to the next heading of
+    /// the same or a higher level, or a block's text including its `^id` marker.
+    pub range: Range<usize>,
+}
+
+/// Parsed anchor part of a link (`#Heading`, `#A#B`, `#^block`) or of a meta target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnchorRef {
+    /// Heading path from outermost to innermost.
+    Heading(Vec<String>),
+    Block(String),
+}
+
+impl AnchorRef {
+    /// Parses an anchor with or without its leading `#`.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        let raw = raw.strip_prefix('#').unwrap_or(raw).trim();
+        if let Some(block_id) = raw.strip_prefix('^') {
+            return is_block_id(block_id).then(|| Self::Block(block_id.to_string()));
+        }
+        let path = raw
+            .split('#')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        (!path.is_empty()).then_some(Self::Heading(path))
+    }
+}
+
+/// Splits a link target such as `Note#Heading|alias` into its note part and anchor.
+///
+/// The note part is empty for same-note links like `#^block`.
+pub fn split_link_anchor(raw_link: &str) -> (String, Option<AnchorRef>) {
+    let raw = raw_link.trim();
+    let raw = raw.split_once('|').map(|(target, _)| target).unwrap_or(raw);
+    match raw.split_once('#') {
+        Some((target, anchor)) => (target.trim().to_string(), AnchorRef::parse(anchor)),
+        None => (raw.trim().to_string(), None),
+    }
+}
+
+/// GitHub-style slug: lowercase, alphanumerics kept, whitespace and `-` collapsed to `-`.
+pub fn heading_slug(text: &str) -> String {
+    let mut out = String::new();
+    let mut pending_dash = false;
+    for ch in text.trim().chars() {
+        if ch.is_alphanumeric() || ch == '_' {
+            if pending_dash && !out.is_empty() {
+                out.push('-');
+            }
+            pending_dash = false;
+            out.extend(ch.to_lowercase());
+        } else if ch.is_whitespace() || ch == '-' {
+            pending_dash = true;
+        }
+    }
+    out
+}
+
+/// Every heading and block anchor in `content`, in document order.
+pub fn parse_note_anchors(content: &str) -> Vec<NoteAnchor> {
+    let blocks = scan_blocks(content);
+    let mut anchors = Vec::new();
+    let mut slug_counts = HashMap::<String, usize>::new();
+
+    for (ix, block) in blocks.iter().enumerate() {
+        match &block.kind {
+            ScannedKind::Heading { level, text } => {
+                let base = heading_slug(text);
+                let seen = slug_counts.entry(base.clone()).or_default();
+                let id = if *seen == 0 {
+                    base
+                } else {
+                    format!("{base}-{seen}")
+                };
+                *seen += 1;
+                let section_end = blocks[ix + 1..]
+                    .iter()
+                    .find_map(|next| match next.kind {
+                        ScannedKind::Heading {
+                            level: next_level, ..
+                        } if next_level <= *level => Some(next.range.start),
+                        _ => None,
+                    })
+                    .unwrap_or(content.len());
+                anchors.push(NoteAnchor {
+                    kind: NoteAnchorKind::Heading,
+                    id,
+                    label: text.clone(),
+                    level: *level,
+                    line: block.line,
+                    range: block.range.start..section_end,
+                });
+            }
+            ScannedKind::Paragraph | ScannedKind::ListItem => {
+                let Some((block_id, marker)) = block.block_id.as_ref() else {
+                    continue;
+                };
+                anchors.push(NoteAnchor {
+                    kind: NoteAnchorKind::Block,
+                    id: block_id.clone(),
+                    label: block_id.clone(),
+                    level: 0,
+                    line: block.line,
+                    range: block.range.start..block.range.end.max(marker.end),
+                });
+            }
+        }
+    }
+    anchors
+}
+
+/// Finds the anchor `anchor` refers to in `content`.
+pub fn resolve_anchor(content: &str, anchor: &AnchorRef) -> Option<NoteAnchor> {
+    let anchors = parse_note_anchors(content);
+    match anchor {
+        AnchorRef::Block(block_id) => anchors
+            .into_iter()
+            .find(|a| a.kind == NoteAnchorKind::Block && a.id.eq_ignore_ascii_case(block_id)),
+        AnchorRef::Heading(path) => {
+            let mut scope: Option<NoteAnchor> = None;
+            for part in path {
+                let slug = heading_slug(part);
+                let found = anchors.iter().find(|a| {
+                    a.kind == NoteAnchorKind::Heading
+                        && (a.id == slug || heading_slug(&a.label) == slug)
+                        && scope.as_ref().is_none_or(|outer| {
+                            a.level > outer.level
+                                && a.range.start > outer.range.start
+                                && a.range.start < outer.range.end
+                        })
+                })?;
+                scope = Some(found.clone());
+            }
+            scope
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockIdAssignment {
+    pub block_id: String,
+    /// Note content with the id appended; equal to the input when the block had one.
+    pub content: String,
+    /// False when the block already carried an id, which is returned unchanged.
+    pub created: bool,
+}
+
+/// Gives the paragraph or list item at byte `offset` a `^block-id`, reusing its existing one.
+pub fn assign_block_id(content: &str, offset: usize) -> Result<BlockIdAssignment> {
+    if offset > content.len() {
+        return Err(anyhow!("offset {offset} is past the end of the note"));
+    }
+    let blocks = scan_blocks(content);
+    let block = blocks
+        .iter()
+        .find(|block| {
+            let end = block
+                .block_id
+                .as_ref()
+                .map_or(block.range.end, |(_, marker)| marker.end);
+            offset >= block.range.start && offset <= end
+        })
+        .ok_or_else(|| anyhow!("no paragraph at offset {offset}"))?;
+    if matches!(block.kind, ScannedKind::Heading { .. }) {
+        return Err(anyhow!("offset {offset} is inside a heading"));
+    }
+
+    if let Some((block_id, _)) = block.block_id.as_ref() {
+        return Ok(BlockIdAssignment {
+            block_id: block_id.clone(),
+            content: content.to_string(),
+            created: false,
+        });
+    }
+
+    let existing = blocks
+        .iter()
+        .filter_map(|b| b.block_id.as_ref().map(|(id, _)| id.to_ascii_lowercase()))
+        .collect::<HashSet<_>>();
+    let block_id = loop {
+        let candidate = generate_block_id();
+        if !existing.contains(&candidate) {
+            break candidate;
+        }
+    };
+    let insert_at = block.range.end;
+    let mut next = String::with_capacity(content.len() + block_id.len() + 2);
+    next.push_str(&content[..insert_at]);
+    next.push_str(" ^");
+    next.push_str(&block_id);
+    next.push_str(&content[insert_at..]);
+    Ok(BlockIdAssignment {
+        block_id,
+        content: next,
+        created: true,
+    })
+}
+
+/// Six lowercase base-36 characters, as Obsidian generates them.
+pub fn generate_block_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default();
+    let seq = BLOCK_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut x = nanos ^ seq.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ u64::from(std::process::id());
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    x ^= x >> 33;
+
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    (0..6)
+        .map(|_| {
+            let digit = DIGITS[(x % 36) as usize] as char;
+            x /= 36;
+            digit
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedAnchor {
+    /// Vault-relative path of the note holding the anchor.
+    pub path: String,
+    pub anchor: NoteAnchor,
+}
+
+/// Resolves a link such as `Note#Heading` or `#^block` written in `from_path`.
+///
+/// Returns `None` when the link has no anchor, its note is unknown or the anchor is dead.
+pub fn resolve_link_anchor(
+    vault: &Vault,
+    knowledge: &KnowledgeIndex,
+    from_path: &str,
+    raw_link: &str,
+) -> Result<Option<ResolvedAnchor>> {
+    let (target, anchor) = split_link_anchor(raw_link);
+    let Some(anchor) = anchor else {
+        return Ok(None);
+    };
+    let path = if target.is_empty() {
+        from_path.to_string()
+    } else {
+        match knowledge.resolve_link_target(&target) {
+            Some(path) => path,
+            None => return Ok(None),
+        }
+    };
+    let content = vault.read_note(&path)?;
+    Ok(resolve_anchor(&content, &anchor).map(|anchor| ResolvedAnchor { path, anchor }))
+}
+
+/// Resolves the anchor of a `kind: knowledge` note meta relation target.
+pub fn resolve_meta_anchor(
+    vault: &Vault,
+    knowledge: &KnowledgeIndex,
+    target: &NoteMetaTarget,
+) -> Result<Option<ResolvedAnchor>> {
+    if target.kind.trim() != "knowledge" {
+        return Err(anyhow!(
+            "relation target kind is not knowledge: {}",
+            target.kind
+        ));
+    }
+    let Some(anchor) = target.anchor.as_deref().and_then(AnchorRef::parse) else {
+        return Ok(None);
+    };
+    let Some(path) = knowledge.path_for_note_id(&target.id) else {
+        return Ok(None);
+    };
+    let content = vault.read_note(&path)?;
+    Ok(resolve_anchor(&content, &anchor).map(|anchor| ResolvedAnchor { path, anchor }))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeadAnchorSource {
+    /// A link in a note body; `range` covers the link target text.
+    Link { path: String, range: Range<usize> },
+    /// A note meta relation, by position in `NoteMetaV1::relations`.
+    Relation {
+        note_id: String,
+        relation_index: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeadAnchor {
+    pub source: DeadAnchorSource,
+    /// Vault-relative path of the note the anchor should be in.
+    pub target_path: String,
+    pub anchor: String,
+}
+
+/// Every link and relation anchor that no longer resolves.
+///
+/// Links to unknown notes are broken links rather than dead anchors and are skipped.
+pub fn find_dead_anchors(vault: &Vault, knowledge: &KnowledgeIndex) -> Result<Vec<DeadAnchor>> {
+    let mut pending = Vec::new();
+    for path in knowledge.all_paths_sorted() {
+        let content = vault.read_note(&path)?;
+        let mut ranges = wikilink_inner_ranges(&content);
+        ranges.extend(markdown_link_target_ranges(&content));
+        ranges.sort();
+        for (start, end) in ranges {
+            let raw = &content[start..end];
+            if raw.contains("://") || !raw.contains('#') {
+                continue;
+            }
+            let (target, Some(anchor)) = split_link_anchor(raw) else {
+                continue;
+            };
+            let target_path = if target.is_empty() {
+                Some(path.clone())
+            } else {
+                knowledge.resolve_link_target(&target)
+            };
+            if let Some(target_path) = target_path {
+                let raw_anchor = raw.split_once('#').map(|(_, a)| a).unwrap_or_default();
+                let raw_anchor = raw_anchor.split('|').next().unwrap_or_default().trim();
+                pending.push((
+                    DeadAnchorSource::Link {
+                        path: path.clone(),
+                        range: start..end,
+                    },
+                    target_path,
+                    Some(anchor),
+                    raw_anchor.to_string(),
+                ));
+            }
+        }
+    }
+    for meta in vault.list_note_metas()? {
+        for (relation_index, relation) in meta.relations.iter().enumerate() {
+            if relation.to.kind.trim() != "knowledge" {
+                continue;
+            }
+            let Some(raw_anchor) = relation.to.anchor.as_deref() else {
+                continue;
+            };
+            let Some(target_path) = knowledge.path_for_note_id(&relation.to.id) else {
+                continue;
+            };
+            let source = DeadAnchorSource::Relation {
+                note_id: meta.id.clone(),
+                relation_index,
+            };
+            pending.push((
+                source,
+                target_path,
+                AnchorRef::parse(raw_anchor),
+                raw_anchor.to_string(),
+            ));
+        }
+    }
+
+    let mut anchors_by_path = HashMap::<String, Vec<NoteAnchor>>::new();
+    let mut dead = Vec::new();
+    for (source, target_path, anchor, raw_anchor) in pending {
+        if !anchors_by_path.contains_key(&target_path) {
+            let content = vault.read_note(&target_path)?;
+            anchors_by_path.insert(target_path.clone(), parse_note_anchors(&content));
+        }
+        let anchors = &anchors_by_path[&target_path];
+        // Anchors that do not even parse can never resolve.
+        if !anchor.is_some_and(|anchor| anchor_exists(anchors, &anchor)) {
+            dead.push(DeadAnchor {
+                source,
+                target_path,
+                anchor: raw_anchor,
+            });
+        }
+    }
+    Ok(dead)
+}
+
+fn anchor_exists(anchors: &[NoteAnchor], anchor: &AnchorRef) -> bool {
+    match anchor {
+        AnchorRef::Block(block_id) => anchors
+            .iter()
+            .any(|a| a.kind == NoteAnchorKind::Block && a.id.eq_ignore_ascii_case(block_id)),
+        AnchorRef::Heading(path) => {
+            let Some(innermost) = path.last().map(|part| heading_slug(part)) else {
+                return false;
+            };
+            // Nesting is checked by `resolve_anchor`; existence only needs the innermost part.
+            anchors.iter().any(|a| {
+                a.kind == NoteAnchorKind::Heading
+                    && (a.id == innermost || heading_slug(&a.label) == innermost)
+            })
+        }
+    }
+}
+
+fn is_block_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ScannedKind {
+    Heading { level: u8, text: String },
+    Paragraph,
+    ListItem,
+}
+
+#[derive(Clone, Debug)]
+struct ScannedBlock {
+    kind: ScannedKind,
+    line: usize,
+    /// Block text without the trailing newline and without its `^id` marker.
+    range: Range<usize>,
+    /// Block id and the byte range of its marker, from ` ^` to the end of the id.
+    block_id: Option<(String, Range<usize>)>,
+}
+
+/// Splits a note body into headings, paragraphs and list items, skipping frontmatter
+/// and fenced code.
+fn scan_blocks(content: &str) -> Vec<ScannedBlock> {
+    let body_start = frontmatter_bounds(content).map_or(0, |bounds| bounds.end);
+
+    let mut blocks: Vec<ScannedBlock> = Vec::new();
+    let mut paragraph: Option<ScannedBlock> = None;
+    let mut fence: Option<&str> = None;
+    let mut line_start = 0usize;
+
+    for (line_ix, raw_line) in content.split_inclusive('\n').enumerate() {
+        let start = line_start;
+        line_start += raw_line.len();
+        if start < body_start {
+            continue;
+        }
+        let line = raw_line.trim_end_matches(['\n', '\r']);
+        let end = start + line.len();
+        let trimmed = line.trim_start();
+
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            flush_paragraph(&mut paragraph, content, &mut blocks);
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush_paragraph(&mut paragraph, content, &mut blocks);
+            continue;
+        }
+        if let Some(level) = markdown_heading_level(trimmed) {
+            flush_paragraph(&mut paragraph, content, &mut blocks);
+            let text = trimmed[level as usize..]
+                .trim()
+                .trim_end_matches('#')
+                .trim_end()
+                .to_string();
+            blocks.push(ScannedBlock {
+                kind: ScannedKind::Heading { level, text },
+                line: line_ix,
+                range: start..end,
+                block_id: None,
+            });
+            continue;
+        }
+        if let Some(block_id) = trimmed.strip_prefix('^').filter(|id| is_block_id(id)) {
+            // A lone `^id` line labels the block before it (tables, quotes, lists).
+            flush_paragraph(&mut paragraph, content, &mut blocks);
+            if let Some(last) = blocks.last_mut() {
+                if last.block_id.is_none() && !matches!(last.kind, ScannedKind::Heading { .. }) {
+                    last.block_id = Some((block_id.to_string(), start..end));
+                }
+            }
+            continue;
+        }
+        if is_list_item(trimmed) {
+            flush_paragraph(&mut paragraph, content, &mut blocks);
+            paragraph = Some(ScannedBlock {
+                kind: ScannedKind::ListItem,
+                line: line_ix,
+                range: start..end,
+                block_id: None,
+            });
+            flush_paragraph(&mut paragraph, content, &mut blocks);
+            continue;
+        }
+        match paragraph.as_mut() {
+            Some(current) => current.range.end = end,
+            None => {
+                paragraph = Some(ScannedBlock {
+                    kind: ScannedKind::Paragraph,
+                    line: line_ix,
+                    range: start..end,
+                    block_id: None,
+                })
+            }
+        }
+    }
+    flush_paragraph(&mut paragraph, content, &mut blocks);
+    blocks
+}
+
+fn flush_paragraph(
+    paragraph: &mut Option<ScannedBlock>,
+    content: &str,
+    blocks: &mut Vec<ScannedBlock>,
+) {
+    let Some(mut block) = paragraph.take() else {
+        return;
+    };
+    let text = &content[block.range.clone()];
+    if let Some(caret) = text.rfind(" ^") {
+        let id = &text[caret + 2..];
+        if is_block_id(id) {
+            let marker = block.range.start + caret..block.range.end;
+            let text_end = block.range.start + text[..caret].trim_end().len();
+            block.range.end = text_end;
+            block.block_id = Some((id.to_string(), marker));
+        }
+    }
+    blocks.push(block);
+}
+
+fn is_list_item(trimmed: &str) -> bool {
+    if ["- ", "* ", "+ "].iter().any(|m| trimmed.starts_with(m)) {
+        return true;
+    }
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && (trimmed[digits..].starts_with(". ") || trimmed[digits..].starts_with(") "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_meta::NoteMetaRelation;
+    use serde_json::Map;
+    use std::fs;
+
+    const NOTE: &str = "---\nid: N1\n---\n# Guide\n\nIntro line one\nline two ^intro\n\n## Setup\n\n- install ^step-1\n- run\n\n```\n# not a heading ^nope\n```\n\n| a | b |\n^table\n\n## Setup\n\nAgain.\n";
+
+    #[test]
+    fn parse_note_anchors_finds_headings_and_blocks() {
+        let anchors = parse_note_anchors(NOTE);
+        let ids = anchors
+            .iter()
+            .map(|a| (a.kind, a.id.as_str(), a.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                (NoteAnchorKind::Heading, "guide", 3),
+                (NoteAnchorKind::Block, "intro", 5),
+                (NoteAnchorKind::Heading, "setup", 8),
+                (NoteAnchorKind::Block, "step-1", 10),
+                (NoteAnchorKind::Block, "table", 17),
+                (NoteAnchorKind::Heading, "setup-1", 20),
+            ]
+        );
+
+        let intro = &anchors[1];
+        assert_eq!(
+            &NOTE[intro.range.clone()],
+            "Intro line one\nline two ^intro"
+        );
+        let setup = &anchors[2];
+        assert!(NOTE[setup.range.clone()].starts_with("## Setup\n\n- install"));
+        assert!(NOTE[setup.range.clone()].ends_with("^table\n\n"));
+        assert_eq!(anchors[0].range.end, NOTE.len());
+        assert_eq!(
+            heading_slug("  Hello, World -- Again! "),
+            "hello-world-again"
+        );
+        assert_eq!(heading_slug("中文 标题"), "中文-标题");
+    }
+
+    #[test]
+    fn resolve_anchor_handles_links_and_nested_headings() {
+        let (target, anchor) = split_link_anchor("Guide#Setup|see setup");
+        assert_eq!(target, "Guide");
+        let setup = resolve_anchor(NOTE, &anchor.expect("anchor")).expect("resolves");
+        assert_eq!(setup.line, 8);
+
+        let (target, anchor) = split_link_anchor("#^STEP-1");
+        assert!(target.is_empty());
+        let step = resolve_anchor(NOTE, &anchor.expect("anchor")).expect("block");
+        assert_eq!(&NOTE[step.range], "- install ^step-1");
+
+        let nested = AnchorRef::parse("Guide#Setup").expect("nested");
+        assert_eq!(resolve_anchor(NOTE, &nested).map(|a| a.line), Some(8));
+        let wrong_nesting = AnchorRef::parse("Setup#Guide").expect("nested");
+        assert!(resolve_anchor(NOTE, &wrong_nesting).is_none());
+        assert!(resolve_anchor(NOTE, &AnchorRef::Block("nope".to_string())).is_none());
+        assert_eq!(AnchorRef::parse("^bad id"), None);
+    }
+
+    #[test]
+    fn assign_block_id_appends_once() {
+        let doc = "# T\n\nfirst para\ncontinues\n\n- item\n";
+        let offset = doc.find("continues").expect("offset");
+        let assigned = assign_block_id(doc, offset).expect("assign");
+        assert!(assigned.created);
+        assert_eq!(assigned.block_id.len(), 6);
+        assert_eq!(
+            assigned.content,
+            format!(
+                "# T\n\nfirst para\ncontinues ^{}\n\n- item\n",
+                assigned.block_id
+            )
+        );
+
+        let again = assign_block_id(&assigned.content, offset).expect("reuse");
+        assert!(!again.created);
+        assert_eq!(again.block_id, assigned.block_id);
+        assert_eq!(again.content, assigned.content);
+
+        let item = assign_block_id(doc, doc.find("item").expect("item")).expect("item");
+        assert!(item
+            .content
+            .ends_with(&format!("- item ^{}\n", item.block_id)));
+        assert!(assign_block_id(doc, 1).is_err());
+        assert!(assign_block_id(doc, doc.find("\n\n-").expect("blank") + 1).is_err());
+    }
+
+    #[test]
+    fn find_dead_anchors_reports_links_and_relations() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_anchors_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes")).expect("create notes");
+        fs::write(temp_dir.join("notes/Guide.md"), NOTE).expect("guide");
+        fs::write(
+            temp_dir.join("notes/Links.md"),
+            "---\nid: N2\n---\n# Links\n[[Guide#Setup]] [[Guide#Teardown]] [[Guide#^intro]]\n[x](Guide.md#^gone) [[#Links]] [[Missing#Any]]\n",
+        )
+        .expect("links");
+        let vault = Vault::open(&temp_dir).expect("open vault");
+        let knowledge = KnowledgeIndex::rebuild_from_vault(&vault).expect("knowledge");
+
+        let mut meta = crate::note_meta::NoteMetaV1::new("N2").expect("meta");
+        for anchor in ["^step-1", "^removed"] {
+            meta.relations.push(NoteMetaRelation {
+                relation_type: "xnote.references".to_string(),
+                to: NoteMetaTarget {
+                    kind: "knowledge".to_string(),
+                    id: "N1".to_string(),
+                    anchor: Some(anchor.to_string()),
+                    extra: Map::new(),
+                },
+                note: None,
+                created_at: None,
+                created_by: None,
+                extra: Map::new(),
+            });
+        }
+        vault.save_note_meta(&meta).expect("save meta");
+
+        let resolved = resolve_meta_anchor(&vault, &knowledge, &meta.relations[0].to)
+            .expect("resolve")
+            .expect("some");
+        assert_eq!(resolved.path, "notes/Guide.md");
+        assert_eq!(resolved.anchor.id, "step-1");
+        let linked = resolve_link_anchor(&vault, &knowledge, "notes/Links.md", "Guide#^intro")
+            .expect("resolve link")
+            .expect("some");
+        assert_eq!(linked.anchor.line, 5);
+
+        let dead = find_dead_anchors(&vault, &knowledge).expect("dead anchors");
+        let summary = dead
+            .iter()
+            .map(|d| (d.target_path.as_str(), d.anchor.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("notes/Guide.md", "Teardown"),
+                ("notes/Guide.md", "^gone"),
+                ("notes/Guide.md", "^removed"),
+            ]
+        );
+        assert_eq!(
+            dead[2].source,
+            DeadAnchorSource::Relation {
+                note_id: "N2".to_string(),
+                relation_index: 1,
+            }
+        );
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
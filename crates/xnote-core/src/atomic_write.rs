@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Context as _, Result};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+const TEMP_FILE_MARKER: &str = ".xnote-tmp-";
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplacedFile {
+    pub len: u64,
+    pub content_hash: String,
+    pub modified: Option<SystemTime>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AtomicWriteOutcome {
+    pub path: PathBuf,
+    pub len: u64,
+    pub content_hash: String,
+    /// Previous file state, `None` when the target did not exist.
+    pub replaced: Option<ReplacedFile>,
+}
+
+impl AtomicWriteOutcome {
+    pub fn changed(&self) -> bool {
+        self.replaced
+            .as_ref()
+            .is_none_or(|prev| prev.content_hash != self.content_hash)
+    }
+}
+
+/// Lowercase hex SHA-256 of `bytes`, used as the vault-wide content hash.
+pub fn content_hash(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    let mut out = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        out.push_str(&format!("{byte:02x}"));
+    }
+    out
+}
+
+/// Returns true for sibling temp files created by [`write_atomic`].
+pub fn is_atomic_temp_file_name(file_name: &str) -> bool {
+    file_name.starts_with('.') && file_name.contains(TEMP_FILE_MARKER)
+}
+
+/// Write `bytes` to `path` so readers observe either the old or the new content.
+///
+/// The data goes to a temp file in the same directory, is fsynced, renamed over
+/// the target, and the directory entry is fsynced afterwards.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<AtomicWriteOutcome> {
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .ok_or_else(|| anyhow!("atomic write target has no parent dir: {:?}", path))?;
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("atomic write target has no utf-8 file name: {:?}", path))?;
+
+    fs::create_dir_all(parent).with_context(|| format!("create parent dir: {:?}", parent))?;
+
+    let replaced = read_replaced_file(path)?;
+
+    let seq = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let temp_path = parent.join(format!(
+        ".{file_name}{TEMP_FILE_MARKER}{}-{seq}",
+        std::process::id()
+    ));
+
+    if let Err(err) = write_and_sync_temp(&temp_path, bytes) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err);
+    }
+
+    if let Err(err) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err).with_context(|| format!("rename temp file over target: {:?}", path));
+    }
+
+    sync_dir(parent)?;
+
+    Ok(AtomicWriteOutcome {
+        path: path.to_path_buf(),
+        len: bytes.len() as u64,
+        content_hash: content_hash(bytes),
+        replaced,
+    })
+}
+
+fn read_replaced_file(path: &Path) -> Result<Option<ReplacedFile>> {
+    let previous = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("read previous file: {:?}", path)),
+    };
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    Ok(Some(ReplacedFile {
+        len: previous.len() as u64,
+        content_hash: content_hash(&previous),
+        modified,
+    }))
+}
+
+fn write_and_sync_temp(temp_path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(temp_path)
+        .with_context(|| format!("create temp file: {:?}", temp_path))?;
+    file.write_all(bytes)
+        .with_context(|| format!("write temp file: {:?}", temp_path))?;
+    file.sync_all()
+        .with_context(|| format!("fsync temp file: {:?}", temp_path))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    let handle = fs::File::open(dir).with_context(|| format!("open dir for fsync: {:?}", dir))?;
+    handle
+        .sync_all()
+        .with_context(|| format!("fsync dir: {:?}", dir))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    // Directory handles cannot be fsynced portably; the rename itself is durable
+    // once the metadata journal flushes.
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hash_is_stable_sha256_hex() {
+        assert_eq!(
+            content_hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn write_atomic_reports_replaced_and_leaves_no_temp_files() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_atomic_write_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        let target = temp_dir.join("sub/a.md");
+
+        let first = write_atomic(&target, b"one").expect("first write");
+        assert!(first.replaced.is_none());
+        assert!(first.changed());
+
+        let second = write_atomic(&target, b"two").expect("second write");
+        let replaced = second.replaced.clone().expect("replaced info");
+        assert_eq!(replaced.len, 3);
+        assert_eq!(replaced.content_hash, content_hash(b"one"));
+        assert!(second.changed());
+        assert_eq!(fs::read_to_string(&target).expect("read"), "two");
+
+        let same = write_atomic(&target, b"two").expect("same write");
+        assert!(!same.changed());
+
+        let leftovers = fs::read_dir(temp_dir.join("sub"))
+            .expect("read dir")
+            .filter_map(|e| e.ok())
+            .filter(|e| is_atomic_temp_file_name(&e.file_name().to_string_lossy()))
+            .count();
+        assert_eq!(leftovers, 0);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn temp_file_names_are_recognized() {
+        assert!(is_atomic_temp_file_name(".a.md.xnote-tmp-12-3"));
+        assert!(!is_atomic_temp_file_name("a.md"));
+        assert!(!is_atomic_temp_file_name(".hidden.md"));
+    }
+}
@@ -0,0 +1,308 @@
+/// Upper bound on LCS table cells before falling back to a coarse replace hunk.
+const MAX_LCS_CELLS: usize = 16_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineDiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineDiff {
+    pub op: LineDiffOp,
+    /// 1-based line number in the old text, `None` for inserted lines.
+    pub old_line: Option<usize>,
+    /// 1-based line number in the new text, `None` for deleted lines.
+    pub new_line: Option<usize>,
+    pub text: String,
+}
+
+/// Line-level diff of two texts, in old-then-new order within each changed run.
+pub fn diff_lines(old: &str, new: &str) -> Vec<LineDiff> {
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+
+    diff_slices(&old_lines, &new_lines)
+        .into_iter()
+        .map(|(op, old_ix, new_ix)| {
+            let text = match op {
+                LineDiffOp::Insert => new_lines[new_ix],
+                LineDiffOp::Equal | LineDiffOp::Delete => old_lines[old_ix],
+            };
+            LineDiff {
+                op,
+                old_line: (op != LineDiffOp::Insert).then_some(old_ix + 1),
+                new_line: (op != LineDiffOp::Delete).then_some(new_ix + 1),
+                text: text.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Diff of two slices as `(op, old_index, new_index)` triples.
+///
+/// For `Insert` the old index is the insertion point, for `Delete` the new index is.
+pub fn diff_slices<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(LineDiffOp, usize, usize)> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut out = Vec::with_capacity(old.len().max(new.len()));
+    for ix in 0..prefix {
+        out.push((LineDiffOp::Equal, ix, ix));
+    }
+    diff_middle(old_mid, new_mid, prefix, prefix, &mut out);
+    for ix in 0..suffix {
+        out.push((
+            LineDiffOp::Equal,
+            old.len() - suffix + ix,
+            new.len() - suffix + ix,
+        ));
+    }
+    out
+}
+
+fn diff_middle<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    old_base: usize,
+    new_base: usize,
+    out: &mut Vec<(LineDiffOp, usize, usize)>,
+) {
+    let n = old.len();
+    let m = new.len();
+    if n == 0 || m == 0 || (n + 1).saturating_mul(m + 1) > MAX_LCS_CELLS {
+        for ix in 0..n {
+            out.push((LineDiffOp::Delete, old_base + ix, new_base));
+        }
+        for ix in 0..m {
+            out.push((LineDiffOp::Insert, old_base + n, new_base + ix));
+        }
+        return;
+    }
+
+    // lcs[i][j] = LCS length of old[i..] and new[j..]
+    let width = m + 1;
+    let mut lcs = vec![0u32; (n + 1) * width];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i * width + j] = if old[i] == new[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0usize, 0usize);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            out.push((LineDiffOp::Equal, old_base + i, new_base + j));
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            out.push((LineDiffOp::Delete, old_base + i, new_base + j));
+            i += 1;
+        } else {
+            out.push((LineDiffOp::Insert, old_base + i, new_base + j));
+            j += 1;
+        }
+    }
+    while i < n {
+        out.push((LineDiffOp::Delete, old_base + i, new_base + j));
+        i += 1;
+    }
+    while j < m {
+        out.push((LineDiffOp::Insert, old_base + i, new_base + j));
+        j += 1;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MergeHunk {
+    /// Text both sides agree on, or that only one side changed.
+    Resolved(String),
+    Conflict {
+        base: String,
+        ours: String,
+        theirs: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ThreeWayMerge {
+    Merged(String),
+    /// Hunks in document order; concatenating them with a pick per conflict yields the text.
+    Conflicted(Vec<MergeHunk>),
+}
+
+/// Line-based three-way merge of `ours` and `theirs` against their common `base`.
+///
+/// Line endings are kept as they appear in the inputs.
+pub fn merge_three_way(base: &str, ours: &str, theirs: &str) -> ThreeWayMerge {
+    let base_lines = base.split_inclusive('\n').collect::<Vec<_>>();
+    let ours_lines = ours.split_inclusive('\n').collect::<Vec<_>>();
+    let theirs_lines = theirs.split_inclusive('\n').collect::<Vec<_>>();
+
+    let ours_match = base_matches(&base_lines, &ours_lines);
+    let theirs_match = base_matches(&base_lines, &theirs_lines);
+
+    let mut hunks = Vec::<MergeHunk>::new();
+    let mut has_conflict = false;
+    let (mut io, mut ia, mut ib) = (0usize, 0usize, 0usize);
+    loop {
+        let mut stable = 0usize;
+        while io + stable < base_lines.len()
+            && ours_match[io + stable] == Some(ia + stable)
+            && theirs_match[io + stable] == Some(ib + stable)
+        {
+            stable += 1;
+        }
+        if stable > 0 {
+            push_resolved(&mut hunks, &base_lines[io..io + stable]);
+            io += stable;
+            ia += stable;
+            ib += stable;
+            continue;
+        }
+
+        let sync = (io..base_lines.len())
+            .find_map(|o| Some((o, ours_match[o]?, theirs_match[o]?)))
+            .unwrap_or((base_lines.len(), ours_lines.len(), theirs_lines.len()));
+        let (o_end, a_end, b_end) = sync;
+        if o_end == io && a_end == ia && b_end == ib {
+            break;
+        }
+
+        let base_chunk = &base_lines[io..o_end];
+        let ours_chunk = &ours_lines[ia..a_end];
+        let theirs_chunk = &theirs_lines[ib..b_end];
+        if ours_chunk == base_chunk || ours_chunk == theirs_chunk {
+            push_resolved(&mut hunks, theirs_chunk);
+        } else if theirs_chunk == base_chunk {
+            push_resolved(&mut hunks, ours_chunk);
+        } else {
+            has_conflict = true;
+            hunks.push(MergeHunk::Conflict {
+                base: base_chunk.concat(),
+                ours: ours_chunk.concat(),
+                theirs: theirs_chunk.concat(),
+            });
+        }
+        io = o_end;
+        ia = a_end;
+        ib = b_end;
+    }
+
+    if has_conflict {
+        return ThreeWayMerge::Conflicted(hunks);
+    }
+    let mut merged = String::new();
+    for hunk in hunks {
+        if let MergeHunk::Resolved(text) = hunk {
+            merged.push_str(&text);
+        }
+    }
+    ThreeWayMerge::Merged(merged)
+}
+
+/// For each base line, the index of the line it is matched to in `other`.
+fn base_matches(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut out = vec![None; base.len()];
+    for (op, base_ix, other_ix) in diff_slices(base, other) {
+        if op == LineDiffOp::Equal {
+            out[base_ix] = Some(other_ix);
+        }
+    }
+    out
+}
+
+fn push_resolved(hunks: &mut Vec<MergeHunk>, lines: &[&str]) {
+    if lines.is_empty() {
+        return;
+    }
+    if let Some(MergeHunk::Resolved(text)) = hunks.last_mut() {
+        text.push_str(&lines.concat());
+        return;
+    }
+    hunks.push(MergeHunk::Resolved(lines.concat()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lines_reports_inserts_and_deletes_with_line_numbers() {
+        let out = diff_lines("a\nb\nc\n", "a\nx\nc\nd\n");
+        let ops = out
+            .iter()
+            .map(|d| (d.op, d.old_line, d.new_line, d.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ops,
+            vec![
+                (LineDiffOp::Equal, Some(1), Some(1), "a"),
+                (LineDiffOp::Delete, Some(2), None, "b"),
+                (LineDiffOp::Insert, None, Some(2), "x"),
+                (LineDiffOp::Equal, Some(3), Some(3), "c"),
+                (LineDiffOp::Insert, None, Some(4), "d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_lines_of_identical_text_is_all_equal() {
+        let out = diff_lines("a\nb", "a\nb");
+        assert!(out.iter().all(|d| d.op == LineDiffOp::Equal));
+        assert_eq!(out.len(), 2);
+    }
+
+    #[test]
+    fn merge_three_way_combines_non_overlapping_edits() {
+        let base = "a\nb\nc\nd\n";
+        let ours = "a\nB\nc\nd\n";
+        let theirs = "a\nb\nc\nD\ne\n";
+        assert_eq!(
+            merge_three_way(base, ours, theirs),
+            ThreeWayMerge::Merged("a\nB\nc\nD\ne\n".to_string())
+        );
+    }
+
+    #[test]
+    fn merge_three_way_reports_overlapping_edits_as_conflict_hunks() {
+        let base = "title\nbody\nend\n";
+        let ours = "title\nours body\nend\n";
+        let theirs = "title\ntheirs body\nend\n";
+        assert_eq!(
+            merge_three_way(base, ours, theirs),
+            ThreeWayMerge::Conflicted(vec![
+                MergeHunk::Resolved("title\n".to_string()),
+                MergeHunk::Conflict {
+                    base: "body\n".to_string(),
+                    ours: "ours body\n".to_string(),
+                    theirs: "theirs body\n".to_string(),
+                },
+                MergeHunk::Resolved("end\n".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn merge_three_way_accepts_identical_changes_on_both_sides() {
+        let merged = merge_three_way("a\r\nb\r\n", "a\r\nc\r\n", "a\r\nc\r\n");
+        assert_eq!(merged, ThreeWayMerge::Merged("a\r\nc\r\n".to_string()));
+    }
+}
@@ -0,0 +1,1137 @@
+//! YAML frontmatter: parsing and in-place property edits.
+//!
+//! The parser covers the YAML that notes actually use: nested block maps, block and
+//! flow lists, flow maps, plain and quoted scalars, and `|` / `>` block scalars. Edits
+//! rewrite only the lines of the property they touch, so comments, key order and the
+//! note's line endings survive.
+
+use anyhow::{anyhow, Result};
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum YamlValue {
+    Null,
+    Bool(bool),
+    /// Numbers keep their source text so `007` or `1.50` survive a round trip.
+    Number(String),
+    String(String),
+    List(Vec<YamlValue>),
+    Map(Vec<(String, YamlValue)>),
+}
+
+impl YamlValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[YamlValue]> {
+        match self {
+            Self::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Value of `key` in a map, compared exactly and then ASCII case-insensitively.
+    pub fn get(&self, key: &str) -> Option<&YamlValue> {
+        let Self::Map(entries) = self else {
+            return None;
+        };
+        entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .or_else(|| entries.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)))
+            .map(|(_, v)| v)
+    }
+
+    /// Text of a scalar as written (`Null` is empty); `None` for lists and maps.
+    pub fn as_scalar_text(&self) -> Option<String> {
+        match self {
+            Self::Null => Some(String::new()),
+            Self::Bool(b) => Some(b.to_string()),
+            Self::Number(text) | Self::String(text) => Some(text.clone()),
+            Self::List(_) | Self::Map(_) => None,
+        }
+    }
+
+    /// Scalars and list items as strings: a scalar yields itself, a list its scalar items.
+    pub fn string_list(&self) -> Vec<String> {
+        match self {
+            Self::Null => Vec::new(),
+            Self::List(items) => items.iter().filter_map(Self::as_scalar_text).collect(),
+            Self::Map(_) => Vec::new(),
+            scalar => scalar.as_scalar_text().into_iter().collect(),
+        }
+    }
+
+    /// One-line rendering for search and display: `[a, b]` for lists, `{k: v}` for maps.
+    pub fn to_flat_string(&self) -> String {
+        match self {
+            Self::List(items) => format!(
+                "[{}]",
+                items
+                    .iter()
+                    .map(Self::to_flat_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::Map(entries) => format!(
+                "{{{}}}",
+                entries
+                    .iter()
+                    .map(|(k, v)| format!("{k}: {}", v.to_flat_string()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            scalar => scalar.as_scalar_text().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrontmatterBounds {
+    /// The YAML between the `---` fences.
+    pub yaml: Range<usize>,
+    /// First byte after the closing fence line.
+    pub end: usize,
+}
+
+/// Locates a `---` fenced block at the very start of `content`.
+pub fn frontmatter_bounds(content: &str) -> Option<FrontmatterBounds> {
+    let first_line_end = content.find('\n')?;
+    if content[..first_line_end].trim_end_matches('\r') != "---" {
+        return None;
+    }
+
+    let yaml_start = first_line_end + 1;
+    let mut cursor = yaml_start;
+    while cursor <= content.len() {
+        let line_end = content[cursor..]
+            .find('\n')
+            .map(|rel| cursor + rel)
+            .unwrap_or(content.len());
+        if content[cursor..line_end].trim_end_matches('\r') == "---" {
+            return Some(FrontmatterBounds {
+                yaml: yaml_start..cursor,
+                end: (line_end + 1).min(content.len()),
+            });
+        }
+        if line_end == content.len() {
+            break;
+        }
+        cursor = line_end + 1;
+    }
+    None
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrontmatterEntry {
+    pub key: String,
+    pub value: YamlValue,
+    /// Lines holding the property in the note, including the final line ending.
+    pub span: Range<usize>,
+    /// Trailing `# comment` of a one-line property, kept when the value is replaced.
+    comment: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frontmatter {
+    pub bounds: FrontmatterBounds,
+    /// Top-level properties in file order.
+    pub entries: Vec<FrontmatterEntry>,
+}
+
+impl Frontmatter {
+    /// Parses the frontmatter of `content`; `Ok(None)` when the note has none.
+    pub fn parse(content: &str) -> Result<Option<Self>> {
+        let Some(bounds) = frontmatter_bounds(content) else {
+            return Ok(None);
+        };
+        let mut parser = Parser::new(content, bounds.yaml.clone());
+        let entries = match parser.peek() {
+            None => Vec::new(),
+            Some(line) => {
+                let indent = line.indent;
+                let entries = parser.parse_mapping(indent)?;
+                if let Some(line) = parser.peek() {
+                    return Err(parser.error(line.number, "unexpected content after properties"));
+                }
+                entries
+            }
+        };
+        Ok(Some(Self { bounds, entries }))
+    }
+
+    /// Like [`Self::parse`], but frontmatter the parser rejects still yields each top-level
+    /// property that parses on its own, falling back to its first line alone; the rest are
+    /// skipped. Readers use this so one bad line does not hide a note's id or tags.
+    pub fn parse_lenient(content: &str) -> Option<Self> {
+        if let Ok(parsed) = Self::parse(content) {
+            return parsed;
+        }
+        let bounds = frontmatter_bounds(content)?;
+        let lines = Parser::new(content, bounds.yaml.clone()).lines;
+        let key_lines = lines
+            .iter()
+            .filter(|line| {
+                line.indent == 0
+                    && !line.body.starts_with('\t')
+                    && matches!(split_key(line.body), Ok(Some(_)))
+            })
+            .collect::<Vec<_>>();
+
+        let parse_one = |range: Range<usize>| {
+            let mut parser = Parser::new(content, range);
+            let mut entries = parser.parse_mapping(0).ok()?;
+            (parser.peek().is_none() && entries.len() == 1).then(|| entries.remove(0))
+        };
+        let entries = key_lines
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, line)| {
+                let chunk_end = key_lines
+                    .get(ix + 1)
+                    .map_or(bounds.yaml.end, |next| next.start);
+                parse_one(line.start..chunk_end).or_else(|| parse_one(line.start..line.end))
+            })
+            .collect();
+        Some(Self { bounds, entries })
+    }
+
+    pub fn entry(&self, key: &str) -> Option<&FrontmatterEntry> {
+        self.entries.iter().find(|e| e.key == key).or_else(|| {
+            self.entries
+                .iter()
+                .find(|e| e.key.eq_ignore_ascii_case(key))
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&YamlValue> {
+        self.entry(key).map(|e| &e.value)
+    }
+
+    /// All properties as one map value.
+    pub fn to_value(&self) -> YamlValue {
+        YamlValue::Map(
+            self.entries
+                .iter()
+                .map(|e| (e.key.clone(), e.value.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// Reads one top-level property.
+pub fn get_frontmatter_property(content: &str, key: &str) -> Result<Option<YamlValue>> {
+    Ok(Frontmatter::parse(content)?.and_then(|fm| fm.get(key).cloned()))
+}
+
+/// Sets a top-level property, replacing it in place or appending it to the frontmatter.
+///
+/// A note without frontmatter gets a new block. The existing key spelling and a trailing
+/// comment on a one-line property are kept.
+pub fn set_frontmatter_property(content: &str, key: &str, value: &YamlValue) -> Result<String> {
+    let newline = detect_line_ending(content);
+    let Some(frontmatter) = Frontmatter::parse(content)? else {
+        let entry = render_property(key, value, newline);
+        return Ok(format!("---{newline}{entry}---{newline}{content}"));
+    };
+
+    match frontmatter.entry(key) {
+        Some(entry) => {
+            let mut rendered = render_property(&entry.key, value, newline);
+            if let Some(comment) = entry.comment.as_deref() {
+                if rendered.matches(newline).count() == 1 {
+                    rendered.truncate(rendered.len() - newline.len());
+                    rendered.push_str(&format!(" {comment}{newline}"));
+                }
+            }
+            let mut out = String::with_capacity(content.len() + rendered.len());
+            out.push_str(&content[..entry.span.start]);
+            out.push_str(&rendered);
+            out.push_str(&content[entry.span.end..]);
+            Ok(out)
+        }
+        None => {
+            let at = frontmatter.bounds.yaml.end;
+            let rendered = render_property(key, value, newline);
+            let mut out = String::with_capacity(content.len() + rendered.len());
+            out.push_str(&content[..at]);
+            out.push_str(&rendered);
+            out.push_str(&content[at..]);
+            Ok(out)
+        }
+    }
+}
+
+/// Removes a top-level property; `Ok(None)` when it is not set.
+pub fn delete_frontmatter_property(content: &str, key: &str) -> Result<Option<String>> {
+    let Some(frontmatter) = Frontmatter::parse(content)? else {
+        return Ok(None);
+    };
+    let Some(entry) = frontmatter.entry(key) else {
+        return Ok(None);
+    };
+    let mut out = String::with_capacity(content.len());
+    out.push_str(&content[..entry.span.start]);
+    out.push_str(&content[entry.span.end..]);
+    Ok(Some(out))
+}
+
+pub fn detect_line_ending(content: &str) -> &'static str {
+    if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Line<'a> {
+    /// Absolute offset of the line start in the note.
+    start: usize,
+    /// Absolute offset just past the line ending.
+    end: usize,
+    /// 1-based line number in the note.
+    number: usize,
+    /// The line without its ending.
+    text: &'a str,
+    /// Column where `body` starts; list items move it past their `- `.
+    indent: usize,
+    body: &'a str,
+}
+
+struct Parser<'a> {
+    lines: Vec<Line<'a>>,
+    pos: usize,
+    /// End of the last line handed to a value, for entry spans.
+    consumed_end: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(content: &'a str, yaml: Range<usize>) -> Self {
+        let first_number = content[..yaml.start].matches('\n').count() + 1;
+        let mut lines = Vec::new();
+        let mut start = yaml.start;
+        for (ix, raw) in content[yaml.clone()].split_inclusive('\n').enumerate() {
+            let text = raw.trim_end_matches(['\n', '\r']);
+            let body = text.trim_start_matches(' ');
+            lines.push(Line {
+                start,
+                end: start + raw.len(),
+                number: first_number + ix,
+                text,
+                indent: text.len() - body.len(),
+                body,
+            });
+            start += raw.len();
+        }
+        Self {
+            lines,
+            pos: 0,
+            consumed_end: yaml.start,
+        }
+    }
+
+    fn error(&self, line: usize, message: &str) -> anyhow::Error {
+        anyhow!("frontmatter line {line}: {message}")
+    }
+
+    fn peek(&mut self) -> Option<Line<'a>> {
+        while let Some(line) = self.lines.get(self.pos) {
+            let body = line.body.trim();
+            if body.is_empty() || body.starts_with('#') {
+                self.pos += 1;
+            } else {
+                return Some(*line);
+            }
+        }
+        None
+    }
+
+    fn consume(&mut self) {
+        self.consumed_end = self.lines[self.pos].end;
+        self.pos += 1;
+    }
+
+    fn parse_mapping(&mut self, indent: usize) -> Result<Vec<FrontmatterEntry>> {
+        let mut entries = Vec::new();
+        while let Some(line) = self.peek() {
+            if line.indent < indent {
+                break;
+            }
+            if line.indent > indent {
+                return Err(self.error(line.number, "unexpected indentation"));
+            }
+            if line.body.starts_with('\t') {
+                return Err(self.error(line.number, "tabs are not allowed in indentation"));
+            }
+            let Some((key, rest)) =
+                split_key(line.body).map_err(|e| self.error(line.number, &e))?
+            else {
+                if is_sequence_item(line.body) && !entries.is_empty() {
+                    break;
+                }
+                return Err(self.error(line.number, "expected `key: value`"));
+            };
+            let (value, comment) = self.parse_value_after(rest, indent, true)?;
+            entries.push(FrontmatterEntry {
+                key,
+                value,
+                span: line.start..self.consumed_end,
+                comment,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn parse_sequence(&mut self, indent: usize) -> Result<YamlValue> {
+        let mut items = Vec::new();
+        while let Some(line) = self.peek() {
+            if line.indent != indent || !is_sequence_item(line.body) {
+                break;
+            }
+            let after_dash = &line.body[1..];
+            let rest = after_dash.trim_start_matches(' ');
+            if rest.is_empty() || rest.starts_with('#') {
+                self.consume();
+                items.push(self.parse_block(indent + 1)?);
+                continue;
+            }
+            // Re-read the item text as a node of its own, indented past the `- `.
+            let offset = 1 + after_dash.len() - rest.len();
+            let current = &mut self.lines[self.pos];
+            current.indent += offset;
+            current.body = rest;
+            items.push(self.parse_block(indent + 1)?);
+        }
+        Ok(YamlValue::List(items))
+    }
+
+    /// Parses the node starting at the next line, which must be indented at least `min_indent`.
+    fn parse_block(&mut self, min_indent: usize) -> Result<YamlValue> {
+        let Some(line) = self.peek() else {
+            return Ok(YamlValue::Null);
+        };
+        if line.indent < min_indent {
+            return Ok(YamlValue::Null);
+        }
+        if is_sequence_item(line.body) {
+            return self.parse_sequence(line.indent);
+        }
+        let is_key = split_key(line.body)
+            .map_err(|e| self.error(line.number, &e))?
+            .is_some();
+        if is_key {
+            let entries = self.parse_mapping(line.indent)?;
+            return Ok(YamlValue::Map(
+                entries.into_iter().map(|e| (e.key, e.value)).collect(),
+            ));
+        }
+        let (value, _) = self.parse_value_after(line.body, min_indent.saturating_sub(1), false)?;
+        Ok(value)
+    }
+
+    /// Parses the value text `rest` found on the current line, plus any continuation lines
+    /// indented deeper than `parent_indent`.
+    fn parse_value_after(
+        &mut self,
+        rest: &'a str,
+        parent_indent: usize,
+        allow_compact_sequence: bool,
+    ) -> Result<(YamlValue, Option<String>)> {
+        let line = self.lines[self.pos];
+        let rest = rest.trim();
+
+        if rest.is_empty() || rest.starts_with('#') {
+            self.consume();
+            let comment = (!rest.is_empty()).then(|| rest.to_string());
+            let value = match self.peek() {
+                Some(next) if next.indent > parent_indent => self.parse_block(parent_indent + 1)?,
+                Some(next)
+                    if allow_compact_sequence
+                        && next.indent == parent_indent
+                        && is_sequence_item(next.body) =>
+                {
+                    self.parse_sequence(parent_indent)?
+                }
+                _ => YamlValue::Null,
+            };
+            return Ok((value, comment));
+        }
+
+        if rest.starts_with('|') || rest.starts_with('>') {
+            let value = self.parse_block_scalar(rest, parent_indent)?;
+            return Ok((YamlValue::String(value), None));
+        }
+
+        if rest.starts_with('[') || rest.starts_with('{') || rest.starts_with(['"', '\'']) {
+            let mut text = rest.to_string();
+            self.consume();
+            loop {
+                let mut flow = Flow::new(&text);
+                match flow.node(false) {
+                    Ok(value) => {
+                        let tail = flow.remaining().trim();
+                        if !tail.is_empty() && !tail.starts_with('#') {
+                            return Err(self.error(line.number, "unexpected text after value"));
+                        }
+                        let comment = (!tail.is_empty()).then(|| tail.to_string());
+                        return Ok((value, comment));
+                    }
+                    Err(FlowError::Incomplete) => {
+                        let Some(next) = self.lines.get(self.pos).copied() else {
+                            return Err(self.error(line.number, "unterminated value"));
+                        };
+                        text.push(' ');
+                        text.push_str(next.text.trim());
+                        self.consume();
+                    }
+                    Err(FlowError::Invalid(message)) => {
+                        return Err(self.error(line.number, &message));
+                    }
+                }
+            }
+        }
+
+        let (plain, comment) = split_plain_comment(rest);
+        let mut text = plain.to_string();
+        self.consume();
+        if comment.is_none() {
+            while let Some(next) = self.lines.get(self.pos).copied() {
+                let body = next.body.trim();
+                if body.is_empty() || body.starts_with('#') || next.indent <= parent_indent {
+                    break;
+                }
+                if matches!(split_key(body), Ok(Some(_))) {
+                    return Err(self.error(next.number, "unexpected indentation"));
+                }
+                let (more, _) = split_plain_comment(body);
+                text.push(' ');
+                text.push_str(more);
+                self.consume();
+            }
+        }
+        Ok((resolve_plain(&text), comment.map(str::to_string)))
+    }
+
+    fn parse_block_scalar(&mut self, header: &str, parent_indent: usize) -> Result<String> {
+        let line = self.lines[self.pos];
+        let (header, _) = split_plain_comment(header);
+        let folded = header.starts_with('>');
+        let mut chomp = 'c';
+        let mut explicit_indent = None;
+        for ch in header[1..].chars() {
+            match ch {
+                '-' | '+' => chomp = ch,
+                '1'..='9' => explicit_indent = ch.to_digit(10).map(|d| parent_indent + d as usize),
+                _ => return Err(self.error(line.number, "invalid block scalar header")),
+            }
+        }
+        self.consume();
+
+        let mut raw_lines = Vec::new();
+        let mut last_content = self.pos;
+        let mut cursor = self.pos;
+        while let Some(next) = self.lines.get(cursor) {
+            if next.text.trim().is_empty() {
+                cursor += 1;
+                continue;
+            }
+            if next.indent <= parent_indent {
+                break;
+            }
+            cursor += 1;
+            last_content = cursor;
+        }
+        while self.pos < last_content {
+            raw_lines.push(self.lines[self.pos]);
+            self.consume();
+        }
+
+        let content_indent = explicit_indent.unwrap_or_else(|| {
+            raw_lines
+                .iter()
+                .find(|l| !l.text.trim().is_empty())
+                .map_or(parent_indent + 1, |l| l.indent)
+        });
+        let mut lines = Vec::with_capacity(raw_lines.len());
+        for l in &raw_lines {
+            if l.text.trim().is_empty() {
+                lines.push("");
+            } else if l.indent < content_indent {
+                return Err(self.error(l.number, "block scalar line is under-indented"));
+            } else {
+                lines.push(&l.text[content_indent..]);
+            }
+        }
+
+        let mut out = if folded {
+            let mut out = String::new();
+            let mut prev_blank = true;
+            for l in &lines {
+                if l.is_empty() {
+                    out.push('\n');
+                    prev_blank = true;
+                } else {
+                    if !prev_blank && !l.starts_with(' ') {
+                        out.push(' ');
+                    } else if !prev_blank {
+                        out.push('\n');
+                    }
+                    out.push_str(l);
+                    prev_blank = false;
+                }
+            }
+            out
+        } else {
+            lines.join("\n")
+        };
+        match chomp {
+            '-' => {}
+            _ if out.is_empty() => {}
+            _ => out.push('\n'),
+        }
+        Ok(out)
+    }
+}
+
+fn is_sequence_item(body: &str) -> bool {
+    body == "-" || body.starts_with("- ")
+}
+
+/// Splits `key: rest` off a line; `Ok(None)` when the line is not a mapping entry.
+fn split_key(body: &str) -> std::result::Result<Option<(String, &str)>, String> {
+    if body.starts_with(['"', '\'']) {
+        let mut flow = Flow::new(body);
+        let key = match flow.quoted() {
+            Ok(key) => key,
+            Err(FlowError::Incomplete) => return Err("unterminated quoted key".to_string()),
+            Err(FlowError::Invalid(message)) => return Err(message),
+        };
+        let after = flow.remaining().trim_start();
+        return Ok(after
+            .strip_prefix(':')
+            .filter(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+            .map(|rest| (key, rest)));
+    }
+    if is_sequence_item(body) || body.starts_with(['#', '[', '{']) {
+        return Ok(None);
+    }
+    let bytes = body.as_bytes();
+    for (ix, &b) in bytes.iter().enumerate() {
+        if b == b'#' && ix > 0 && matches!(bytes[ix - 1], b' ' | b'\t') {
+            return Ok(None);
+        }
+        if b == b':' && (ix + 1 == bytes.len() || matches!(bytes[ix + 1], b' ' | b'\t')) {
+            let key = body[..ix].trim_end();
+            if key.is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some((key.to_string(), &body[ix + 1..])));
+        }
+    }
+    Ok(None)
+}
+
+fn split_plain_comment(text: &str) -> (&str, Option<&str>) {
+    if text.starts_with('#') {
+        return ("", Some(text));
+    }
+    let bytes = text.as_bytes();
+    for ix in 1..bytes.len() {
+        if bytes[ix] == b'#' && matches!(bytes[ix - 1], b' ' | b'\t') {
+            return (text[..ix].trim_end(), Some(&text[ix..]));
+        }
+    }
+    (text.trim_end(), None)
+}
+
+fn resolve_plain(text: &str) -> YamlValue {
+    match text.trim() {
+        "" | "~" | "null" | "Null" | "NULL" => YamlValue::Null,
+        "true" | "True" | "TRUE" => YamlValue::Bool(true),
+        "false" | "False" | "FALSE" => YamlValue::Bool(false),
+        other if is_yaml_number(other) => YamlValue::Number(other.to_string()),
+        other => YamlValue::String(other.to_string()),
+    }
+}
+
+fn is_yaml_number(text: &str) -> bool {
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    if let Some(hex) = unsigned.strip_prefix("0x") {
+        return !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit());
+    }
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(ix) => (&unsigned[..ix], Some(&unsigned[ix + 1..])),
+        None => (unsigned, None),
+    };
+    let (int_part, frac_part) = match mantissa.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (mantissa, None),
+    };
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let has_digit = !int_part.is_empty() || frac_part.is_some_and(|f| !f.is_empty());
+    has_digit
+        && digits(int_part)
+        && frac_part.is_none_or(digits)
+        && exponent.is_none_or(|e| {
+            let e = e.strip_prefix(['-', '+']).unwrap_or(e);
+            !e.is_empty() && digits(e)
+        })
+}
+
+enum FlowError {
+    /// The text ended inside a quote or bracket; more lines may complete it.
+    Incomplete,
+    Invalid(String),
+}
+
+/// Parser for flow nodes (`[a, b]`, `{k: v}`) and quoted scalars on one logical line.
+struct Flow<'s> {
+    text: &'s str,
+    pos: usize,
+}
+
+impl<'s> Flow<'s> {
+    fn new(text: &'s str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn remaining(&self) -> &'s str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.remaining();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.remaining().chars().next()
+    }
+
+    fn node(&mut self, in_flow: bool) -> std::result::Result<YamlValue, FlowError> {
+        self.skip_ws();
+        match self.peek() {
+            None => Err(FlowError::Incomplete),
+            Some('[') => self.sequence(),
+            Some('{') => self.mapping(),
+            Some('"') | Some('\'') => self.quoted().map(YamlValue::String),
+            Some(_) if in_flow => Ok(resolve_plain(self.plain(&[',', ']', '}']))),
+            Some(_) => Err(FlowError::Invalid("expected a flow value".to_string())),
+        }
+    }
+
+    fn plain(&mut self, stops: &[char]) -> &'s str {
+        let rest = self.remaining();
+        let end = rest.find(|c| stops.contains(&c)).unwrap_or(rest.len());
+        self.pos += end;
+        rest[..end].trim()
+    }
+
+    fn sequence(&mut self) -> std::result::Result<YamlValue, FlowError> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => return Err(FlowError::Incomplete),
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(YamlValue::List(items));
+                }
+                _ => {}
+            }
+            items.push(self.node(true)?);
+            self.skip_ws();
+            match self.peek() {
+                None => return Err(FlowError::Incomplete),
+                Some(',') => self.pos += 1,
+                Some(']') => {}
+                Some(c) => return Err(FlowError::Invalid(format!("unexpected `{c}` in list"))),
+            }
+        }
+    }
+
+    fn mapping(&mut self) -> std::result::Result<YamlValue, FlowError> {
+        self.pos += 1;
+        let mut entries = Vec::new();
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => return Err(FlowError::Incomplete),
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(YamlValue::Map(entries));
+                }
+                _ => {}
+            }
+            let key = match self.peek() {
+                Some('"') | Some('\'') => self.quoted()?,
+                _ => self.plain(&[':', ',', '}']).to_string(),
+            };
+            self.skip_ws();
+            let value = if self.peek() == Some(':') {
+                self.pos += 1;
+                self.skip_ws();
+                match self.peek() {
+                    Some(',') | Some('}') => YamlValue::Null,
+                    _ => self.node(true)?,
+                }
+            } else {
+                YamlValue::Null
+            };
+            entries.push((key, value));
+            self.skip_ws();
+            match self.peek() {
+                None => return Err(FlowError::Incomplete),
+                Some(',') => self.pos += 1,
+                Some('}') => {}
+                Some(c) => return Err(FlowError::Invalid(format!("unexpected `{c}` in map"))),
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> std::result::Result<String, FlowError> {
+        let quote = self.peek().ok_or(FlowError::Incomplete)?;
+        self.pos += 1;
+        let mut out = String::new();
+        let mut chars = self.remaining().char_indices();
+        while let Some((ix, ch)) = chars.next() {
+            if ch == quote {
+                if quote == '\'' && self.remaining()[ix + 1..].starts_with('\'') {
+                    chars.next();
+                    out.push('\'');
+                    continue;
+                }
+                self.pos += ix + 1;
+                return Ok(out);
+            }
+            if ch == '\\' && quote == '"' {
+                let Some((_, esc)) = chars.next() else {
+                    return Err(FlowError::Incomplete);
+                };
+                match esc {
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    'r' => out.push('\r'),
+                    '0' => out.push('\0'),
+                    '"' | '\\' | '/' | ' ' => out.push(esc),
+                    'x' | 'u' | 'U' => {
+                        let len = match esc {
+                            'x' => 2,
+                            'u' => 4,
+                            _ => 8,
+                        };
+                        let hex = chars.by_ref().take(len).map(|(_, c)| c).collect::<String>();
+                        let decoded = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .filter(|_| hex.len() == len)
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| {
+                                FlowError::Invalid(format!("invalid escape \\{esc}{hex}"))
+                            })?;
+                        out.push(decoded);
+                    }
+                    other => {
+                        return Err(FlowError::Invalid(format!("invalid escape \\{other}")));
+                    }
+                }
+                continue;
+            }
+            out.push(ch);
+        }
+        Err(FlowError::Incomplete)
+    }
+}
+
+/// Renders `key: value` as the lines of one property, each ending with `newline`.
+fn render_property(key: &str, value: &YamlValue, newline: &str) -> String {
+    let mut lines = Vec::new();
+    write_entry(&mut lines, 0, &render_string(key), value);
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&line);
+        out.push_str(newline);
+    }
+    out
+}
+
+fn write_entry(out: &mut Vec<String>, indent: usize, key: &str, value: &YamlValue) {
+    let pad = " ".repeat(indent);
+    match value {
+        YamlValue::Null => out.push(format!("{pad}{key}:")),
+        YamlValue::List(items) if !items.is_empty() => {
+            out.push(format!("{pad}{key}:"));
+            write_list(out, indent + 2, items);
+        }
+        YamlValue::Map(entries) if !entries.is_empty() => {
+            out.push(format!("{pad}{key}:"));
+            for (k, v) in entries {
+                write_entry(out, indent + 2, &render_string(k), v);
+            }
+        }
+        YamlValue::String(s) if block_literal_safe(s) => {
+            let (header, body) = match s.strip_suffix('\n') {
+                Some(body) => ("|", body),
+                None => ("|-", s.as_str()),
+            };
+            out.push(format!("{pad}{key}: {header}"));
+            for line in body.split('\n') {
+                if line.is_empty() {
+                    out.push(String::new());
+                } else {
+                    out.push(format!("{pad}  {line}"));
+                }
+            }
+        }
+        scalar => out.push(format!("{pad}{key}: {}", render_scalar(scalar))),
+    }
+}
+
+fn write_list(out: &mut Vec<String>, indent: usize, items: &[YamlValue]) {
+    let pad = " ".repeat(indent);
+    for item in items {
+        match item {
+            YamlValue::Map(entries) if !entries.is_empty() => {
+                let mut nested = Vec::new();
+                for (k, v) in entries {
+                    write_entry(&mut nested, indent + 2, &render_string(k), v);
+                }
+                nested[0] = format!("{pad}- {}", &nested[0][indent + 2..]);
+                out.extend(nested);
+            }
+            YamlValue::List(inner) if !inner.is_empty() => {
+                out.push(format!("{pad}-"));
+                write_list(out, indent + 2, inner);
+            }
+            YamlValue::Null => out.push(format!("{pad}-")),
+            scalar => out.push(format!("{pad}- {}", render_scalar(scalar))),
+        }
+    }
+}
+
+fn render_scalar(value: &YamlValue) -> String {
+    match value {
+        YamlValue::Null => "null".to_string(),
+        YamlValue::Bool(b) => b.to_string(),
+        YamlValue::Number(text) if is_yaml_number(text) => text.clone(),
+        YamlValue::Number(text) | YamlValue::String(text) => render_string(text),
+        YamlValue::List(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(render_scalar)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        YamlValue::Map(entries) => format!(
+            "{{{}}}",
+            entries
+                .iter()
+                .map(|(k, v)| format!("{}: {}", render_string(k), render_scalar(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Plain when that reads back as the same string, double-quoted otherwise.
+fn render_string(text: &str) -> String {
+    let plain_safe = !text.is_empty()
+        && text.trim() == text
+        && !text.starts_with([
+            '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%',
+            '@', '`',
+        ])
+        && !text.contains(": ")
+        && !text.contains(" #")
+        && !text.ends_with(':')
+        && !text.chars().any(char::is_control)
+        && matches!(resolve_plain(text), YamlValue::String(_));
+    if plain_safe {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Multi-line strings the `|` / `|-` forms reproduce exactly.
+fn block_literal_safe(text: &str) -> bool {
+    text.contains('\n')
+        && !text.ends_with("\n\n")
+        && !text.starts_with([' ', '\n'])
+        && !text.contains('\r')
+        && !text.chars().any(|c| c.is_control() && c != '\n')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "---\n# leading comment\ntitle: \"Hello: world\"  # shown in lists\nid: 01HABC\ntags: [rust, 'it''s', \"a, b\"]\naliases:\n  - One\n  - Two # second\nauthor:\n  name: Ada\n  links:\n  - https://example.com\n  - {kind: site, rank: 2}\nsummary: |\n  line one\n\n  line three\nfolded: >-\n  a\n  b\nempty:\ncount: 007\nratio: -1.5e3\ndone: false\n---\n# Body\n";
+
+    #[test]
+    fn parse_reads_nested_and_flow_values() {
+        let fm = Frontmatter::parse(NOTE)
+            .expect("parse")
+            .expect("frontmatter");
+        let keys = fm
+            .entries
+            .iter()
+            .map(|e| e.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                "title", "id", "tags", "aliases", "author", "summary", "folded", "empty", "count",
+                "ratio", "done"
+            ]
+        );
+        assert_eq!(
+            fm.get("TITLE").and_then(YamlValue::as_str),
+            Some("Hello: world")
+        );
+        assert_eq!(
+            fm.get("tags")
+                .map(YamlValue::string_list)
+                .unwrap_or_default(),
+            vec!["rust", "it's", "a, b"]
+        );
+        assert_eq!(
+            fm.get("aliases"),
+            Some(&YamlValue::List(vec![
+                YamlValue::String("One".to_string()),
+                YamlValue::String("Two".to_string()),
+            ]))
+        );
+        let author = fm.get("author").expect("author");
+        assert_eq!(author.get("name").and_then(YamlValue::as_str), Some("Ada"));
+        let links = author
+            .get("links")
+            .and_then(YamlValue::as_list)
+            .expect("links");
+        assert_eq!(links[0].as_str(), Some("https://example.com"));
+        assert_eq!(links[1].get("rank").and_then(YamlValue::as_f64), Some(2.0));
+        assert_eq!(
+            fm.get("summary").and_then(YamlValue::as_str),
+            Some("line one\n\nline three\n")
+        );
+        assert_eq!(fm.get("folded").and_then(YamlValue::as_str), Some("a b"));
+        assert_eq!(fm.get("empty"), Some(&YamlValue::Null));
+        assert_eq!(fm.get("count"), Some(&YamlValue::Number("007".to_string())));
+        assert_eq!(fm.get("ratio").and_then(YamlValue::as_f64), Some(-1500.0));
+        assert_eq!(fm.get("done").and_then(YamlValue::as_bool), Some(false));
+        assert_eq!(fm.get("id").and_then(YamlValue::as_str), Some("01HABC"));
+
+        assert!(Frontmatter::parse("# no frontmatter\n")
+            .expect("parse")
+            .is_none());
+        assert!(Frontmatter::parse("---\ntags: [a, b\n---\n").is_err());
+        assert!(Frontmatter::parse("---\na: 1\n   b: 2\n---\n").is_err());
+    }
+
+    #[test]
+    fn set_and_delete_preserve_comments_order_and_line_endings() {
+        let retitled = set_frontmatter_property(NOTE, "Title", &YamlValue::String("New".into()))
+            .expect("set title");
+        assert!(retitled.contains("\ntitle: New # shown in lists\nid: 01HABC\n"));
+        assert!(retitled.starts_with("---\n# leading comment\n"));
+
+        let relisted = set_frontmatter_property(
+            NOTE,
+            "aliases",
+            &YamlValue::List(vec![YamlValue::String("Solo: one".into())]),
+        )
+        .expect("set aliases");
+        assert!(relisted.contains("aliases:\n  - \"Solo: one\"\nauthor:\n"));
+
+        let appended = set_frontmatter_property(NOTE, "status", &YamlValue::String("draft".into()))
+            .expect("append");
+        assert!(appended.ends_with("done: false\nstatus: draft\n---\n# Body\n"));
+
+        let deleted = delete_frontmatter_property(NOTE, "author")
+            .expect("delete")
+            .expect("present");
+        assert!(deleted.contains("  - Two # second\nsummary: |\n"));
+        let reparsed = Frontmatter::parse(&deleted).expect("parse").expect("fm");
+        assert!(reparsed.get("author").is_none());
+        assert_eq!(reparsed.entries.len(), 10);
+        assert!(delete_frontmatter_property(NOTE, "missing")
+            .expect("delete")
+            .is_none());
+
+        let crlf = "---\r\ntitle: A\r\n---\r\nbody\r\n";
+        let out = set_frontmatter_property(crlf, "note", &YamlValue::String("x\ny".into()))
+            .expect("set crlf");
+        assert_eq!(
+            out,
+            "---\r\ntitle: A\r\nnote: |-\r\n  x\r\n  y\r\n---\r\nbody\r\n"
+        );
+        let fresh =
+            set_frontmatter_property("body\n", "done", &YamlValue::Bool(true)).expect("new");
+        assert_eq!(fresh, "---\ndone: true\n---\nbody\n");
+    }
+
+    #[test]
+    fn rendered_values_parse_back_unchanged() {
+        let value = YamlValue::Map(vec![
+            ("plain".to_string(), YamlValue::String("text".into())),
+            (
+                "looks_numeric".to_string(),
+                YamlValue::String("0012".into()),
+            ),
+            ("looks_bool".to_string(), YamlValue::String("true".into())),
+            (
+                "quoted".to_string(),
+                YamlValue::String("say \"hi\" # now".into()),
+            ),
+            ("multi".to_string(), YamlValue::String("a\n  b\n".into())),
+            (
+                "items".to_string(),
+                YamlValue::List(vec![
+                    YamlValue::Number("3".into()),
+                    YamlValue::Map(vec![
+                        ("x".to_string(), YamlValue::Null),
+                        ("y".to_string(), YamlValue::List(Vec::new())),
+                    ]),
+                    YamlValue::List(vec![YamlValue::Bool(false)]),
+                ]),
+            ),
+        ]);
+        let out = set_frontmatter_property("", "data", &value).expect("render");
+        let fm = Frontmatter::parse(&out).expect("parse").expect("fm");
+        assert_eq!(fm.get("data"), Some(&value));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::resource_meta::has_url_scheme;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const INFO_META_VERSION_V1: u32 = 1;
+static INFO_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum InfoKind {
+    #[default]
+    Snippet,
+    Bookmark,
+    Quote,
+}
+
+impl InfoKind {
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::Snippet => "snippet",
+            Self::Bookmark => "bookmark",
+            Self::Quote => "quote",
+        }
+    }
+}
+
+/// Where a piece of information was captured from.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct InfoSource {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct InfoMetaV1 {
+    pub version: u32,
+    pub id: String,
+    #[serde(default)]
+    pub kind: InfoKind,
+    #[serde(default)]
+    pub source: InfoSource,
+    #[serde(rename = "capturedAtMs", default)]
+    pub captured_at_ms: u64,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(rename = "updatedAt", default)]
+    pub updated_at: String,
+    #[serde(default)]
+    pub ext: Map<String, Value>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl InfoMetaV1 {
+    pub fn new(info_id: impl Into<String>, kind: InfoKind) -> Result<Self> {
+        let id = normalize_info_id(&info_id.into())?;
+        Ok(Self {
+            version: INFO_META_VERSION_V1,
+            id,
+            kind,
+            source: InfoSource::default(),
+            captured_at_ms: 0,
+            body: String::new(),
+            tags: Vec::new(),
+            updated_at: String::new(),
+            ext: Map::new(),
+            extra: Map::new(),
+        })
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.version != INFO_META_VERSION_V1 {
+            return Err(anyhow!("unsupported info meta version: {}", self.version));
+        }
+
+        normalize_info_id(&self.id)?;
+
+        if let Some(url) = self.source.url.as_deref() {
+            if !has_url_scheme(url) {
+                return Err(anyhow!("info source url has no scheme: {url}"));
+            }
+        }
+
+        match self.kind {
+            InfoKind::Bookmark if self.source.url.is_none() => {
+                return Err(anyhow!("bookmark info {} has no source url", self.id));
+            }
+            InfoKind::Snippet | InfoKind::Quote if self.body.trim().is_empty() => {
+                return Err(anyhow!(
+                    "{} info {} has an empty body",
+                    self.kind.as_tag(),
+                    self.id
+                ));
+            }
+            _ => {}
+        }
+
+        if self.tags.iter().any(|tag| tag.trim().is_empty()) {
+            return Err(anyhow!("info meta tag is empty"));
+        }
+
+        Ok(())
+    }
+
+    pub fn canonical_json(&self) -> Result<String> {
+        self.validate()?;
+        let mut out = serde_json::to_string_pretty(self)?;
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Short label for lists: the source title, else the first body line, else the URL.
+    pub fn display_title(&self) -> &str {
+        if let Some(title) = self
+            .source
+            .title
+            .as_deref()
+            .filter(|t| !t.trim().is_empty())
+        {
+            return title.trim();
+        }
+        if let Some(line) = self.body.lines().map(str::trim).find(|l| !l.is_empty()) {
+            return line;
+        }
+        self.source.url.as_deref().unwrap_or(&self.id)
+    }
+}
+
+pub fn normalize_info_id(info_id: &str) -> Result<String> {
+    let id = info_id.trim();
+    if id.is_empty() {
+        return Err(anyhow!("info id cannot be empty"));
+    }
+
+    if id
+        .chars()
+        .any(|ch| !(ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'))
+    {
+        return Err(anyhow!(
+            "info id must contain only [A-Za-z0-9_-], got: {id}"
+        ));
+    }
+
+    Ok(id.to_string())
+}
+
+pub fn generate_info_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default();
+    let pid = std::process::id() as u64;
+    let seq = INFO_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("F{millis:011X}{pid:05X}{seq:04X}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_meta_roundtrip_keeps_unknown_fields() {
+        let mut info = InfoMetaV1::new("F1", InfoKind::Quote).expect("new");
+        info.body = "Simple is better than complex.".to_string();
+        info.source.url = Some("https://peps.python.org/pep-0020/".to_string());
+        info.source.author = Some("Tim Peters".to_string());
+        info.captured_at_ms = 1_700_000_000_000;
+        info.tags.push("zen".to_string());
+
+        let mut value = serde_json::to_value(&info).expect("to value");
+        value["source"]["archived"] = Value::from(true);
+        value["futureField"] = Value::from("x");
+        let parsed: InfoMetaV1 = serde_json::from_value(value).expect("parse");
+        assert_eq!(
+            parsed.source.extra.get("archived"),
+            Some(&Value::from(true))
+        );
+
+        let json = parsed.canonical_json().expect("json");
+        assert!(json.contains("\"kind\": \"quote\""));
+        assert!(json.contains("\"capturedAtMs\": 1700000000000"));
+        assert!(json.contains("\"futureField\": \"x\""));
+        let reparsed: InfoMetaV1 = serde_json::from_str(&json).expect("reparse");
+        assert_eq!(reparsed, parsed);
+        assert_eq!(reparsed.display_title(), "Simple is better than complex.");
+    }
+
+    #[test]
+    fn info_meta_validation_depends_on_kind() {
+        let snippet = InfoMetaV1::new("F1", InfoKind::Snippet).expect("new");
+        assert!(snippet.validate().is_err());
+
+        let mut bookmark = InfoMetaV1::new("F2", InfoKind::Bookmark).expect("new");
+        assert!(bookmark.validate().is_err());
+        bookmark.source.url = Some("example.com".to_string());
+        assert!(bookmark.validate().is_err());
+        bookmark.source.url = Some("https://example.com".to_string());
+        assert!(bookmark.validate().is_ok());
+        assert_eq!(bookmark.display_title(), "https://example.com");
+
+        assert!(InfoMetaV1::new("../F3", InfoKind::Quote).is_err());
+    }
+}
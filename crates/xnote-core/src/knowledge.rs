@@ -1,16 +1,47 @@
+use crate::frontmatter::{Frontmatter, YamlValue};
+use crate::info_meta::{InfoKind, InfoMetaV1};
 use crate::note_meta::normalize_note_id;
 use crate::paths::normalize_vault_rel_path;
-use crate::vault::{NoteEntry, Vault};
+use crate::properties::{
+    typed_properties, PropertyComparison, PropertyExpr, PropertyOp, PropertyQuery, PropertyTypes,
+    PropertyValue,
+};
+use crate::search_query::SearchQueryError;
+use crate::vault::{NoteEntry, Vault, VaultMoveChangeSet};
 use anyhow::Result;
-use std::collections::{HashMap, HashSet};
+use bm25::{bm25f_score, CorpusStats, FieldFreqs};
+use cache::{read_note_stamped, NoteStamp};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
 use std::time::Instant;
 
+mod bm25;
+mod cache;
+mod query;
+mod tokenizer;
+
+pub use bm25::{Bm25Params, FieldWeight, SearchField, SEARCH_FIELD_COUNT};
+pub use cache::{IndexCacheStats, IndexCacheStatus, KNOWLEDGE_CACHE_VERSION};
+pub use query::literal_pattern;
+pub use tokenizer::{CjkSegmentation, TextTokenizer, Tokenizer};
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SearchOptions {
     pub max_files_with_matches: usize,
     pub max_match_rows: usize,
     pub max_preview_matches_per_file: usize,
     pub max_matches_to_count_per_file: usize,
+    /// Treat the query as a regular expression over note lines instead of the search
+    /// query language.
+    pub regex: bool,
+    /// Match letter case exactly; otherwise case is ignored.
+    pub case_sensitive: bool,
+    /// Only match words and phrases that are not part of a longer word.
+    pub whole_word: bool,
+    /// Longer preview lines are cut to this many characters around the first match;
+    /// `0` shows whole lines.
+    pub max_preview_chars: usize,
 }
 
 impl Default for SearchOptions {
@@ -20,6 +51,10 @@ impl Default for SearchOptions {
             max_match_rows: 200,
             max_preview_matches_per_file: 3,
             max_matches_to_count_per_file: 50,
+            regex: false,
+            case_sensitive: false,
+            whole_word: false,
+            max_preview_chars: 160,
         }
     }
 }
@@ -27,28 +62,63 @@ impl Default for SearchOptions {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SearchPreviewMatch {
     pub line: usize,
+    /// The line without surrounding whitespace. A long line is windowed around its first
+    /// match, with [`PREVIEW_ELLIPSIS`] marking the text cut on either side.
     pub preview: String,
+    /// Byte range of the line shown in `preview`, ellipses aside.
+    pub line_range: Range<usize>,
+    /// Matches within the shown part of the line, in order.
+    pub spans: Vec<SearchMatchSpan>,
 }
 
+/// One match in a [`SearchPreviewMatch`]; a match running past the preview window is
+/// clipped to it.
 #[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchMatchSpan {
+    /// Byte range within `preview`.
+    pub bytes: Range<usize>,
+    /// The same range in UTF-16 code units, as text layout counts them.
+    pub utf16: Range<usize>,
+    /// Byte range within the untrimmed line.
+    pub line_bytes: Range<usize>,
+}
+
+pub const PREVIEW_ELLIPSIS: &str = "\u{2026}";
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct SearchHit {
     pub path: String,
+    /// BM25F relevance; hits are ordered by it, highest first.
+    pub score: f64,
     pub match_count: usize,
     pub previews: Vec<SearchPreviewMatch>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SearchOutcome {
     pub query: String,
     pub elapsed_ms: u128,
     pub hits: Vec<SearchHit>,
+    /// Why the query could not be parsed; there are no hits then.
+    pub error: Option<SearchQueryError>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InfoSearchHit {
+    pub id: String,
+    pub kind: InfoKind,
+    pub title: String,
+    /// First body line containing the query, else the first body line.
+    pub preview: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct NoteMetadata {
     pub note_id: Option<String>,
     pub title: String,
     pub frontmatter: HashMap<String, String>,
+    /// Typed frontmatter properties by lowercased key.
+    pub properties: BTreeMap<String, PropertyValue>,
     pub aliases: Vec<String>,
     pub links: Vec<String>,
     pub tags: Vec<String>,
@@ -78,14 +148,107 @@ struct IndexedNote {
     tags_lower: Vec<String>,
     links: Vec<String>,
     links_lower: Vec<String>,
+    properties: BTreeMap<String, PropertyValue>,
+    /// Search terms with their frequency in each field.
+    terms: HashMap<String, FieldFreqs>,
+    /// Number of terms in each field.
+    field_lens: [u32; SEARCH_FIELD_COUNT],
+    stamp: NoteStamp,
+}
+
+/// The parsed, original-case fields of an indexed note.
+struct NoteFields {
+    note_id: Option<String>,
+    title: String,
+    aliases: Vec<String>,
+    tags: Vec<String>,
+    links: Vec<String>,
+    properties: BTreeMap<String, PropertyValue>,
+}
+
+impl IndexedNote {
+    fn new(
+        path: String,
+        fields: NoteFields,
+        terms: HashMap<String, FieldFreqs>,
+        stamp: NoteStamp,
+    ) -> Self {
+        let mut field_lens = [0u32; SEARCH_FIELD_COUNT];
+        for freqs in terms.values() {
+            for (len, tf) in field_lens.iter_mut().zip(freqs) {
+                *len += tf;
+            }
+        }
+        let lower_all = |values: &[String]| values.iter().map(|s| s.to_lowercase()).collect();
+        Self {
+            path_lower: path.to_lowercase(),
+            path,
+            note_id_lower: fields.note_id.as_ref().map(|value| value.to_lowercase()),
+            note_id: fields.note_id,
+            title_lower: fields.title.to_lowercase(),
+            title: fields.title,
+            aliases_lower: lower_all(&fields.aliases),
+            aliases: fields.aliases,
+            tags_lower: lower_all(&fields.tags),
+            tags: fields.tags,
+            links_lower: lower_all(&fields.links),
+            links: fields.links,
+            properties: fields.properties,
+            terms,
+            field_lens,
+            stamp,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct IndexedInfo {
+    id: String,
+    kind: InfoKind,
+    title: String,
+    title_lower: String,
+    body: String,
+    body_lower: String,
+    url_lower: String,
+    tags_lower: Vec<String>,
     token_set: HashSet<String>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct KnowledgeIndex {
     notes: HashMap<String, IndexedNote>,
     inverted: HashMap<String, HashSet<String>>,
     note_id_to_path: HashMap<String, String>,
+    infos: HashMap<String, IndexedInfo>,
+    info_inverted: HashMap<String, HashSet<String>>,
+    property_types: PropertyTypes,
+    /// Property key to the paths of notes that have it.
+    property_paths: HashMap<String, HashSet<String>>,
+    /// Sum of every note's field lengths and the number of notes where each field is
+    /// non-empty, for average field lengths.
+    field_len_totals: [u64; SEARCH_FIELD_COUNT],
+    field_doc_counts: [usize; SEARCH_FIELD_COUNT],
+    bm25: Bm25Params,
+    /// Splits note, info and query text into terms.
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+impl Default for KnowledgeIndex {
+    fn default() -> Self {
+        Self {
+            notes: HashMap::new(),
+            inverted: HashMap::new(),
+            note_id_to_path: HashMap::new(),
+            infos: HashMap::new(),
+            info_inverted: HashMap::new(),
+            property_types: PropertyTypes::new(),
+            property_paths: HashMap::new(),
+            field_len_totals: [0; SEARCH_FIELD_COUNT],
+            field_doc_counts: [0; SEARCH_FIELD_COUNT],
+            bm25: Bm25Params::default(),
+            tokenizer: Arc::new(TextTokenizer::default()),
+        }
+    }
 }
 
 impl KnowledgeIndex {
@@ -116,6 +279,13 @@ impl KnowledgeIndex {
         })
     }
 
+    /// Path of the note whose frontmatter `id:` is `note_id`, compared case-insensitively.
+    pub fn path_for_note_id(&self, note_id: &str) -> Option<String> {
+        self.note_id_to_path
+            .get(&note_id.trim().to_lowercase())
+            .cloned()
+    }
+
     pub fn resolve_link_target(&self, raw_link: &str) -> Option<String> {
         let query = normalize_note_link_target(raw_link)?;
         if query.is_empty() {
@@ -221,11 +391,25 @@ impl KnowledgeIndex {
         out
     }
 
+    /// An empty index using the vault's declared property types and search tokenizer.
+    fn empty_for_vault(vault: &Vault) -> Self {
+        let search = vault.search_settings().unwrap_or_default();
+        Self {
+            property_types: vault
+                .property_settings()
+                .map(|settings| settings.normalized_types())
+                .unwrap_or_default(),
+            tokenizer: Arc::new(search.tokenizer()),
+            ..Self::default()
+        }
+    }
+
     pub fn build_from_entries(vault: &Vault, entries: &[NoteEntry]) -> Result<Self> {
-        let mut index = Self::default();
+        let mut index = Self::empty_for_vault(vault);
         for entry in entries {
             let _ = index.upsert_note(vault, &entry.path);
         }
+        let _ = index.reload_infos(vault);
         Ok(index)
     }
 
@@ -243,11 +427,23 @@ impl KnowledgeIndex {
             if let Some(note_id) = existing.note_id_lower {
                 self.note_id_to_path.remove(&note_id);
             }
-            for token in existing.token_set {
-                if let Some(paths) = self.inverted.get_mut(&token) {
+            for token in existing.terms.keys() {
+                if let Some(paths) = self.inverted.get_mut(token) {
                     paths.remove(&path);
                     if paths.is_empty() {
-                        self.inverted.remove(&token);
+                        self.inverted.remove(token);
+                    }
+                }
+            }
+            for (ix, len) in existing.field_lens.into_iter().enumerate() {
+                self.field_len_totals[ix] -= u64::from(len);
+                self.field_doc_counts[ix] -= usize::from(len > 0);
+            }
+            for key in existing.properties.keys() {
+                if let Some(paths) = self.property_paths.get_mut(key) {
+                    paths.remove(&path);
+                    if paths.is_empty() {
+                        self.property_paths.remove(key);
                     }
                 }
             }
@@ -256,126 +452,198 @@ impl KnowledgeIndex {
 
     pub fn upsert_note(&mut self, vault: &Vault, note_path: &str) -> Result<()> {
         let path = normalize_vault_rel_path(note_path)?;
-        let content = vault.read_note(&path)?;
+        let (content, stamp) = read_note_stamped(vault, &path)?;
         self.remove_note(&path);
+        let note = self.parse_indexed_note(path, &content, stamp);
+        self.insert_indexed(note);
+        Ok(())
+    }
 
-        let metadata = parse_note_metadata(&content, &path);
-        let path_lower = path.to_lowercase();
-        let note_id_lower = metadata.note_id.as_ref().map(|value| value.to_lowercase());
-        let title_lower = metadata.title.to_lowercase();
-        let aliases_lower = metadata
-            .aliases
-            .iter()
-            .map(|s| s.to_lowercase())
-            .collect::<Vec<_>>();
-        let tags_lower = metadata
-            .tags
-            .iter()
-            .map(|s| s.to_lowercase())
-            .collect::<Vec<_>>();
-        let links_lower = metadata
-            .links
-            .iter()
-            .map(|s| s.to_lowercase())
-            .collect::<Vec<_>>();
-
-        let mut token_set = HashSet::new();
-        token_set.extend(tokenize(&path_lower));
-        if let Some(note_id) = note_id_lower.as_ref() {
-            token_set.extend(tokenize(note_id));
-        }
-        token_set.extend(tokenize(&title_lower));
-        for alias in &aliases_lower {
-            token_set.extend(tokenize(alias));
-        }
-        for tag in &tags_lower {
-            token_set.extend(tokenize(tag));
-        }
-        for link in &links_lower {
-            token_set.extend(tokenize(link));
+    fn parse_indexed_note(&self, path: String, content: &str, stamp: NoteStamp) -> IndexedNote {
+        let metadata = parse_note_metadata_with_types(content, &path, &self.property_types);
+        let fields = NoteFields {
+            note_id: metadata.note_id,
+            title: metadata.title,
+            aliases: metadata.aliases,
+            tags: metadata.tags,
+            links: metadata.links,
+            properties: metadata.properties,
+        };
+        let mut terms: HashMap<String, FieldFreqs> = HashMap::new();
+        let mut add = |field: SearchField, text: &str| {
+            for token in self.tokenizer.tokenize(text) {
+                terms.entry(token).or_default()[field.index()] += 1;
+            }
+        };
+        add(SearchField::Path, &path);
+        add(SearchField::Title, &fields.title);
+        for alias in &fields.aliases {
+            add(SearchField::Aliases, alias);
         }
-        for value in metadata.frontmatter.values() {
-            token_set.extend(tokenize(&value.to_lowercase()));
+        for tag in &fields.tags {
+            add(SearchField::Tags, tag);
         }
         for line in content.lines() {
-            token_set.extend(tokenize(&line.to_lowercase()));
+            add(SearchField::Body, line);
         }
+        IndexedNote::new(path, fields, terms, stamp)
+    }
 
-        for token in &token_set {
+    /// Adds a note to the lookup maps; any previous entry for its path must be removed first.
+    fn insert_indexed(&mut self, note: IndexedNote) {
+        let path = note.path.clone();
+        for (ix, len) in note.field_lens.into_iter().enumerate() {
+            self.field_len_totals[ix] += u64::from(len);
+            self.field_doc_counts[ix] += usize::from(len > 0);
+        }
+        for token in note.terms.keys() {
             self.inverted
                 .entry(token.clone())
                 .or_default()
                 .insert(path.clone());
         }
+        for key in note.properties.keys() {
+            self.property_paths
+                .entry(key.clone())
+                .or_default()
+                .insert(path.clone());
+        }
+        if let Some(note_id) = note.note_id_lower.clone() {
+            self.note_id_to_path.insert(note_id, path.clone());
+        }
+        self.notes.insert(path, note);
+    }
 
-        self.notes.insert(
-            path.clone(),
-            IndexedNote {
-                path: path.clone(),
-                path_lower,
-                note_id: metadata.note_id.clone(),
-                note_id_lower: note_id_lower.clone(),
-                title: metadata.title.clone(),
-                title_lower,
-                aliases: metadata.aliases.clone(),
-                aliases_lower,
-                tags: metadata.tags.clone(),
-                tags_lower,
-                links: metadata.links.clone(),
-                links_lower,
-                token_set,
-            },
-        );
+    /// Typed properties of a note, keyed by lowercased property name.
+    pub fn note_properties(&self, note_path: &str) -> Option<&BTreeMap<String, PropertyValue>> {
+        let path = normalize_vault_rel_path(note_path).ok()?;
+        self.notes.get(&path).map(|note| &note.properties)
+    }
+
+    /// Every property key in the vault with the number of notes that set it.
+    pub fn property_keys(&self) -> Vec<(String, usize)> {
+        let mut out = self
+            .property_paths
+            .iter()
+            .map(|(key, paths)| (key.clone(), paths.len()))
+            .collect::<Vec<_>>();
+        out.sort();
+        out
+    }
 
-        if let Some(note_id) = note_id_lower {
-            self.note_id_to_path.insert(note_id, path);
+    /// Replaces the declared property types and re-reads every note with them.
+    pub fn set_property_types(&mut self, vault: &Vault, types: PropertyTypes) {
+        self.property_types = types;
+        for path in self.all_paths_sorted() {
+            let _ = self.upsert_note(vault, &path);
         }
+    }
 
-        Ok(())
+    /// Paths of the notes matching `query`, in its sort order and then by path.
+    pub fn query_properties(&self, query: &PropertyQuery) -> Vec<String> {
+        let candidates: Box<dyn Iterator<Item = &IndexedNote>> =
+            match query.filter.as_ref().and_then(required_property_key) {
+                Some(key) => Box::new(
+                    self.property_paths
+                        .get(key)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|path| self.notes.get(path)),
+                ),
+                None => Box::new(self.notes.values()),
+            };
+        let mut matched = candidates
+            .filter(|note| query.matches(&note.properties))
+            .collect::<Vec<_>>();
+        matched.sort_by(|a, b| {
+            query
+                .cmp_properties(&a.properties, &b.properties)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        matched.into_iter().map(|note| note.path.clone()).collect()
+    }
+
+    /// Brings the index in line with a vault move that has already been applied.
+    pub fn apply_move_change_set(&mut self, vault: &Vault, change_set: &VaultMoveChangeSet) {
+        for (from, _) in &change_set.moved_notes {
+            self.remove_note(from);
+        }
+        for path in change_set.touched_note_paths() {
+            let _ = self.upsert_note(vault, &path);
+        }
     }
 
+    /// Runs a query in the [`crate::search_query`] language, or a regular expression
+    /// with [`SearchOptions::regex`]. An invalid query yields no hits and the error.
     pub fn search(&self, vault: &Vault, query: &str, options: SearchOptions) -> SearchOutcome {
         let started_at = Instant::now();
         let query = query.trim();
-        if query.is_empty() {
-            return SearchOutcome {
-                query: String::new(),
-                elapsed_ms: 0,
-                hits: Vec::new(),
-            };
-        }
+        let plan = match self.plan_search(query, &options) {
+            Ok(Some(plan)) if !query.is_empty() => plan,
+            Ok(_) => {
+                return SearchOutcome {
+                    query: String::new(),
+                    elapsed_ms: 0,
+                    hits: Vec::new(),
+                    error: None,
+                }
+            }
+            Err(err) => {
+                return SearchOutcome {
+                    query: query.to_string(),
+                    elapsed_ms: started_at.elapsed().as_millis(),
+                    hits: Vec::new(),
+                    error: Some(err),
+                }
+            }
+        };
 
-        let query_lower = query.to_lowercase();
-        let query_tokens = tokenize(&query_lower);
-        let candidate_paths = self.collect_candidates(&query_lower, &query_tokens);
+        let compiled = &plan.compiled;
+        let candidate_paths = compiled
+            .candidates()
+            .unwrap_or_else(|| self.notes.keys().cloned().collect());
+        let needs_content = compiled.needs_content();
+        let stats = self.corpus_stats();
+        let doc_freq = |term: &str| self.inverted.get(term).map_or(0, HashSet::len);
 
         let mut ranked = candidate_paths
             .into_iter()
             .filter_map(|path| {
-                self.notes.get(&path).map(|note| {
-                    (
-                        score_note_for_query(note, &query_lower, &query_tokens),
-                        note.path.clone(),
-                    )
-                })
+                let note = self.notes.get(&path)?;
+                let content = if needs_content {
+                    Some(vault.read_note(&path).ok()?)
+                } else {
+                    None
+                };
+                if !compiled.matches(&path, content.as_deref()) {
+                    return None;
+                }
+                let score = bm25f_score(&self.bm25, &stats, note, &plan.tokens, doc_freq);
+                let fallback = score_note_for_query(note, &plan.fallback_text, &plan.tokens);
+                Some((score, fallback, path, content))
             })
-            .filter(|(score, _)| *score > 0)
             .collect::<Vec<_>>();
 
-        ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        // Queries without word characters have no BM25 terms; the path/title heuristic
+        // ranks those and breaks ties.
+        ranked.sort_by(|a, b| {
+            b.0.total_cmp(&a.0)
+                .then_with(|| b.1.cmp(&a.1))
+                .then_with(|| a.2.cmp(&b.2))
+        });
 
         let mut hits = Vec::new();
         let mut files_with_matches = 0usize;
         let mut rows = 0usize;
 
-        for (_, path) in ranked {
+        for (score, _, path, content) in ranked {
             if files_with_matches >= options.max_files_with_matches
                 || rows >= options.max_match_rows
             {
                 break;
             }
 
-            let content = match vault.read_note(&path) {
+            let content = match content.map_or_else(|| vault.read_note(&path), Ok) {
                 Ok(s) => s,
                 Err(_) => continue,
             };
@@ -387,7 +655,14 @@ impl KnowledgeIndex {
                 if match_count >= options.max_matches_to_count_per_file {
                     break;
                 }
-                if !line.to_lowercase().contains(&query_lower) {
+                let highlighted = plan
+                    .highlight
+                    .as_ref()
+                    .is_some_and(|highlight| highlight.find_iter(line).any(|m| !m.is_empty()));
+                if !highlighted
+                    && (plan.strict_lines
+                        || !line_matches_text(line, &plan.needles, &plan.tokens, &*self.tokenizer))
+                {
                     continue;
                 }
 
@@ -395,27 +670,28 @@ impl KnowledgeIndex {
                 if previews.len() < options.max_preview_matches_per_file
                     && rows < options.max_match_rows
                 {
-                    previews.push(SearchPreviewMatch {
-                        line: line_ix + 1,
-                        preview: line.trim().to_string(),
+                    let matches = plan.highlight.as_ref().map_or_else(Vec::new, |highlight| {
+                        highlight
+                            .find_iter(line)
+                            .filter(|m| !m.is_empty())
+                            .map(|m| m.range())
+                            .collect::<Vec<_>>()
                     });
+                    previews.push(preview_line(
+                        line_ix + 1,
+                        line,
+                        &matches,
+                        options.max_preview_chars,
+                    ));
                     rows += 1;
                 }
             }
 
-            if match_count == 0 {
-                if self.notes.get(&path).is_some_and(|n| {
-                    n.path_lower.contains(&query_lower) || n.title_lower.contains(&query_lower)
-                }) {
-                    match_count = 1;
-                } else {
-                    continue;
-                }
-            }
-
             hits.push(SearchHit {
                 path,
-                match_count,
+                score,
+                // Notes matched by name, alias, tag or filter alone still count once.
+                match_count: match_count.max(1),
                 previews,
             });
             files_with_matches += 1;
@@ -425,9 +701,203 @@ impl KnowledgeIndex {
             query: query.to_string(),
             elapsed_ms: started_at.elapsed().as_millis(),
             hits,
+            error: None,
         }
     }
 
+    /// The pattern that finds the highlighted text of `query` within a note line, as used
+    /// for search previews; `None` when the query has no text to highlight.
+    pub fn match_pattern(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Option<regex::Regex>, SearchQueryError> {
+        Ok(self
+            .plan_search(query.trim(), options)?
+            .and_then(|plan| plan.highlight))
+    }
+
+    pub fn tokenizer(&self) -> &Arc<dyn Tokenizer> {
+        &self.tokenizer
+    }
+
+    /// Switches the tokenizer and re-indexes every note and info record with it, since
+    /// terms from different tokenizers do not match each other.
+    pub fn set_tokenizer(&mut self, vault: &Vault, tokenizer: Arc<dyn Tokenizer>) -> Result<()> {
+        self.tokenizer = tokenizer;
+        for path in self.all_paths_sorted() {
+            if self.upsert_note(vault, &path).is_err() {
+                self.remove_note(&path);
+            }
+        }
+        self.reload_infos(vault)
+    }
+
+    pub fn bm25_params(&self) -> &Bm25Params {
+        &self.bm25
+    }
+
+    pub fn set_bm25_params(&mut self, params: Bm25Params) {
+        self.bm25 = params;
+    }
+
+    fn corpus_stats(&self) -> CorpusStats {
+        let mut avg_field_lens = [0.0; SEARCH_FIELD_COUNT];
+        for (ix, avg) in avg_field_lens.iter_mut().enumerate() {
+            // Averaged over notes that have the field, so sparse fields like aliases are
+            // not treated as overlong wherever they appear.
+            if self.field_doc_counts[ix] > 0 {
+                *avg = self.field_len_totals[ix] as f64 / self.field_doc_counts[ix] as f64;
+            }
+        }
+        CorpusStats {
+            doc_count: self.notes.len(),
+            avg_field_lens,
+        }
+    }
+
+    pub fn info_count(&self) -> usize {
+        self.infos.len()
+    }
+
+    /// Replaces all indexed info records with the ones currently stored in the vault.
+    pub fn reload_infos(&mut self, vault: &Vault) -> Result<()> {
+        let infos = vault.list_info_metas()?;
+        self.infos.clear();
+        self.info_inverted.clear();
+        for info in &infos {
+            self.upsert_info(info);
+        }
+        Ok(())
+    }
+
+    pub fn remove_info(&mut self, info_id: &str) {
+        let Some(existing) = self.infos.remove(info_id.trim()) else {
+            return;
+        };
+        for token in existing.token_set {
+            if let Some(ids) = self.info_inverted.get_mut(&token) {
+                ids.remove(&existing.id);
+                if ids.is_empty() {
+                    self.info_inverted.remove(&token);
+                }
+            }
+        }
+    }
+
+    pub fn upsert_info(&mut self, info: &InfoMetaV1) {
+        self.remove_info(&info.id);
+
+        let title = info.display_title().to_string();
+        let title_lower = title.to_lowercase();
+        let body_lower = info.body.to_lowercase();
+        let url_lower = info
+            .source
+            .url
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase();
+        let tags_lower = info
+            .tags
+            .iter()
+            .map(|s| s.to_lowercase())
+            .collect::<Vec<_>>();
+
+        let tokenizer = &self.tokenizer;
+        let mut token_set = HashSet::new();
+        token_set.extend(tokenizer.tokenize(&info.id));
+        token_set.extend(tokenizer.tokenize(&title_lower));
+        token_set.extend(tokenizer.tokenize(&body_lower));
+        token_set.extend(tokenizer.tokenize(&url_lower));
+        if let Some(author) = info.source.author.as_deref() {
+            token_set.extend(tokenizer.tokenize(author));
+        }
+        for tag in &tags_lower {
+            token_set.extend(tokenizer.tokenize(tag));
+        }
+
+        for token in &token_set {
+            self.info_inverted
+                .entry(token.clone())
+                .or_default()
+                .insert(info.id.clone());
+        }
+
+        self.infos.insert(
+            info.id.clone(),
+            IndexedInfo {
+                id: info.id.clone(),
+                kind: info.kind,
+                title,
+                title_lower,
+                body: info.body.clone(),
+                body_lower,
+                url_lower,
+                tags_lower,
+                token_set,
+            },
+        );
+    }
+
+    pub fn search_infos(&self, query: &str, max_results: usize) -> Vec<InfoSearchHit> {
+        let query = query.trim();
+        if query.is_empty() || max_results == 0 {
+            return Vec::new();
+        }
+
+        let query_lower = query.to_lowercase();
+        let query_tokens = self.tokenizer.tokenize(&query_lower);
+        let candidates = if query_tokens.is_empty() {
+            self.infos.keys().cloned().collect::<HashSet<_>>()
+        } else {
+            let mut sets = query_tokens
+                .iter()
+                .map(|token| self.info_inverted.get(token).cloned().unwrap_or_default())
+                .collect::<Vec<_>>();
+            sets.sort_by_key(|s| s.len());
+            let mut out = sets.remove(0);
+            for set in sets {
+                out.retain(|id| set.contains(id));
+            }
+            out
+        };
+
+        let mut ranked = candidates
+            .into_iter()
+            .filter_map(|id| self.infos.get(&id))
+            .map(|info| {
+                (
+                    score_info_for_query(info, &query_lower, &query_tokens),
+                    info,
+                )
+            })
+            .filter(|(score, _)| *score > 0)
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.id.cmp(&b.1.id)));
+
+        ranked
+            .into_iter()
+            .take(max_results)
+            .map(|(_, info)| {
+                let mut lines = info.body.lines().map(str::trim).filter(|l| !l.is_empty());
+                let preview = info
+                    .body
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| line.to_lowercase().contains(&query_lower))
+                    .or_else(|| lines.next())
+                    .unwrap_or_default()
+                    .to_string();
+                InfoSearchHit {
+                    id: info.id.clone(),
+                    kind: info.kind,
+                    title: info.title.clone(),
+                    preview,
+                }
+            })
+            .collect()
+    }
+
     fn collect_candidates(&self, query_lower: &str, query_tokens: &[String]) -> Vec<String> {
         if query_tokens.is_empty() {
             return self
@@ -485,7 +955,7 @@ impl KnowledgeIndex {
         }
 
         let query_lower = query.to_lowercase();
-        let query_tokens = tokenize(&query_lower);
+        let query_tokens = self.tokenizer.tokenize(&query_lower);
         let mut candidates = self.collect_candidates(&query_lower, &query_tokens);
 
         let expansion_limit = (max_results.saturating_mul(16)).clamp(256, 4_096);
@@ -612,7 +1082,7 @@ fn score_note_for_query(note: &IndexedNote, query_lower: &str, query_tokens: &[S
     }
 
     for token in query_tokens {
-        if note.token_set.contains(token) {
+        if note.terms.contains_key(token) {
             score += 8;
         }
     }
@@ -628,6 +1098,121 @@ fn score_note_for_query(note: &IndexedNote, query_lower: &str, query_tokens: &[S
     score
 }
 
+fn score_info_for_query(info: &IndexedInfo, query_lower: &str, query_tokens: &[String]) -> usize {
+    let mut score = 0usize;
+    if info.title_lower == query_lower {
+        score += 220;
+    }
+    if info.title_lower.starts_with(query_lower) {
+        score += 130;
+    }
+    if info.title_lower.contains(query_lower) {
+        score += 70;
+    }
+    if info.body_lower.contains(query_lower) {
+        score += 40;
+    }
+    if info.url_lower.contains(query_lower) {
+        score += 30;
+    }
+    for tag in &info.tags_lower {
+        if tag == query_lower {
+            score += 40;
+        } else if tag.contains(query_lower) {
+            score += 24;
+        }
+    }
+    for token in query_tokens {
+        if info.token_set.contains(token) {
+            score += 8;
+        }
+    }
+    score
+}
+
+/// Trims `line` and, past `max_chars` characters, cuts it to a window that starts a
+/// quarter of the budget before the first of `matches` (byte ranges in `line`).
+fn preview_line(
+    line_no: usize,
+    line: &str,
+    matches: &[Range<usize>],
+    max_chars: usize,
+) -> SearchPreviewMatch {
+    let text_start = line.len() - line.trim_start().len();
+    let text_end = text_start + line.trim().len();
+    // Char boundaries of the trimmed text, its end included.
+    let bounds = line[text_start..text_end]
+        .char_indices()
+        .map(|(ix, _)| text_start + ix)
+        .chain(std::iter::once(text_end))
+        .collect::<Vec<_>>();
+    let char_count = bounds.len() - 1;
+
+    let (mut start, mut end) = (text_start, text_end);
+    if max_chars > 0 && char_count > max_chars {
+        let anchor = matches.first().map_or(text_start, |m| m.start);
+        let anchor_ix = bounds.partition_point(|&bound| bound < anchor);
+        let end_ix = (anchor_ix.saturating_sub(max_chars / 4) + max_chars).min(char_count);
+        start = bounds[end_ix - max_chars];
+        end = bounds[end_ix];
+    }
+
+    let prefix = if start > text_start {
+        PREVIEW_ELLIPSIS
+    } else {
+        ""
+    };
+    let mut preview = format!("{prefix}{}", &line[start..end]);
+    let spans = matches
+        .iter()
+        .map(|m| m.start.max(start)..m.end.min(end))
+        .filter(|m| m.start < m.end)
+        .map(|m| {
+            let utf16_start =
+                prefix.encode_utf16().count() + line[start..m.start].encode_utf16().count();
+            SearchMatchSpan {
+                bytes: prefix.len() + m.start - start..prefix.len() + m.end - start,
+                utf16: utf16_start..utf16_start + line[m.clone()].encode_utf16().count(),
+                line_bytes: m,
+            }
+        })
+        .collect();
+    if end < text_end {
+        preview.push_str(PREVIEW_ELLIPSIS);
+    }
+
+    SearchPreviewMatch {
+        line: line_no,
+        preview,
+        line_range: start..end,
+        spans,
+    }
+}
+
+/// Whether a line shows a phrase or punctuation-only word of the query verbatim, or any
+/// query term as the tokenizer sees it (stems and CJK segments included).
+fn line_matches_text(
+    line: &str,
+    needles: &[String],
+    query_tokens: &[String],
+    tokenizer: &dyn Tokenizer,
+) -> bool {
+    if !needles.is_empty() {
+        let line_lower = line.to_lowercase();
+        if needles
+            .iter()
+            .any(|needle| line_lower.contains(needle.as_str()))
+        {
+            return true;
+        }
+    }
+    if query_tokens.is_empty() {
+        return false;
+    }
+    let line_tokens = tokenizer.tokenize(line);
+    query_tokens.iter().any(|token| line_tokens.contains(token))
+}
+
 fn quick_open_fallback_match(note: &IndexedNote, query_lower: &str) -> bool {
     if query_lower.is_empty() {
         return true;
@@ -730,10 +1315,36 @@ fn is_word_boundary_at(haystack: &str, index: usize) -> bool {
 }
 
 pub fn parse_note_metadata(content: &str, fallback_path: &str) -> NoteMetadata {
+    parse_note_metadata_with_types(content, fallback_path, &PropertyTypes::new())
+}
+
+/// Like [`parse_note_metadata`], typing the properties `declared` names as declared.
+pub fn parse_note_metadata_with_types(
+    content: &str,
+    fallback_path: &str,
+    declared: &PropertyTypes,
+) -> NoteMetadata {
     let title = extract_title(content).unwrap_or_else(|| file_name_from_path(fallback_path));
-    let frontmatter = extract_frontmatter(content);
-    let note_id = extract_note_id(&frontmatter);
-    let aliases = extract_aliases(&frontmatter, &title);
+    // Malformed YAML only drops the properties that cannot be read on their own.
+    let parsed = Frontmatter::parse_lenient(content);
+    let note_id = parsed.as_ref().and_then(extract_note_id);
+    let aliases = parsed
+        .as_ref()
+        .map(|fm| extract_aliases(fm, &title))
+        .unwrap_or_default();
+    let entries = parsed
+        .map(|fm| {
+            fm.entries
+                .into_iter()
+                .map(|entry| (entry.key, entry.value))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let properties = typed_properties(&entries, declared);
+    let frontmatter = entries
+        .into_iter()
+        .map(|(key, value)| (key, value.to_flat_string()))
+        .collect();
     let mut links = extract_wikilinks(content);
     links.extend(extract_markdown_links(content));
     links = dedup_links_preserve_order(links);
@@ -743,33 +1354,36 @@ pub fn parse_note_metadata(content: &str, fallback_path: &str) -> NoteMetadata {
         note_id,
         title,
         frontmatter,
+        properties,
         aliases,
         links,
         tags,
     }
 }
 
-fn frontmatter_value<'a>(frontmatter: &'a HashMap<String, String>, key: &str) -> Option<&'a str> {
-    frontmatter.get(key).map(String::as_str).or_else(|| {
-        frontmatter
-            .iter()
-            .find(|(existing, _)| existing.eq_ignore_ascii_case(key))
-            .map(|(_, value)| value.as_str())
-    })
+/// A key every note matching `expr` must have, to narrow candidates via `property_paths`.
+fn required_property_key(expr: &PropertyExpr) -> Option<&str> {
+    match expr {
+        PropertyExpr::Exists(key) => Some(key),
+        PropertyExpr::Compare(PropertyComparison { key, op, .. }) if *op != PropertyOp::Ne => {
+            Some(key)
+        }
+        PropertyExpr::And(parts) => parts.iter().find_map(required_property_key),
+        _ => None,
+    }
 }
 
-fn extract_note_id(frontmatter: &HashMap<String, String>) -> Option<String> {
-    let raw = frontmatter_value(frontmatter, "id")?.trim();
-    normalize_note_id(raw).ok()
+fn extract_note_id(frontmatter: &Frontmatter) -> Option<String> {
+    let raw = frontmatter.get("id")?.as_scalar_text()?;
+    normalize_note_id(raw.trim()).ok()
 }
 
-fn extract_aliases(frontmatter: &HashMap<String, String>, title: &str) -> Vec<String> {
-    let mut out = Vec::new();
-    if let Some(raw) = frontmatter_value(frontmatter, "aliases")
-        .or_else(|| frontmatter_value(frontmatter, "alias"))
-    {
-        out.extend(parse_alias_values(raw));
-    }
+fn extract_aliases(frontmatter: &Frontmatter, title: &str) -> Vec<String> {
+    let mut out = frontmatter
+        .get("aliases")
+        .or_else(|| frontmatter.get("alias"))
+        .map(YamlValue::string_list)
+        .unwrap_or_default();
 
     let title_trimmed = title.trim();
     out.retain(|alias| {
@@ -779,30 +1393,6 @@ fn extract_aliases(frontmatter: &HashMap<String, String>, title: &str) -> Vec<St
     dedup_links_preserve_order(out)
 }
 
-fn parse_alias_values(raw: &str) -> Vec<String> {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return Vec::new();
-    }
-
-    let content = trimmed
-        .strip_prefix('[')
-        .and_then(|s| s.strip_suffix(']'))
-        .unwrap_or(trimmed);
-
-    content
-        .split(',')
-        .map(|part| {
-            part.trim()
-                .trim_matches('"')
-                .trim_matches('\'')
-                .trim()
-                .to_string()
-        })
-        .filter(|alias| !alias.is_empty())
-        .collect()
-}
-
 fn extract_title(content: &str) -> Option<String> {
     for line in content.lines() {
         let line = line.trim();
@@ -816,76 +1406,6 @@ fn extract_title(content: &str) -> Option<String> {
     None
 }
 
-fn extract_frontmatter(content: &str) -> HashMap<String, String> {
-    let mut out = HashMap::new();
-    let mut lines = content.lines();
-    if lines.next().map(str::trim) != Some("---") {
-        return out;
-    }
-
-    let mut list_key: Option<String> = None;
-    let mut list_values: Vec<String> = Vec::new();
-
-    for raw_line in lines {
-        let line = raw_line.trim();
-        if line == "---" {
-            flush_frontmatter_list(&mut out, &mut list_key, &mut list_values);
-            break;
-        }
-
-        if list_key.is_some() {
-            if line.starts_with("- ") {
-                let value = line.trim_start_matches('-').trim();
-                if !value.is_empty() {
-                    list_values.push(value.to_string());
-                }
-                continue;
-            }
-
-            if !raw_line.starts_with(' ') && !raw_line.starts_with('\t') {
-                flush_frontmatter_list(&mut out, &mut list_key, &mut list_values);
-            } else {
-                continue;
-            }
-        }
-
-        let Some((key, value)) = line.split_once(':') else {
-            continue;
-        };
-        let key = key.trim();
-        let value = value.trim();
-        if !key.is_empty() {
-            if value.is_empty() {
-                list_key = Some(key.to_string());
-                list_values.clear();
-            } else {
-                out.insert(key.to_string(), value.to_string());
-            }
-        }
-    }
-
-    flush_frontmatter_list(&mut out, &mut list_key, &mut list_values);
-
-    out
-}
-
-fn flush_frontmatter_list(
-    out: &mut HashMap<String, String>,
-    list_key: &mut Option<String>,
-    list_values: &mut Vec<String>,
-) {
-    let Some(key) = list_key.take() else {
-        return;
-    };
-
-    if list_values.is_empty() {
-        out.insert(key, String::new());
-    } else {
-        out.insert(key, format!("[{}]", list_values.join(", ")));
-    }
-    list_values.clear();
-}
-
 fn extract_wikilinks(content: &str) -> Vec<String> {
     let mut out = Vec::new();
     let mut remain = content;
@@ -947,6 +1467,141 @@ fn extract_markdown_links(content: &str) -> Vec<String> {
     out
 }
 
+/// Rewrites the target of every wikilink and markdown link in `content`.
+///
+/// `rewrite` receives the normalized link target (no alias, heading or angle
+/// brackets) and returns the replacement target, or `None` to keep the link.
+/// Alias (`|...`) and heading (`#...`) suffixes are preserved verbatim.
+pub fn rewrite_note_links(
+    content: &str,
+    mut rewrite: impl FnMut(&str) -> Option<String>,
+) -> String {
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    for (start, end) in wikilink_inner_ranges(content) {
+        if let Some(edit) = rewrite_link_inner(content, start, end, &mut rewrite) {
+            edits.push(edit);
+        }
+    }
+    for (start, end) in markdown_link_target_ranges(content) {
+        if let Some(edit) = rewrite_link_inner(content, start, end, &mut rewrite) {
+            edits.push(edit);
+        }
+    }
+
+    if edits.is_empty() {
+        return content.to_string();
+    }
+
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut out = String::with_capacity(content.len());
+    let mut cursor = 0usize;
+    for (start, end, replacement) in edits {
+        if start < cursor {
+            continue;
+        }
+        out.push_str(&content[cursor..start]);
+        out.push_str(&replacement);
+        cursor = end;
+    }
+    out.push_str(&content[cursor..]);
+    out
+}
+
+fn rewrite_link_inner(
+    content: &str,
+    start: usize,
+    end: usize,
+    rewrite: &mut impl FnMut(&str) -> Option<String>,
+) -> Option<(usize, usize, String)> {
+    let raw = &content[start..end];
+    let target = normalize_note_link_target(raw)?;
+    let replacement = rewrite(&target)?;
+
+    let core_start = raw.find(|c: char| !c.is_whitespace() && c != '<')?;
+    let core_end = raw[core_start..]
+        .find(['#', '|', '>'])
+        .map(|rel| core_start + rel)
+        .unwrap_or(raw.len());
+    let core = &raw[core_start..core_end];
+    let core_trimmed_end = core_start + core.trim_end().len();
+
+    Some((start + core_start, start + core_trimmed_end, replacement))
+}
+
+pub(crate) fn wikilink_inner_ranges(content: &str) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    let mut offset = 0usize;
+    while let Some(rel_start) = content[offset..].find("[[") {
+        let inner_start = offset + rel_start + 2;
+        let Some(rel_end) = content[inner_start..].find("]]") else {
+            break;
+        };
+        let inner_end = inner_start + rel_end;
+        out.push((inner_start, inner_end));
+        offset = inner_end + 2;
+    }
+    out
+}
+
+/// Raw targets of every wikilink, markdown link and image/embed in `content`.
+///
+/// Targets keep their original spelling minus `<>` wrapping, titles, `#` anchors and
+/// `|` aliases; they are not resolved against any note.
+pub fn collect_link_targets_with_embeds(content: &str) -> Vec<String> {
+    let mut ranges = wikilink_inner_ranges(content);
+    ranges.extend(markdown_link_target_ranges_impl(content, true));
+    ranges.sort();
+    ranges
+        .into_iter()
+        .filter_map(|(start, end)| {
+            let raw = content[start..end].trim();
+            let raw = match raw.strip_prefix('<') {
+                Some(rest) => rest.split('>').next().unwrap_or(rest),
+                None => raw.split(char::is_whitespace).next().unwrap_or(raw),
+            };
+            let target = raw.split(['#', '|', '?']).next().unwrap_or(raw).trim();
+            (!target.is_empty()).then(|| target.to_string())
+        })
+        .collect()
+}
+
+pub(crate) fn markdown_link_target_ranges(content: &str) -> Vec<(usize, usize)> {
+    markdown_link_target_ranges_impl(content, false)
+}
+
+fn markdown_link_target_ranges_impl(content: &str, include_images: bool) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    let bytes = content.as_bytes();
+    let mut i = 0usize;
+
+    while i < bytes.len() {
+        if bytes[i] != b'[' {
+            i += 1;
+            continue;
+        }
+        let Some(close_bracket_rel) = content[i + 1..].find(']') else {
+            i += 1;
+            continue;
+        };
+        let close_bracket = i + 1 + close_bracket_rel;
+        if close_bracket + 1 >= bytes.len() || bytes[close_bracket + 1] != b'(' {
+            i = close_bracket.saturating_add(1);
+            continue;
+        }
+        let Some(close_paren_rel) = content[close_bracket + 2..].find(')') else {
+            i = close_bracket.saturating_add(2);
+            continue;
+        };
+        let close_paren = close_bracket + 2 + close_paren_rel;
+        if include_images || i == 0 || bytes[i - 1] != b'!' {
+            out.push((close_bracket + 2, close_paren));
+        }
+        i = close_paren.saturating_add(1);
+    }
+
+    out
+}
+
 fn dedup_links_preserve_order(links: Vec<String>) -> Vec<String> {
     let mut seen = HashSet::new();
     let mut out = Vec::new();
@@ -1019,24 +1674,6 @@ fn extract_tags(content: &str) -> Vec<String> {
     out
 }
 
-fn tokenize(text: &str) -> Vec<String> {
-    let mut out = Vec::new();
-    let mut current = String::new();
-
-    for ch in text.chars() {
-        if ch.is_alphanumeric() || ch == '_' || ch == '-' {
-            current.push(ch.to_ascii_lowercase());
-        } else if !current.is_empty() {
-            out.push(std::mem::take(&mut current));
-        }
-    }
-    if !current.is_empty() {
-        out.push(current);
-    }
-
-    out
-}
-
 fn file_name_from_path(path: &str) -> String {
     path.rsplit_once('/')
         .map(|(_, name)| name)
@@ -1048,6 +1685,7 @@ fn file_name_from_path(path: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search_query::SearchQueryErrorKind;
     use std::fs;
 
     #[test]
@@ -1073,6 +1711,23 @@ Body with [[Linked/Note]] and #tag_one #tag-two.
         assert!(meta.tags.contains(&"tag-two".to_string()));
     }
 
+    #[test]
+    fn parse_metadata_keeps_readable_properties_of_malformed_yaml() {
+        let content = "---\nid: N1\n\tindented: v\naliases:\n  - Guide\nbroken: {a: 1\ntags: [rust, yaml]\nstatus: draft\n- stray\n---\n# Title\n";
+        let meta = parse_note_metadata(content, "notes/Fallback.md");
+        assert_eq!(meta.note_id.as_deref(), Some("N1"));
+        assert_eq!(meta.aliases, vec!["Guide".to_string()]);
+        assert_eq!(
+            meta.frontmatter.get("tags").map(String::as_str),
+            Some("[rust, yaml]")
+        );
+        assert_eq!(
+            meta.frontmatter.get("status").map(String::as_str),
+            Some("draft")
+        );
+        assert!(!meta.frontmatter.contains_key("broken"));
+    }
+
     #[test]
     fn parse_metadata_extracts_aliases_from_frontmatter_lists() {
         let content = r#"---
@@ -1201,6 +1856,497 @@ Body.
         let _ = fs::remove_dir_all(&temp_dir);
     }
 
+    #[test]
+    fn cjk_and_stemmed_terms_hit_the_inverted_index() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_knowledge_cjk_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes")).expect("create test dir");
+        fs::write(
+            temp_dir.join("notes/知识管理.md"),
+            "# 知识管理\n我们用双向链接组织个人知识库。\nLinking notes by hand\n",
+        )
+        .expect("write CJK note");
+        fs::write(
+            temp_dir.join("notes/Structures.md"),
+            "# Structures\n链表是一种数据结构\n",
+        )
+        .expect("write other note");
+
+        let vault = Vault::open(&temp_dir).expect("open vault");
+        let mut index = KnowledgeIndex::rebuild_from_vault(&vault).expect("build index");
+        assert!(index.inverted.contains_key("链接"));
+        assert_eq!(
+            index.collect_candidates("链接", &index.tokenizer.tokenize("链接")),
+            vec!["notes/知识管理.md".to_string()]
+        );
+
+        let outcome = index.search(&vault, "链接", SearchOptions::default());
+        assert_eq!(outcome.hits.len(), 1);
+        assert_eq!(outcome.hits[0].path, "notes/知识管理.md");
+        assert_eq!(outcome.hits[0].previews[0].line, 2);
+        assert_eq!(
+            index.quick_open_paths("管理", 5),
+            vec!["notes/知识管理.md".to_string()]
+        );
+        assert!(index
+            .search(&vault, "linked", SearchOptions::default())
+            .hits
+            .is_empty());
+
+        let stemming = TextTokenizer {
+            cjk: CjkSegmentation::Bigram,
+            stem_latin: true,
+        };
+        index
+            .set_tokenizer(&vault, Arc::new(stemming))
+            .expect("re-tokenize");
+        let stemmed = index.search(&vault, "linked", SearchOptions::default());
+        assert_eq!(stemmed.hits.len(), 1);
+        assert_eq!(stemmed.hits[0].previews[0].line, 3);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn structured_queries_combine_terms_phrases_and_filters() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_knowledge_query_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes/lang")).expect("create test dir");
+        let notes = [
+            (
+                "notes/Home.md",
+                "---\nid: home-1\npriority: 3\n---\n# Home\nStart at [[Rust]] and [[Go]].\n",
+            ),
+            (
+                "notes/lang/Rust.md",
+                "---\npriority: 1\n---\n# Rust\nThe borrow checker guards memory. #lang/systems\n",
+            ),
+            (
+                "notes/lang/Go.md",
+                "# Go\nA checker that borrows nothing. #lang\nBack to [[Home]]\n",
+            ),
+            ("notes/Draft.md", "# Draft\nborrow checker notes, draft\n"),
+        ];
+        for (path, content) in notes {
+            fs::write(temp_dir.join(path), content).expect("write note");
+        }
+        let vault = Vault::open(&temp_dir).expect("open vault");
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("build index");
+        let paths = |query: &str| {
+            let outcome = index.search(&vault, query, SearchOptions::default());
+            assert_eq!(outcome.error, None, "{query}");
+            let mut paths = outcome
+                .hits
+                .into_iter()
+                .map(|hit| hit.path)
+                .collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(
+            paths("checker borrow"),
+            vec!["notes/Draft.md", "notes/lang/Rust.md"]
+        );
+        assert_eq!(
+            paths("\"borrow checker\" -draft"),
+            vec!["notes/lang/Rust.md"]
+        );
+        assert_eq!(
+            paths("(memory OR nothing) checker"),
+            vec!["notes/lang/Go.md", "notes/lang/Rust.md"]
+        );
+        assert_eq!(
+            paths("tag:lang"),
+            vec!["notes/lang/Go.md", "notes/lang/Rust.md"]
+        );
+        assert_eq!(paths("tag:lang/systems"), vec!["notes/lang/Rust.md"]);
+        assert_eq!(paths("path:lang/ title:go"), vec!["notes/lang/Go.md"]);
+        assert_eq!(paths("id:HOME-1"), vec!["notes/Home.md"]);
+        assert_eq!(paths("has:link"), vec!["notes/Home.md", "notes/lang/Go.md"]);
+        assert_eq!(
+            paths("links-to:Home OR linked-from:Home"),
+            vec!["notes/lang/Go.md", "notes/lang/Rust.md"]
+        );
+        assert_eq!(
+            paths("[priority >= 2] OR -[priority]"),
+            vec!["notes/Draft.md", "notes/Home.md", "notes/lang/Go.md"]
+        );
+
+        let rust = index.search(
+            &vault,
+            "\"borrow checker\" -draft",
+            SearchOptions::default(),
+        );
+        assert_eq!(rust.hits[0].previews[0].line, 5);
+
+        let invalid = index.search(&vault, "tag:lang (rust", SearchOptions::default());
+        assert!(invalid.hits.is_empty());
+        let error = invalid.error.expect("parse error");
+        assert_eq!(error.kind, SearchQueryErrorKind::UnclosedGroup);
+        assert_eq!(error.span, 9..10);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn regex_case_sensitive_and_whole_word_modes() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_knowledge_modes_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes")).expect("create test dir");
+        fs::write(
+            temp_dir.join("notes/Upper.md"),
+            "# Upper\n  Rust borrows, Rust checks.\n",
+        )
+        .expect("write upper");
+        fs::write(
+            temp_dir.join("notes/Lower.md"),
+            "# Lower\nrust and a checker v2.1\n",
+        )
+        .expect("write lower");
+        let vault = Vault::open(&temp_dir).expect("open vault");
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("build index");
+        let run = |query: &str, options: SearchOptions| {
+            let outcome = index.search(&vault, query, options);
+            let mut hits = outcome.hits;
+            hits.sort_by(|a, b| a.path.cmp(&b.path));
+            (hits, outcome.error)
+        };
+        let paths =
+            |hits: &[SearchHit]| hits.iter().map(|hit| hit.path.clone()).collect::<Vec<_>>();
+
+        let (hits, _) = run("rust", SearchOptions::default());
+        assert_eq!(paths(&hits), vec!["notes/Lower.md", "notes/Upper.md"]);
+        let case_sensitive = SearchOptions {
+            case_sensitive: true,
+            ..SearchOptions::default()
+        };
+        let (hits, _) = run("Rust", case_sensitive.clone());
+        assert_eq!(paths(&hits), vec!["notes/Upper.md"]);
+        assert_eq!(hits[0].previews[0].preview, "Rust borrows, Rust checks.");
+        let spans = &hits[0].previews[0].spans;
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| span.bytes.clone())
+                .collect::<Vec<_>>(),
+            vec![0..4, 14..18]
+        );
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| span.line_bytes.clone())
+                .collect::<Vec<_>>(),
+            vec![2..6, 16..20]
+        );
+        let (hits, _) = run("Upper", case_sensitive);
+        assert_eq!(paths(&hits), vec!["notes/Upper.md"]);
+
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        let (hits, _) = run("\"check\"", SearchOptions::default());
+        assert_eq!(paths(&hits), vec!["notes/Lower.md", "notes/Upper.md"]);
+        let (hits, _) = run("\"check\"", whole_word.clone());
+        assert!(hits.is_empty());
+        let (hits, _) = run("\"checks\"", whole_word);
+        assert_eq!(paths(&hits), vec!["notes/Upper.md"]);
+
+        let regex = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        let (hits, _) = run(r"check\w*", regex.clone());
+        assert_eq!(paths(&hits), vec!["notes/Lower.md", "notes/Upper.md"]);
+        assert_eq!(hits[0].previews[0].spans[0].bytes, 11..18);
+        let (hits, _) = run(r"v\d+\.\d", regex.clone());
+        assert_eq!(paths(&hits), vec!["notes/Lower.md"]);
+        let (hits, _) = run(
+            "^Rust",
+            SearchOptions {
+                case_sensitive: true,
+                ..regex.clone()
+            },
+        );
+        assert!(hits.is_empty(), "anchors apply to the untrimmed line");
+
+        // Literal prefixes narrow the candidates through the term table; patterns
+        // without one scan every note.
+        let candidates = |pattern: &str| {
+            index
+                .plan_search(pattern, &regex)
+                .expect("valid pattern")
+                .expect("plan")
+                .compiled
+                .candidates()
+                .map(|set| {
+                    let mut paths = set.into_iter().collect::<Vec<_>>();
+                    paths.sort();
+                    paths
+                })
+        };
+        assert_eq!(
+            candidates(r"orrow\w*"),
+            Some(vec!["notes/Upper.md".to_string()])
+        );
+        assert_eq!(
+            candidates("Rust (borrows|and)"),
+            Some(vec![
+                "notes/Lower.md".to_string(),
+                "notes/Upper.md".to_string(),
+            ])
+        );
+        assert_eq!(candidates(r"\w+s\b"), None);
+
+        let (hits, error) = run("(unclosed", regex);
+        assert!(hits.is_empty());
+        let error = error.expect("invalid pattern");
+        assert!(matches!(error.kind, SearchQueryErrorKind::InvalidRegex(_)));
+        assert_eq!(error.span, 0..1);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn previews_report_utf16_spans_and_window_long_lines() {
+        let line = "  😀知识 target ";
+        let matches = line
+            .match_indices("target")
+            .map(|(at, m)| at..at + m.len())
+            .collect::<Vec<_>>();
+        let preview = preview_line(4, line, &matches, 160);
+        assert_eq!(preview.line, 4);
+        assert_eq!(preview.preview, "😀知识 target");
+        assert_eq!(preview.line_range, 2..19);
+        assert_eq!(
+            preview.spans,
+            vec![SearchMatchSpan {
+                bytes: 11..17,
+                utf16: 5..11,
+                line_bytes: 13..19,
+            }]
+        );
+
+        let line = format!("{}target{}target", "w".repeat(100), "z".repeat(100));
+        let preview = preview_line(1, &line, &[100..106, 206..212], 40);
+        assert_eq!(
+            preview.preview,
+            format!(
+                "{PREVIEW_ELLIPSIS}{}target{}{PREVIEW_ELLIPSIS}",
+                "w".repeat(10),
+                "z".repeat(24)
+            )
+        );
+        assert_eq!(preview.line_range, 90..130);
+        assert_eq!(
+            preview.spans,
+            vec![SearchMatchSpan {
+                bytes: 13..19,
+                utf16: 11..17,
+                line_bytes: 100..106,
+            }]
+        );
+        assert_eq!(&preview.preview[13..19], "target");
+
+        let unwindowed = preview_line(1, &line, &[100..106, 206..212], 0);
+        assert_eq!(unwindowed.preview, line);
+        assert_eq!(unwindowed.spans.len(), 2);
+    }
+
+    #[test]
+    fn info_records_are_indexed_and_searchable() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_knowledge_infos_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes")).expect("create test dir");
+        fs::write(temp_dir.join("notes/A.md"), "# Alpha\nborrow checker").expect("write A");
+        let vault = Vault::open(&temp_dir).expect("open vault");
+        let quote = vault
+            .capture_info(
+                InfoKind::Quote,
+                "Intro line\nThe borrow checker is your friend.",
+                crate::info_meta::InfoSource::default(),
+                vec!["rust".to_string()],
+            )
+            .expect("capture quote");
+
+        let entries = vault.fast_scan_notes().expect("scan notes");
+        let mut index = KnowledgeIndex::build_from_entries(&vault, &entries).expect("build index");
+        assert_eq!(index.info_count(), 1);
+
+        let hits = index.search_infos("Borrow Checker", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, quote.id);
+        assert_eq!(hits[0].title, "Intro line");
+        assert_eq!(hits[0].preview, "The borrow checker is your friend.");
+        assert!(!index
+            .search(&vault, "borrow checker", SearchOptions::default())
+            .hits
+            .is_empty());
+
+        let mut bookmark = InfoMetaV1::new("F1", InfoKind::Bookmark).expect("bookmark");
+        bookmark.source.url = Some("https://doc.rust-lang.org/book/".to_string());
+        bookmark.source.title = Some("The Rust Book".to_string());
+        index.upsert_info(&bookmark);
+        let hits = index.search_infos("rust", 10);
+        assert_eq!(hits.first().map(|h| h.id.as_str()), Some("F1"));
+        assert_eq!(hits.len(), 2);
+
+        index.remove_info(&quote.id);
+        assert!(index.search_infos("borrow", 10).is_empty());
+        assert_eq!(index.info_count(), 1);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn bm25_ranks_fixed_corpus_by_field_weight_frequency_and_length() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_knowledge_bm25_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes/projects/ferris")).expect("create test dir");
+        let filler = "other words fill this body so it is long ".repeat(20);
+        let corpus = [
+            (
+                "notes/Ferris.md",
+                "# Ferris\nThe crab mascot.\n".to_string(),
+            ),
+            (
+                "notes/Mascots.md",
+                "---\naliases: [Ferris Crab]\n---\n# Mascots\nA list of mascots.\n".to_string(),
+            ),
+            (
+                "notes/Repeated.md",
+                "# Repeated\nferris ferris ferris and the crab\n".to_string(),
+            ),
+            ("notes/Short.md", "# Short\nferris once\n".to_string()),
+            ("notes/Long.md", format!("# Long\nferris once\n{filler}\n")),
+            (
+                "notes/projects/ferris/plan.md",
+                "# Plan\nroadmap only\n".to_string(),
+            ),
+            (
+                "notes/Unrelated.md",
+                "# Unrelated\ncrab cakes\n".to_string(),
+            ),
+        ];
+        for (path, content) in &corpus {
+            fs::write(temp_dir.join(path), content).expect("write corpus note");
+        }
+        let vault = Vault::open(&temp_dir).expect("open vault");
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("build index");
+
+        let outcome = index.search(&vault, "ferris", SearchOptions::default());
+        let order = outcome
+            .hits
+            .iter()
+            .map(|hit| hit.path.as_str())
+            .collect::<Vec<_>>();
+        // Title beats everything; three body mentions saturate past a single alias; a
+        // short body beats the path-only note, and the long body's mention is diluted.
+        assert_eq!(
+            order,
+            vec![
+                "notes/Ferris.md",
+                "notes/Repeated.md",
+                "notes/Mascots.md",
+                "notes/Short.md",
+                "notes/projects/ferris/plan.md",
+                "notes/Long.md",
+            ]
+        );
+        assert!(outcome
+            .hits
+            .windows(2)
+            .all(|pair| pair[0].score > pair[1].score));
+
+        let mut params = Bm25Params::default();
+        params.body.weight = 20.0;
+        let mut body_heavy = index.clone();
+        body_heavy.set_bm25_params(params);
+        let boosted = body_heavy.search(&vault, "ferris", SearchOptions::default());
+        assert_eq!(boosted.hits[0].path, "notes/Repeated.md");
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn property_queries_filter_and_sort_typed_values() {
+        let temp_dir = std::env::temp_dir().join(format!(
+            "xnote_core_knowledge_properties_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes")).expect("create test dir");
+        let notes = [
+            ("A", "status: draft\ndue: 2026-10-20\nweight: 10\n"),
+            ("B", "status: draft\ndue: 2026-10-05\nweight: 9\n"),
+            ("C", "status: done\ndue: 2026-09-01\nweight: 100\n"),
+            ("D", "status: draft\ndue: 2026-12-01\n"),
+            ("E", "status: draft\n"),
+        ];
+        for (name, yaml) in notes {
+            fs::write(
+                temp_dir.join(format!("notes/{name}.md")),
+                format!("---\n{yaml}---\n# {name}\n"),
+            )
+            .expect("write note");
+        }
+        let vault = Vault::open(&temp_dir).expect("open vault");
+        let entries = vault.fast_scan_notes().expect("scan notes");
+        let mut index = KnowledgeIndex::build_from_entries(&vault, &entries).expect("build index");
+
+        let query = PropertyQuery::parse("status = draft AND due < 2026-11-01 SORT BY due")
+            .expect("parse query");
+        assert_eq!(
+            index.query_properties(&query),
+            vec!["notes/B.md".to_string(), "notes/A.md".to_string()]
+        );
+        let by_weight = PropertyQuery::parse("SORT BY weight DESC").expect("parse sort");
+        assert_eq!(
+            index.query_properties(&by_weight),
+            ["C", "A", "B", "D", "E"]
+                .map(|name| format!("notes/{name}.md"))
+                .to_vec()
+        );
+        assert_eq!(
+            index.property_keys(),
+            vec![
+                ("due".to_string(), 4),
+                ("status".to_string(), 5),
+                ("weight".to_string(), 3)
+            ]
+        );
+
+        let mut settings = crate::settings::AppSettings::default();
+        settings
+            .properties
+            .types
+            .insert("Weight".to_string(), crate::properties::PropertyType::Text);
+        crate::settings::save_project_settings(&temp_dir, &settings).expect("save settings");
+        let rebuilt = KnowledgeIndex::build_from_entries(&vault, &entries).expect("rebuild");
+        assert_eq!(
+            rebuilt.query_properties(&by_weight)[..3],
+            ["notes/B.md", "notes/C.md", "notes/A.md"].map(str::to_string)
+        );
+
+        index.set_property_types(&vault, PropertyTypes::new());
+        index.remove_note("notes/C.md");
+        assert_eq!(
+            index
+                .note_properties("notes/A.md")
+                .and_then(|props| props.get("weight")),
+            Some(&PropertyValue::Number(10.0))
+        );
+        assert_eq!(index.property_keys()[2], ("weight".to_string(), 2));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
     #[test]
     fn quick_open_prefers_title_and_tag_matches() {
         let temp_dir = std::env::temp_dir().join(format!(
@@ -1431,4 +2577,14 @@ Body.
 
         let _ = fs::remove_dir_all(&temp_dir);
     }
+
+    #[test]
+    fn collect_link_targets_with_embeds_includes_images() {
+        let content =
+            "![a](../att/x.png \"t\") [b](<dir/y z.pdf#p=2>) ![[clip.mp4|200]] [[Note#H]]";
+        assert_eq!(
+            collect_link_targets_with_embeds(content),
+            vec!["../att/x.png", "dir/y z.pdf", "clip.mp4", "Note"]
+        );
+    }
 }
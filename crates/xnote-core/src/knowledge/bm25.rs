@@ -0,0 +1,154 @@
+//! BM25F relevance scoring.
+//!
+//! Every note keeps per-field term frequencies and field lengths; the index keeps the
+//! summed field lengths, how many notes have each field, and (through the inverted map)
+//! each term's document frequency.
+//! Field frequencies are length-normalised and weighted before the BM25 saturation, so a
+//! term in a short title counts for more than the same term deep in a long body.
+
+use super::IndexedNote;
+
+pub const SEARCH_FIELD_COUNT: usize = 5;
+
+/// Per-field term frequencies of one term in one note, indexed by [`SearchField::index`].
+pub(super) type FieldFreqs = [u32; SEARCH_FIELD_COUNT];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SearchField {
+    Title,
+    Aliases,
+    Tags,
+    Path,
+    Body,
+}
+
+impl SearchField {
+    pub const ALL: [Self; SEARCH_FIELD_COUNT] = [
+        Self::Title,
+        Self::Aliases,
+        Self::Tags,
+        Self::Path,
+        Self::Body,
+    ];
+
+    pub const fn index(self) -> usize {
+        match self {
+            Self::Title => 0,
+            Self::Aliases => 1,
+            Self::Tags => 2,
+            Self::Path => 3,
+            Self::Body => 4,
+        }
+    }
+
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Aliases => "aliases",
+            Self::Tags => "tags",
+            Self::Path => "path",
+            Self::Body => "body",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FieldWeight {
+    /// Multiplier on the field's normalised term frequency.
+    pub weight: f64,
+    /// Length normalisation, from 0 (none) to 1 (full).
+    pub b: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bm25Params {
+    /// Term frequency saturation.
+    pub k1: f64,
+    pub title: FieldWeight,
+    pub aliases: FieldWeight,
+    pub tags: FieldWeight,
+    pub path: FieldWeight,
+    pub body: FieldWeight,
+}
+
+impl Default for Bm25Params {
+    fn default() -> Self {
+        Self {
+            k1: 1.2,
+            title: FieldWeight {
+                weight: 5.0,
+                b: 0.5,
+            },
+            aliases: FieldWeight {
+                weight: 4.0,
+                b: 0.5,
+            },
+            tags: FieldWeight {
+                weight: 3.0,
+                b: 0.3,
+            },
+            path: FieldWeight {
+                weight: 2.0,
+                b: 0.4,
+            },
+            body: FieldWeight {
+                weight: 1.0,
+                b: 0.75,
+            },
+        }
+    }
+}
+
+impl Bm25Params {
+    pub fn field(&self, field: SearchField) -> FieldWeight {
+        match field {
+            SearchField::Title => self.title,
+            SearchField::Aliases => self.aliases,
+            SearchField::Tags => self.tags,
+            SearchField::Path => self.path,
+            SearchField::Body => self.body,
+        }
+    }
+}
+
+/// Collection-wide statistics a score is computed against.
+pub(super) struct CorpusStats {
+    pub doc_count: usize,
+    pub avg_field_lens: [f64; SEARCH_FIELD_COUNT],
+}
+
+/// BM25F score of `note` for the distinct `query_terms`; `doc_freq` gives each term's
+/// number of matching notes.
+pub(super) fn bm25f_score(
+    params: &Bm25Params,
+    stats: &CorpusStats,
+    note: &IndexedNote,
+    query_terms: &[String],
+    doc_freq: impl Fn(&str) -> usize,
+) -> f64 {
+    let n = stats.doc_count as f64;
+    let mut score = 0.0;
+    for term in query_terms {
+        let Some(freqs) = note.terms.get(term) else {
+            continue;
+        };
+        let mut weighted_tf = 0.0;
+        for field in SearchField::ALL {
+            let tf = freqs[field.index()];
+            if tf == 0 {
+                continue;
+            }
+            let FieldWeight { weight, b } = params.field(field);
+            let avg = stats.avg_field_lens[field.index()].max(1.0);
+            let len = f64::from(note.field_lens[field.index()]);
+            weighted_tf += weight * f64::from(tf) / (1.0 - b + b * len / avg);
+        }
+        if weighted_tf <= 0.0 {
+            continue;
+        }
+        let df = doc_freq(term) as f64;
+        let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+        score += idf * weighted_tf / (params.k1 + weighted_tf);
+    }
+    score
+}
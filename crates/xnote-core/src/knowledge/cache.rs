@@ -0,0 +1,749 @@
+//! On-disk snapshot of the [`KnowledgeIndex`] in `.xnote/cache`, so opening a vault only
+//! re-parses the notes that changed since the last run.
+//!
+//! Layout, little-endian with lengths and counts as LEB128 varints: the magic `XNKI`, a
+//! `u32` format version, the property type declarations the notes were typed with, the
+//! fingerprint of the tokenizer that produced the terms, a table of every search term,
+//! then each note with its stat, content hash, parsed fields and term ids with per-field
+//! frequencies (the inverted map, per note). A SHA-256 of everything before it closes the
+//! file. The inverted map, id map and field length totals are rebuilt from the notes on
+//! load.
+
+use super::{FieldFreqs, IndexedNote, KnowledgeIndex, NoteFields, SEARCH_FIELD_COUNT};
+use crate::atomic_write::write_atomic;
+use crate::paths::{join_inside, normalize_vault_rel_path};
+use crate::properties::{
+    PropertyDate, PropertyDateTime, PropertyType, PropertyTypes, PropertyValue,
+};
+use crate::vault::{NoteEntry, Vault};
+use anyhow::{anyhow, Context as _, Result};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+pub const KNOWLEDGE_CACHE_VERSION: u32 = 4;
+const CACHE_MAGIC: &[u8; 4] = b"XNKI";
+const CACHE_FILE_NAME: &str = "knowledge-index.bin";
+const CHECKSUM_LEN: usize = 32;
+
+/// What a note file looked like when it was indexed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) struct NoteStamp {
+    modified_secs: u64,
+    modified_nanos: u32,
+    size: u64,
+    hash: [u8; 32],
+}
+
+impl NoteStamp {
+    fn from_metadata(metadata: &fs::Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Self {
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            size: metadata.len(),
+            hash: [0; 32],
+        }
+    }
+
+    fn same_stat(&self, other: &Self) -> bool {
+        self.modified_secs == other.modified_secs
+            && self.modified_nanos == other.modified_nanos
+            && self.size == other.size
+    }
+}
+
+/// Reads a note and stamps it. The file is stat'ed before it is read, so a concurrent
+/// edit leaves an outdated stat that the next warm start re-checks.
+pub(super) fn read_note_stamped(vault: &Vault, rel: &str) -> Result<(String, NoteStamp)> {
+    let full = join_inside(vault.root(), rel)?;
+    let metadata = fs::metadata(&full).with_context(|| format!("stat note: {rel}"))?;
+    let bytes = fs::read(&full).with_context(|| format!("read note: {rel}"))?;
+    let mut stamp = NoteStamp::from_metadata(&metadata);
+    stamp.hash = Sha256::digest(&bytes).into();
+    let content = String::from_utf8(bytes).map_err(|_| anyhow!("read note: {rel}: not UTF-8"))?;
+    Ok((content, stamp))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IndexCacheStatus {
+    /// A valid cache was found and unchanged notes were taken from it.
+    Warm,
+    Missing,
+    /// Written by another cache format version.
+    VersionMismatch {
+        found: u32,
+    },
+    /// Written while the vault declared different property types.
+    PropertyTypesChanged,
+    /// Written with differently segmented search terms.
+    TokenizerChanged,
+    Corrupt {
+        reason: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexCacheStats {
+    pub status: IndexCacheStatus,
+    /// Notes taken from the cache without being parsed.
+    pub reused: usize,
+    /// Notes read and parsed.
+    pub parsed: usize,
+    /// Cached notes that are no longer in the vault.
+    pub dropped: usize,
+}
+
+impl KnowledgeIndex {
+    pub fn cache_path(vault: &Vault) -> PathBuf {
+        vault
+            .root()
+            .join(".xnote")
+            .join("cache")
+            .join(CACHE_FILE_NAME)
+    }
+
+    /// Scans the vault and builds the index with [`KnowledgeIndex::build_with_cache`].
+    pub fn open_with_cache(vault: &Vault) -> Result<(Self, IndexCacheStats)> {
+        let entries = vault.fast_scan_notes()?;
+        Self::build_with_cache(vault, &entries)
+    }
+
+    /// Builds the index for `entries`, re-parsing only notes whose size, mtime and content
+    /// hash differ from the cached ones.
+    ///
+    /// A missing, corrupt or outdated cache means a full rebuild. The cache is rewritten
+    /// when anything changed; failing to write it does not fail the build.
+    pub fn build_with_cache(
+        vault: &Vault,
+        entries: &[NoteEntry],
+    ) -> Result<(Self, IndexCacheStats)> {
+        let mut index = Self::empty_for_vault(vault);
+        let (status, mut cached) = load_cached_notes(&Self::cache_path(vault), &index);
+        let mut stats = IndexCacheStats {
+            status,
+            reused: 0,
+            parsed: 0,
+            dropped: 0,
+        };
+        let mut restamped = 0usize;
+
+        for entry in entries {
+            let Ok(path) = normalize_vault_rel_path(&entry.path) else {
+                continue;
+            };
+            let Ok(full) = join_inside(vault.root(), &path) else {
+                continue;
+            };
+            let Ok(metadata) = fs::metadata(&full) else {
+                continue;
+            };
+            let stat = NoteStamp::from_metadata(&metadata);
+            let cached_note = cached.remove(&path);
+            if let Some(note) = cached_note
+                .as_ref()
+                .filter(|note| note.stamp.same_stat(&stat))
+            {
+                index.insert_indexed(note.clone());
+                stats.reused += 1;
+                continue;
+            }
+
+            let Ok((content, stamp)) = read_note_stamped(vault, &path) else {
+                continue;
+            };
+            match cached_note {
+                Some(mut note) if note.stamp.hash == stamp.hash => {
+                    note.stamp = stamp;
+                    index.insert_indexed(note);
+                    stats.reused += 1;
+                    restamped += 1;
+                }
+                _ => {
+                    let note = index.parse_indexed_note(path, &content, stamp);
+                    index.insert_indexed(note);
+                    stats.parsed += 1;
+                }
+            }
+        }
+        stats.dropped = cached.len();
+        let _ = index.reload_infos(vault);
+
+        let changed = stats.parsed + stats.dropped + restamped > 0;
+        if stats.status != IndexCacheStatus::Warm || changed {
+            let _ = index.save_cache(vault);
+        }
+        Ok((index, stats))
+    }
+
+    /// Writes the current notes to the cache file, replacing it atomically.
+    pub fn save_cache(&self, vault: &Vault) -> Result<()> {
+        let path = Self::cache_path(vault);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("create cache dir: {:?}", parent))?;
+        }
+        write_atomic(&path, &encode_cache(self))
+            .with_context(|| format!("write knowledge cache: {:?}", path))?;
+        Ok(())
+    }
+}
+
+/// Cached notes that were parsed the way `index` would parse them.
+fn load_cached_notes(
+    path: &Path,
+    index: &KnowledgeIndex,
+) -> (IndexCacheStatus, HashMap<String, IndexedNote>) {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return (IndexCacheStatus::Missing, HashMap::new());
+        }
+        Err(err) => {
+            let reason = format!("read failed: {err}");
+            return (IndexCacheStatus::Corrupt { reason }, HashMap::new());
+        }
+    };
+    match decode_cache(
+        &bytes,
+        &index.property_types,
+        &index.tokenizer.fingerprint(),
+    ) {
+        Ok(notes) => (IndexCacheStatus::Warm, notes),
+        Err(status) => (status, HashMap::new()),
+    }
+}
+
+fn encode_cache(index: &KnowledgeIndex) -> Vec<u8> {
+    let mut notes = index.notes.values().collect::<Vec<_>>();
+    notes.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut tokens = notes
+        .iter()
+        .flat_map(|note| note.terms.keys().map(String::as_str))
+        .collect::<Vec<_>>();
+    tokens.sort_unstable();
+    tokens.dedup();
+    let token_ids = tokens
+        .iter()
+        .enumerate()
+        .map(|(ix, token)| (*token, ix as u64))
+        .collect::<HashMap<_, _>>();
+
+    let mut out = Writer::default();
+    out.bytes.extend_from_slice(CACHE_MAGIC);
+    out.bytes
+        .extend_from_slice(&KNOWLEDGE_CACHE_VERSION.to_le_bytes());
+    out.varint(index.property_types.len() as u64);
+    for (key, ty) in &index.property_types {
+        out.str(key);
+        out.str(ty.as_tag());
+    }
+    out.str(&index.tokenizer.fingerprint());
+    out.varint(tokens.len() as u64);
+    for token in &tokens {
+        out.str(token);
+    }
+
+    out.varint(notes.len() as u64);
+    for note in notes {
+        out.str(&note.path);
+        out.varint(note.stamp.modified_secs);
+        out.varint(u64::from(note.stamp.modified_nanos));
+        out.varint(note.stamp.size);
+        out.bytes.extend_from_slice(&note.stamp.hash);
+        match note.note_id.as_deref() {
+            Some(id) => {
+                out.u8(1);
+                out.str(id);
+            }
+            None => out.u8(0),
+        }
+        out.str(&note.title);
+        for list in [&note.aliases, &note.tags, &note.links] {
+            out.varint(list.len() as u64);
+            for item in list {
+                out.str(item);
+            }
+        }
+        out.varint(note.properties.len() as u64);
+        for (key, value) in &note.properties {
+            out.str(key);
+            out.property(value);
+        }
+        let mut terms = note
+            .terms
+            .iter()
+            .map(|(token, freqs)| (token_ids[token.as_str()], freqs))
+            .collect::<Vec<_>>();
+        terms.sort_unstable_by_key(|(id, _)| *id);
+        out.varint(terms.len() as u64);
+        let mut previous = 0;
+        for (id, freqs) in terms {
+            out.varint(id - previous);
+            previous = id;
+            // A bit per field with a non-zero frequency, then those frequencies.
+            let mask = freqs
+                .iter()
+                .enumerate()
+                .filter(|(_, tf)| **tf > 0)
+                .fold(0u8, |mask, (ix, _)| mask | (1 << ix));
+            out.u8(mask);
+            for tf in freqs.iter().filter(|tf| **tf > 0) {
+                out.varint(u64::from(*tf));
+            }
+        }
+    }
+
+    let checksum = Sha256::digest(&out.bytes);
+    out.bytes.extend_from_slice(&checksum);
+    out.bytes
+}
+
+fn decode_cache(
+    bytes: &[u8],
+    property_types: &PropertyTypes,
+    tokenizer: &str,
+) -> std::result::Result<HashMap<String, IndexedNote>, IndexCacheStatus> {
+    let corrupt = |reason: &str| IndexCacheStatus::Corrupt {
+        reason: reason.to_string(),
+    };
+    if bytes.len() < 8 + CHECKSUM_LEN || &bytes[..4] != CACHE_MAGIC {
+        return Err(corrupt("not a knowledge cache"));
+    }
+    let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    if version != KNOWLEDGE_CACHE_VERSION {
+        return Err(IndexCacheStatus::VersionMismatch { found: version });
+    }
+    let (body, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if Sha256::digest(body).as_slice() != checksum {
+        return Err(corrupt("checksum mismatch"));
+    }
+
+    let mut reader = Reader {
+        bytes: body,
+        pos: 8,
+    };
+    let decoded = (|| {
+        let mut cached_types = PropertyTypes::new();
+        for _ in 0..reader.varint()? {
+            let key = reader.str()?;
+            let tag = reader.str()?;
+            let ty = PropertyType::from_tag(&tag).ok_or("unknown property type")?;
+            cached_types.insert(key, ty);
+        }
+        if &cached_types != property_types {
+            return Ok(Err(IndexCacheStatus::PropertyTypesChanged));
+        }
+        if reader.str()? != tokenizer {
+            return Ok(Err(IndexCacheStatus::TokenizerChanged));
+        }
+
+        let token_count = reader.varint()?;
+        let mut tokens = Vec::new();
+        for _ in 0..token_count {
+            tokens.push(reader.str()?);
+        }
+
+        let mut notes = HashMap::new();
+        for _ in 0..reader.varint()? {
+            let path = reader.str()?;
+            let mut stamp = NoteStamp {
+                modified_secs: reader.varint()?,
+                modified_nanos: u32::try_from(reader.varint()?).map_err(|_| "bad mtime")?,
+                size: reader.varint()?,
+                hash: [0; 32],
+            };
+            stamp.hash.copy_from_slice(reader.take(32)?);
+            let note_id = match reader.u8()? {
+                0 => None,
+                _ => Some(reader.str()?),
+            };
+            let title = reader.str()?;
+            let mut lists = [Vec::new(), Vec::new(), Vec::new()];
+            for list in &mut lists {
+                for _ in 0..reader.varint()? {
+                    list.push(reader.str()?);
+                }
+            }
+            let [aliases, tags, links] = lists;
+            let mut properties = BTreeMap::new();
+            for _ in 0..reader.varint()? {
+                let key = reader.str()?;
+                properties.insert(key, reader.property()?);
+            }
+            let mut terms = HashMap::new();
+            let mut id = 0u64;
+            for _ in 0..reader.varint()? {
+                id = id.checked_add(reader.varint()?).ok_or("bad token id")?;
+                let token = tokens.get(id as usize).ok_or("bad token id")?;
+                let mask = reader.u8()?;
+                let mut freqs: FieldFreqs = [0; SEARCH_FIELD_COUNT];
+                for (ix, tf) in freqs.iter_mut().enumerate() {
+                    if mask & (1 << ix) != 0 {
+                        *tf = u32::try_from(reader.varint()?).map_err(|_| "bad term frequency")?;
+                    }
+                }
+                terms.insert(token.clone(), freqs);
+            }
+            let fields = NoteFields {
+                note_id,
+                title,
+                aliases,
+                tags,
+                links,
+                properties,
+            };
+            notes.insert(path.clone(), IndexedNote::new(path, fields, terms, stamp));
+        }
+        if reader.pos != reader.bytes.len() {
+            return Err("trailing bytes");
+        }
+        Ok(Ok(notes))
+    })();
+
+    decoded.unwrap_or_else(|reason| Err(corrupt(reason)))
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn str(&mut self, value: &str) {
+        self.varint(value.len() as u64);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn date(&mut self, date: &PropertyDate) {
+        self.varint(zigzag(i64::from(date.year)));
+        self.u8(date.month);
+        self.u8(date.day);
+    }
+
+    fn property(&mut self, value: &PropertyValue) {
+        match value {
+            PropertyValue::Text(text) => {
+                self.u8(0);
+                self.str(text);
+            }
+            PropertyValue::Number(n) => {
+                self.u8(1);
+                self.bytes.extend_from_slice(&n.to_bits().to_le_bytes());
+            }
+            PropertyValue::Date(date) => {
+                self.u8(2);
+                self.date(date);
+            }
+            PropertyValue::DateTime(dt) => {
+                self.u8(3);
+                self.date(&dt.date);
+                self.u8(dt.hour);
+                self.u8(dt.minute);
+                self.u8(dt.second);
+                match dt.offset_minutes {
+                    Some(offset) => {
+                        self.u8(1);
+                        self.varint(zigzag(i64::from(offset)));
+                    }
+                    None => self.u8(0),
+                }
+            }
+            PropertyValue::Bool(b) => {
+                self.u8(4);
+                self.u8(u8::from(*b));
+            }
+            PropertyValue::List(items) => {
+                self.u8(5);
+                self.varint(items.len() as u64);
+                for item in items {
+                    self.property(item);
+                }
+            }
+            PropertyValue::Link(target) => {
+                self.u8(6);
+                self.str(target);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+type ReadResult<T> = std::result::Result<T, &'static str>;
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> ReadResult<&[u8]> {
+        let end = self.pos.checked_add(len).ok_or("truncated")?;
+        let slice = self.bytes.get(self.pos..end).ok_or("truncated")?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> ReadResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> ReadResult<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("varint too long")
+    }
+
+    fn str(&mut self) -> ReadResult<String> {
+        let len = usize::try_from(self.varint()?).map_err(|_| "truncated")?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "invalid UTF-8")
+    }
+
+    fn date(&mut self) -> ReadResult<PropertyDate> {
+        let year = i32::try_from(unzigzag(self.varint()?)).map_err(|_| "bad date")?;
+        Ok(PropertyDate {
+            year,
+            month: self.u8()?,
+            day: self.u8()?,
+        })
+    }
+
+    fn property(&mut self) -> ReadResult<PropertyValue> {
+        Ok(match self.u8()? {
+            0 => PropertyValue::Text(self.str()?),
+            1 => {
+                let mut bits = [0u8; 8];
+                bits.copy_from_slice(self.take(8)?);
+                PropertyValue::Number(f64::from_bits(u64::from_le_bytes(bits)))
+            }
+            2 => PropertyValue::Date(self.date()?),
+            3 => PropertyValue::DateTime(PropertyDateTime {
+                date: self.date()?,
+                hour: self.u8()?,
+                minute: self.u8()?,
+                second: self.u8()?,
+                offset_minutes: match self.u8()? {
+                    0 => None,
+                    _ => Some(i16::try_from(unzigzag(self.varint()?)).map_err(|_| "bad offset")?),
+                },
+            }),
+            4 => PropertyValue::Bool(self.u8()? != 0),
+            5 => {
+                let mut items = Vec::new();
+                for _ in 0..self.varint()? {
+                    items.push(self.property()?);
+                }
+                PropertyValue::List(items)
+            }
+            6 => PropertyValue::Link(self.str()?),
+            _ => return Err("unknown property tag"),
+        })
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::{NoteSummary, SearchOptions};
+    use crate::properties::PropertyQuery;
+    use crate::vault::test_vault;
+    use std::time::{Duration, SystemTime};
+
+    fn setup(name: &str) -> (PathBuf, Vault) {
+        let (temp_dir, vault) = test_vault(&format!("knowledge_cache_{name}"), &["notes"]);
+        fs::write(
+            temp_dir.join("notes/A.md"),
+            "---\nid: NA\nstatus: draft\ndue: 2026-10-20T09:00+02:00\nrank: -2.5\n---\n# Alpha\nborrow checker [[notes/B]] #rust\n",
+        )
+        .expect("write A");
+        fs::write(
+            temp_dir.join("notes/B.md"),
+            "---\naliases: [Bee]\ntags: [x, [[notes/A]]]\n---\n# Beta\nlifetimes 日本語\n",
+        )
+        .expect("write B");
+        fs::write(temp_dir.join("notes/C.md"), "# Gamma\nplain note\n").expect("write C");
+        (temp_dir, vault)
+    }
+
+    type NoteSnapshot = (
+        Option<NoteSummary>,
+        Option<BTreeMap<String, PropertyValue>>,
+        Vec<(String, FieldFreqs)>,
+    );
+
+    fn snapshot(index: &KnowledgeIndex) -> Vec<NoteSnapshot> {
+        index
+            .all_paths_sorted()
+            .into_iter()
+            .map(|path| {
+                let mut tokens = index.notes[&path]
+                    .terms
+                    .iter()
+                    .map(|(term, freqs)| (term.clone(), *freqs))
+                    .collect::<Vec<_>>();
+                tokens.sort();
+                (
+                    index.note_summary(&path),
+                    index.note_properties(&path).cloned(),
+                    tokens,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn warm_start_reuses_unchanged_notes() {
+        let (temp_dir, vault) = setup("warm");
+        let (cold, stats) = KnowledgeIndex::open_with_cache(&vault).expect("cold open");
+        assert_eq!(stats.status, IndexCacheStatus::Missing);
+        assert_eq!((stats.reused, stats.parsed, stats.dropped), (0, 3, 0));
+        assert!(KnowledgeIndex::cache_path(&vault).is_file());
+
+        let (warm, stats) = KnowledgeIndex::open_with_cache(&vault).expect("warm open");
+        assert_eq!(stats.status, IndexCacheStatus::Warm);
+        assert_eq!((stats.reused, stats.parsed, stats.dropped), (3, 0, 0));
+        assert_eq!(snapshot(&warm), snapshot(&cold));
+        assert_eq!(warm.path_for_note_id("na").as_deref(), Some("notes/A.md"));
+        let search = |index: &KnowledgeIndex| {
+            index
+                .search(&vault, "borrow", SearchOptions::default())
+                .hits
+                .into_iter()
+                .map(|hit| hit.path)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(search(&warm), vec!["notes/A.md".to_string()]);
+        let query = PropertyQuery::parse("due < 2026-10-20T08:00Z").expect("query");
+        assert_eq!(
+            warm.query_properties(&query),
+            vec!["notes/A.md".to_string()]
+        );
+
+        // Same bytes with a new mtime: matched by hash, not parsed again.
+        let file = fs::File::options()
+            .write(true)
+            .open(temp_dir.join("notes/C.md"))
+            .expect("open C");
+        file.set_modified(SystemTime::now() + Duration::from_secs(5))
+            .expect("touch C");
+        drop(file);
+        fs::write(temp_dir.join("notes/A.md"), "# Alpha\nrewritten\n").expect("edit A");
+        fs::remove_file(temp_dir.join("notes/B.md")).expect("remove B");
+        let (updated, stats) = KnowledgeIndex::open_with_cache(&vault).expect("incremental open");
+        assert_eq!((stats.reused, stats.parsed, stats.dropped), (1, 1, 1));
+        assert!(search(&updated).is_empty());
+        assert_eq!(updated.path_for_note_id("na"), None);
+
+        let (_, stats) = KnowledgeIndex::open_with_cache(&vault).expect("reopen");
+        assert_eq!((stats.reused, stats.parsed, stats.dropped), (2, 0, 0));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn invalid_cache_falls_back_to_full_rebuild() {
+        let (temp_dir, vault) = setup("invalid");
+        let (cold, _) = KnowledgeIndex::open_with_cache(&vault).expect("cold open");
+        let cache_path = KnowledgeIndex::cache_path(&vault);
+        let valid = fs::read(&cache_path).expect("read cache");
+
+        let mut flipped = valid.clone();
+        let middle = flipped.len() / 2;
+        flipped[middle] ^= 0xFF;
+        let mut bumped = valid.clone();
+        bumped[4..8].copy_from_slice(&(KNOWLEDGE_CACHE_VERSION + 1).to_le_bytes());
+        let cases = [
+            (
+                flipped,
+                IndexCacheStatus::Corrupt {
+                    reason: "checksum mismatch".to_string(),
+                },
+            ),
+            (
+                valid[..valid.len() / 3].to_vec(),
+                IndexCacheStatus::Corrupt {
+                    reason: "checksum mismatch".to_string(),
+                },
+            ),
+            (
+                b"garbage".to_vec(),
+                IndexCacheStatus::Corrupt {
+                    reason: "not a knowledge cache".to_string(),
+                },
+            ),
+            (
+                bumped,
+                IndexCacheStatus::VersionMismatch {
+                    found: KNOWLEDGE_CACHE_VERSION + 1,
+                },
+            ),
+        ];
+        for (bytes, expected) in cases {
+            fs::write(&cache_path, bytes).expect("write bad cache");
+            let (index, stats) = KnowledgeIndex::open_with_cache(&vault).expect("rebuild");
+            assert_eq!(stats.status, expected);
+            assert_eq!((stats.reused, stats.parsed), (0, 3));
+            assert_eq!(snapshot(&index), snapshot(&cold));
+            assert_eq!(fs::read(&cache_path).expect("rewritten"), valid);
+        }
+
+        let mut settings = crate::settings::AppSettings::default();
+        settings
+            .properties
+            .types
+            .insert("rank".to_string(), PropertyType::Text);
+        crate::settings::save_project_settings(&temp_dir, &settings).expect("save settings");
+        let (retyped, stats) = KnowledgeIndex::open_with_cache(&vault).expect("retyped open");
+        assert_eq!(stats.status, IndexCacheStatus::PropertyTypesChanged);
+        assert_eq!(
+            retyped
+                .note_properties("notes/A.md")
+                .and_then(|props| props.get("rank")),
+            Some(&PropertyValue::Text("-2.5".to_string()))
+        );
+
+        settings.search.stem_latin = true;
+        crate::settings::save_project_settings(&temp_dir, &settings).expect("save settings");
+        let (_, stats) = KnowledgeIndex::open_with_cache(&vault).expect("restemmed open");
+        assert_eq!(stats.status, IndexCacheStatus::TokenizerChanged);
+        assert_eq!((stats.reused, stats.parsed), (0, 3));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
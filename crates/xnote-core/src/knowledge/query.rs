@@ -0,0 +1,393 @@
+//! Evaluating a search against the index.
+//!
+//! Terms and field filters are decided from indexed fields, so a query is first compiled
+//! into the set of matching paths per part. Phrases, strict (case-sensitive or whole-word)
+//! terms and regular expressions keep a candidate set from the index and are confirmed
+//! against the note content.
+
+use super::{IndexedNote, KnowledgeIndex, SearchOptions};
+use crate::search_query::{
+    QueryFilter, SearchExpr, SearchQuery, SearchQueryError, SearchQueryErrorKind,
+};
+use regex::{Regex, RegexBuilder};
+use regex_syntax::hir::literal::Extractor;
+use regex_syntax::hir::translate::TranslatorBuilder;
+use std::collections::HashSet;
+
+pub(super) enum CompiledQuery {
+    Paths(HashSet<String>),
+    /// Notes whose content the pattern matches.
+    Text {
+        pattern: Regex,
+        /// Notes that can match, from the index; `None` when any note might.
+        candidates: Option<HashSet<String>>,
+    },
+    Not(Box<CompiledQuery>),
+    And(Vec<CompiledQuery>),
+    Or(Vec<CompiledQuery>),
+}
+
+impl CompiledQuery {
+    /// Paths that can match, or `None` when any note might.
+    pub(super) fn candidates(&self) -> Option<HashSet<String>> {
+        match self {
+            Self::Paths(paths) => Some(paths.clone()),
+            Self::Text { candidates, .. } => candidates.clone(),
+            Self::Not(_) => None,
+            Self::And(parts) => parts
+                .iter()
+                .filter_map(Self::candidates)
+                .reduce(|acc, set| acc.intersection(&set).cloned().collect()),
+            Self::Or(parts) => {
+                parts
+                    .iter()
+                    .map(Self::candidates)
+                    .try_fold(HashSet::new(), |mut acc, set| {
+                        acc.extend(set?);
+                        Some(acc)
+                    })
+            }
+        }
+    }
+
+    pub(super) fn needs_content(&self) -> bool {
+        match self {
+            Self::Paths(_) => false,
+            Self::Text { .. } => true,
+            Self::Not(inner) => inner.needs_content(),
+            Self::And(parts) | Self::Or(parts) => parts.iter().any(Self::needs_content),
+        }
+    }
+
+    /// `content` is the note text, required when [`Self::needs_content`].
+    pub(super) fn matches(&self, path: &str, content: Option<&str>) -> bool {
+        match self {
+            Self::Paths(paths) => paths.contains(path),
+            Self::Text {
+                pattern,
+                candidates,
+            } => {
+                candidates.as_ref().is_none_or(|set| set.contains(path))
+                    && content.is_some_and(|content| pattern.is_match(content))
+            }
+            Self::Not(inner) => !inner.matches(path, content),
+            Self::And(parts) => parts.iter().all(|part| part.matches(path, content)),
+            Self::Or(parts) => parts.iter().any(|part| part.matches(path, content)),
+        }
+    }
+}
+
+/// A search compiled for one set of [`SearchOptions`].
+pub(super) struct SearchPlan {
+    pub compiled: CompiledQuery,
+    /// Terms the hits are ranked by.
+    pub tokens: Vec<String>,
+    /// Text for the path and title ranking heuristic.
+    pub fallback_text: String,
+    /// Finds the matches shown in preview lines.
+    pub highlight: Option<Regex>,
+    /// Preview lines are the ones `highlight` matches. Otherwise a line also shows when
+    /// it has one of `tokens` or contains one of `needles`.
+    pub strict_lines: bool,
+    pub needles: Vec<String>,
+}
+
+impl KnowledgeIndex {
+    /// `None` for a blank query.
+    pub(super) fn plan_search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Option<SearchPlan>, SearchQueryError> {
+        if options.regex {
+            return self.plan_regex(query, options).map(Some);
+        }
+        let parsed = SearchQuery::parse(query)?;
+        let Some(expr) = parsed.expr.as_ref() else {
+            return Ok(None);
+        };
+
+        // Positive words and phrases rank the matches and pick the preview lines; the
+        // rest of the query only decides which notes match.
+        let mut tokens = Vec::new();
+        let mut needles = Vec::new();
+        let mut highlights = Vec::new();
+        for part in parsed.positive_text() {
+            let (SearchExpr::Term(text) | SearchExpr::Phrase(text)) = part else {
+                continue;
+            };
+            let part_tokens = self.tokenizer.tokenize(text);
+            if part_tokens.is_empty() || matches!(part, SearchExpr::Phrase(_)) {
+                needles.push(text.to_lowercase());
+            }
+            tokens.extend(part_tokens);
+            highlights.push(text_pattern(text, options));
+        }
+        tokens.sort();
+        tokens.dedup();
+        let highlight = (!highlights.is_empty())
+            .then(|| build_regex(&highlights.join("|"), options.case_sensitive))
+            .and_then(Result::ok);
+
+        Ok(Some(SearchPlan {
+            compiled: self.compile_query(expr, options),
+            fallback_text: needles.join(" "),
+            tokens,
+            highlight,
+            strict_lines: options.case_sensitive || options.whole_word,
+            needles,
+        }))
+    }
+
+    /// The whole query as one pattern over note lines. Candidates come from the literal
+    /// prefixes of the pattern when the tokenizer allows looking those up.
+    fn plan_regex(
+        &self,
+        pattern: &str,
+        options: &SearchOptions,
+    ) -> Result<SearchPlan, SearchQueryError> {
+        let invalid = |message: String, span: std::ops::Range<usize>| SearchQueryError {
+            kind: SearchQueryErrorKind::InvalidRegex(message),
+            span,
+        };
+        let ast = regex_syntax::ast::parse::Parser::new()
+            .parse(pattern)
+            .map_err(|err| {
+                let span = err.span();
+                invalid(err.kind().to_string(), span.start.offset..span.end.offset)
+            })?;
+        let hir = TranslatorBuilder::new()
+            .case_insensitive(!options.case_sensitive)
+            .build()
+            .translate(pattern, &ast)
+            .map_err(|err| {
+                let span = err.span();
+                invalid(err.kind().to_string(), span.start.offset..span.end.offset)
+            })?;
+        let wrapped = if options.whole_word {
+            format!(r"\b{{start-half}}(?:{pattern})\b{{end-half}}")
+        } else {
+            pattern.to_string()
+        };
+        let regex = build_regex(&wrapped, options.case_sensitive)
+            .map_err(|err| invalid(err.to_string(), 0..pattern.len()))?;
+
+        let candidates = if self.tokenizer.terms_are_substrings() {
+            Extractor::new()
+                .extract(&hir)
+                .literals()
+                .and_then(|literals| {
+                    literals
+                        .iter()
+                        .map(|literal| {
+                            let text = std::str::from_utf8(literal.as_bytes()).ok()?;
+                            self.literal_postings(&text.to_lowercase())
+                        })
+                        .try_fold(HashSet::new(), |mut acc, set| {
+                            acc.extend(set?);
+                            Some(acc)
+                        })
+                })
+        } else {
+            None
+        };
+
+        Ok(SearchPlan {
+            compiled: CompiledQuery::Text {
+                pattern: regex.clone(),
+                candidates,
+            },
+            tokens: Vec::new(),
+            fallback_text: String::new(),
+            highlight: Some(regex),
+            strict_lines: true,
+            needles: Vec::new(),
+        })
+    }
+
+    /// Notes whose terms could contain `literal`, or `None` when it has no terms. The
+    /// first term may end a longer word and the last may start one.
+    fn literal_postings(&self, literal: &str) -> Option<HashSet<String>> {
+        let tokens = self.tokenizer.tokenize(literal);
+        let last = tokens.len().checked_sub(1)?;
+        let mut out: Option<HashSet<String>> = None;
+        for (ix, token) in tokens.iter().enumerate() {
+            let fits = |key: &str| match (ix == 0, ix == last) {
+                (true, true) => key.contains(token.as_str()),
+                (true, false) => key.ends_with(token.as_str()),
+                (false, true) => key.starts_with(token.as_str()),
+                (false, false) => key == token,
+            };
+            let paths = self
+                .inverted
+                .iter()
+                .filter(|(key, _)| fits(key))
+                .flat_map(|(_, paths)| paths.iter().cloned())
+                .collect::<HashSet<_>>();
+            out = Some(match out {
+                Some(acc) => acc.intersection(&paths).cloned().collect(),
+                None => paths,
+            });
+        }
+        out
+    }
+
+    fn compile_query(&self, expr: &SearchExpr, options: &SearchOptions) -> CompiledQuery {
+        let strict = options.case_sensitive || options.whole_word;
+        match expr {
+            SearchExpr::Term(word) if !strict => {
+                // Terms come from the index; the path and title substring keeps partly
+                // typed words and punctuation-only queries finding notes by name.
+                let word_lower = word.to_lowercase();
+                let mut paths = self.term_postings(&self.tokenizer.tokenize(word));
+                paths.extend(self.paths_where(|note| {
+                    note.path_lower.contains(&word_lower) || note.title_lower.contains(&word_lower)
+                }));
+                CompiledQuery::Paths(paths)
+            }
+            // The index is case-folded and tokenized, so a strict term is confirmed in the
+            // path, title or content.
+            SearchExpr::Term(word) => match self.text_query(word, options) {
+                CompiledQuery::Text {
+                    pattern,
+                    candidates,
+                } => CompiledQuery::Or(vec![
+                    CompiledQuery::Paths(self.paths_where(|note| {
+                        pattern.is_match(&note.path) || pattern.is_match(&note.title)
+                    })),
+                    CompiledQuery::Text {
+                        pattern,
+                        candidates,
+                    },
+                ]),
+                other => other,
+            },
+            SearchExpr::Phrase(phrase) => self.text_query(phrase, options),
+            SearchExpr::Filter(filter) => CompiledQuery::Paths(self.filter_paths(filter)),
+            SearchExpr::Not(inner) => {
+                CompiledQuery::Not(Box::new(self.compile_query(inner, options)))
+            }
+            SearchExpr::And(parts) => CompiledQuery::And(
+                parts
+                    .iter()
+                    .map(|part| self.compile_query(part, options))
+                    .collect(),
+            ),
+            SearchExpr::Or(parts) => CompiledQuery::Or(
+                parts
+                    .iter()
+                    .map(|part| self.compile_query(part, options))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Content matches of literal text, with candidates from the term table when the
+    /// tokenizer allows substring lookups.
+    fn text_query(&self, text: &str, options: &SearchOptions) -> CompiledQuery {
+        match build_regex(&text_pattern(text, options), options.case_sensitive) {
+            Ok(pattern) => CompiledQuery::Text {
+                pattern,
+                candidates: if self.tokenizer.terms_are_substrings() {
+                    self.literal_postings(&text.to_lowercase())
+                } else {
+                    None
+                },
+            },
+            Err(_) => CompiledQuery::Paths(HashSet::new()),
+        }
+    }
+
+    /// Notes containing every one of `tokens`; none for no tokens.
+    fn term_postings(&self, tokens: &[String]) -> HashSet<String> {
+        let mut sets = Vec::new();
+        for token in tokens {
+            let Some(paths) = self.inverted.get(token) else {
+                return HashSet::new();
+            };
+            sets.push(paths);
+        }
+        sets.sort_by_key(|set| set.len());
+        let Some((first, rest)) = sets.split_first() else {
+            return HashSet::new();
+        };
+        first
+            .iter()
+            .filter(|path| rest.iter().all(|set| set.contains(*path)))
+            .cloned()
+            .collect()
+    }
+
+    fn filter_paths(&self, filter: &QueryFilter) -> HashSet<String> {
+        match filter {
+            QueryFilter::LinksTo(target) => match self.resolve_link_target(target) {
+                Some(path) => self.backlinks_for(&path, usize::MAX).into_iter().collect(),
+                // Unresolved targets still match notes with a dangling link to them.
+                None => {
+                    let target = target.trim().to_lowercase();
+                    self.paths_where(|note| note.links_lower.contains(&target))
+                }
+            },
+            QueryFilter::LinkedFrom(source) => self
+                .resolve_link_target(source)
+                .and_then(|path| self.notes.get(&path))
+                .map(|note| {
+                    note.links
+                        .iter()
+                        .filter_map(|link| self.resolve_link_target(link))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            QueryFilter::Tag(tag) => self.paths_where(|note| {
+                note.tags_lower.iter().any(|have| {
+                    have == tag
+                        || have
+                            .strip_prefix(tag.as_str())
+                            .is_some_and(|rest| rest.starts_with('/'))
+                })
+            }),
+            QueryFilter::Path(text) => self.paths_where(|note| note.path_lower.contains(text)),
+            QueryFilter::Title(text) => self.paths_where(|note| note.title_lower.contains(text)),
+            QueryFilter::Id(id) => {
+                self.paths_where(|note| note.note_id_lower.as_deref() == Some(id.as_str()))
+            }
+            QueryFilter::HasLink => self.paths_where(|note| !note.links.is_empty()),
+            QueryFilter::Property(expr) => self.paths_where(|note| expr.matches(&note.properties)),
+        }
+    }
+
+    fn paths_where(&self, keep: impl Fn(&IndexedNote) -> bool) -> HashSet<String> {
+        self.notes
+            .values()
+            .filter(|note| keep(note))
+            .map(|note| note.path.clone())
+            .collect()
+    }
+}
+
+/// A pattern for literal query text. Whole words must not touch word characters on
+/// either side, which also works for text that starts or ends with punctuation.
+/// `text` as one literal pattern over note lines, honouring `case_sensitive` and
+/// `whole_word`; `None` when `text` is blank.
+pub fn literal_pattern(text: &str, options: &SearchOptions) -> Option<Regex> {
+    if text.trim().is_empty() {
+        return None;
+    }
+    build_regex(&text_pattern(text, options), options.case_sensitive).ok()
+}
+
+fn text_pattern(text: &str, options: &SearchOptions) -> String {
+    let escaped = regex::escape(text);
+    if options.whole_word {
+        format!(r"\b{{start-half}}{escaped}\b{{end-half}}")
+    } else {
+        escaped
+    }
+}
+
+fn build_regex(pattern: &str, case_sensitive: bool) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .multi_line(true)
+        .build()
+}
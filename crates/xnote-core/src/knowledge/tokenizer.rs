@@ -0,0 +1,207 @@
+//! Splitting note and query text into search terms.
+//!
+//! Index and query text go through the same [`Tokenizer`], so a term only has to be
+//! segmented the same way on both sides to be found. CJK scripts are written without
+//! spaces; they are cut into overlapping bigrams (or single characters) instead of words.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+pub trait Tokenizer: Debug + Send + Sync {
+    /// Lowercased terms of `text`, in order, repeats included.
+    fn tokenize(&self, text: &str) -> Vec<String>;
+
+    /// Identifies the segmentation; terms cached under a different fingerprint are
+    /// re-derived.
+    fn fingerprint(&self) -> String;
+
+    /// Whether every term is a substring of the lowercased text it came from, so regex
+    /// search may narrow its candidates by looking pattern literals up among the terms.
+    fn terms_are_substrings(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum CjkSegmentation {
+    /// Overlapping character pairs: `知识库` gives `知识`, `识库`.
+    #[default]
+    Bigram,
+    /// Every character on its own: smaller postings, looser matches.
+    Unigram,
+}
+
+impl CjkSegmentation {
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::Bigram => "bigram",
+            Self::Unigram => "unigram",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "bigram" => Some(Self::Bigram),
+            "unigram" => Some(Self::Unigram),
+            _ => None,
+        }
+    }
+}
+
+/// The built-in tokenizer: words of letters, digits, `_` and `-` for alphabetic scripts,
+/// and runs of CJK characters segmented per [`CjkSegmentation`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextTokenizer {
+    pub cjk: CjkSegmentation,
+    /// Strip common English suffixes, so `linking`, `linked` and `links` all give `link`.
+    pub stem_latin: bool,
+}
+
+impl Tokenizer for TextTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut word = String::new();
+        let mut cjk_run = Vec::new();
+
+        for ch in text.chars() {
+            if is_cjk(ch) {
+                self.flush_word(&mut word, &mut out);
+                cjk_run.push(ch);
+            } else if ch.is_alphanumeric() || ch == '_' || ch == '-' {
+                self.flush_cjk(&mut cjk_run, &mut out);
+                word.extend(ch.to_lowercase());
+            } else {
+                self.flush_word(&mut word, &mut out);
+                self.flush_cjk(&mut cjk_run, &mut out);
+            }
+        }
+        self.flush_word(&mut word, &mut out);
+        self.flush_cjk(&mut cjk_run, &mut out);
+
+        out
+    }
+
+    fn fingerprint(&self) -> String {
+        format!(
+            "text:{}:{}",
+            self.cjk.as_tag(),
+            if self.stem_latin { "stem" } else { "plain" }
+        )
+    }
+
+    fn terms_are_substrings(&self) -> bool {
+        !self.stem_latin
+    }
+}
+
+impl TextTokenizer {
+    fn flush_word(&self, word: &mut String, out: &mut Vec<String>) {
+        if word.is_empty() {
+            return;
+        }
+        let word = std::mem::take(word);
+        out.push(if self.stem_latin {
+            stem_english(&word)
+        } else {
+            word
+        });
+    }
+
+    fn flush_cjk(&self, run: &mut Vec<char>, out: &mut Vec<String>) {
+        match (self.cjk, run.len()) {
+            (_, 0) => return,
+            (CjkSegmentation::Unigram, _) | (CjkSegmentation::Bigram, 1) => {
+                out.extend(run.iter().map(|ch| ch.to_string()));
+            }
+            (CjkSegmentation::Bigram, _) => {
+                out.extend(run.windows(2).map(|pair| pair.iter().collect::<String>()));
+            }
+        }
+        run.clear();
+    }
+}
+
+/// Han ideographs, kana and Hangul syllables.
+fn is_cjk(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{3040}'..='\u{30FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{AC00}'..='\u{D7AF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FA1F}'
+    )
+}
+
+/// Conservative suffix stripping for lowercase ASCII words; anything else is returned
+/// unchanged. Stems are only ever compared with other stems, so they need not be words.
+fn stem_english(word: &str) -> String {
+    if word.len() <= 3 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+        return word.to_string();
+    }
+    if let Some(stem) = word.strip_suffix("ies").filter(|stem| stem.len() >= 2) {
+        return format!("{stem}y");
+    }
+    if let Some(stem) = word.strip_suffix("sses") {
+        return format!("{stem}ss");
+    }
+    for suffix in ["ing", "ed"] {
+        if let Some(stem) = word.strip_suffix(suffix).filter(|stem| stem.len() >= 4) {
+            return undouble(stem).to_string();
+        }
+    }
+    if word.ends_with('s') && !word.ends_with("ss") && !word.ends_with("us") {
+        return word[..word.len() - 1].to_string();
+    }
+    word.to_string()
+}
+
+/// `runn` to `run`, keeping the doubled `l`, `s` and `z` of `spell`, `pass`, `buzz`.
+fn undouble(stem: &str) -> &str {
+    let bytes = stem.as_bytes();
+    let [.., a, b] = bytes else {
+        return stem;
+    };
+    if a == b && !matches!(a, b'a' | b'e' | b'i' | b'o' | b'u' | b'l' | b's' | b'z') {
+        &stem[..stem.len() - 1]
+    } else {
+        stem
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_cjk_runs_and_stems_latin_words() {
+        let bigram = TextTokenizer::default();
+        assert_eq!(
+            bigram.tokenize("Rust编程：知识库 v2_draft 的"),
+            vec!["rust", "编程", "知识", "识库", "v2_draft", "的"]
+        );
+        assert_eq!(
+            bigram.tokenize("ひらがなカナ"),
+            vec!["ひら", "らが", "がな", "なカ", "カナ"]
+        );
+
+        let unigram = TextTokenizer {
+            cjk: CjkSegmentation::Unigram,
+            stem_latin: false,
+        };
+        assert_eq!(unigram.tokenize("知识库"), vec!["知", "识", "库"]);
+        assert_ne!(bigram.fingerprint(), unigram.fingerprint());
+
+        let stemming = TextTokenizer {
+            cjk: CjkSegmentation::Bigram,
+            stem_latin: true,
+        };
+        assert_eq!(
+            stemming.tokenize("Linking linked links studies classes running status the"),
+            vec!["link", "link", "link", "study", "class", "run", "status", "the"]
+        );
+        assert_eq!(stemming.tokenize("笔记 notes"), vec!["笔记", "note"]);
+    }
+}
@@ -1,15 +1,27 @@
 pub mod ai;
+pub mod anchors;
+pub mod atomic_write;
 pub mod command;
+pub mod diff;
 pub mod editor;
+pub mod frontmatter;
+pub mod info_meta;
 pub mod keybind;
 pub mod knowledge;
 pub mod markdown;
+pub mod migrate;
 pub mod note_meta;
 pub mod paths;
 pub mod plugin;
 pub mod plugin_protocol;
 pub mod plugin_transport;
+pub mod properties;
+pub mod relation_types;
+pub mod relations;
+pub mod resource_meta;
+pub mod search_query;
 pub mod settings;
+pub mod ulid;
 pub mod vcp;
 pub mod vault;
 pub mod watch;
@@ -429,7 +429,7 @@ fn heading_level_to_u8(level: HeadingLevel) -> u8 {
     }
 }
 
-fn markdown_heading_level(line: &str) -> Option<u8> {
+pub(crate) fn markdown_heading_level(line: &str) -> Option<u8> {
     let mut count = 0u8;
     for c in line.chars() {
         if c == '#' {
@@ -0,0 +1,178 @@
+//! Versioned JSON schema migrations shared by note meta and settings.
+//!
+//! A schema is upgraded one version at a time on the raw JSON object, so fields a
+//! step does not know about (`extra`, `ext`, newer keys) pass through untouched.
+
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+/// Upgrades a JSON object from version `n` to `n + 1` in place.
+pub type MigrationStep = fn(&mut Map<String, Value>) -> Result<()>;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SchemaMigrations {
+    /// Human-readable schema name used in errors, e.g. `note meta`.
+    pub schema: &'static str,
+    /// Key holding the version number; a missing key means version 0.
+    pub version_key: &'static str,
+    pub current: u32,
+    /// `steps[n]` upgrades version `n` to `n + 1`; holds exactly `current` steps.
+    pub steps: &'static [MigrationStep],
+}
+
+/// Returned (inside `anyhow::Error`) for files written by a newer XNote.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsupportedSchemaVersion {
+    pub schema: &'static str,
+    pub found: u32,
+    pub supported: u32,
+}
+
+impl std::fmt::Display for UnsupportedSchemaVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} version {} is newer than the supported version {}",
+            self.schema, self.found, self.supported
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedSchemaVersion {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MigrationOutcome {
+    pub value: Value,
+    pub from_version: u32,
+    pub to_version: u32,
+}
+
+impl MigrationOutcome {
+    pub fn migrated(&self) -> bool {
+        self.from_version != self.to_version
+    }
+}
+
+impl SchemaMigrations {
+    pub fn detect_version(&self, value: &Value) -> Result<u32> {
+        let Some(object) = value.as_object() else {
+            return Err(anyhow!("{} must be a JSON object", self.schema));
+        };
+        match object.get(self.version_key) {
+            None | Some(Value::Null) => Ok(0),
+            Some(raw) => raw
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| anyhow!("{} {} is not a version number", self.schema, raw)),
+        }
+    }
+
+    /// Applies every step from the detected version up to `current`.
+    pub fn migrate(&self, value: Value) -> Result<MigrationOutcome> {
+        debug_assert_eq!(self.steps.len(), self.current as usize);
+        let from_version = self.detect_version(&value)?;
+        if from_version > self.current {
+            return Err(UnsupportedSchemaVersion {
+                schema: self.schema,
+                found: from_version,
+                supported: self.current,
+            }
+            .into());
+        }
+
+        let Value::Object(mut object) = value else {
+            unreachable!("detect_version accepts only objects");
+        };
+        for version in from_version..self.current {
+            let step = self.steps[version as usize];
+            step(&mut object).map_err(|err| {
+                anyhow!(
+                    "migrate {} v{version} -> v{}: {err:#}",
+                    self.schema,
+                    version + 1
+                )
+            })?;
+            object.insert(self.version_key.to_string(), Value::from(version + 1));
+        }
+
+        Ok(MigrationOutcome {
+            value: Value::Object(object),
+            from_version,
+            to_version: self.current,
+        })
+    }
+}
+
+/// Moves `from` to `to` unless `to` is already set.
+pub fn rename_key(object: &mut Map<String, Value>, from: &str, to: &str) {
+    if let Some(value) = object.remove(from) {
+        object.entry(to.to_string()).or_insert(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn add_title(object: &mut Map<String, Value>) -> Result<()> {
+        object.insert("title".to_string(), Value::from("untitled"));
+        Ok(())
+    }
+
+    fn split_name(object: &mut Map<String, Value>) -> Result<()> {
+        let name = object
+            .remove("name")
+            .and_then(|v| v.as_str().map(str::to_string))
+            .ok_or_else(|| anyhow!("name is missing"))?;
+        let (first, last) = name.split_once(' ').unwrap_or((&name, ""));
+        object.insert("first".to_string(), Value::from(first));
+        object.insert("last".to_string(), Value::from(last));
+        Ok(())
+    }
+
+    const DEMO: SchemaMigrations = SchemaMigrations {
+        schema: "demo",
+        version_key: "v",
+        current: 2,
+        steps: &[add_title, split_name],
+    };
+
+    #[test]
+    fn migrations_chain_from_detected_version_and_keep_unknown_keys() {
+        let out = DEMO
+            .migrate(json!({"name": "Ada Lovelace", "extra": {"keep": 1}}))
+            .expect("migrate v0");
+        assert!(out.migrated());
+        assert_eq!(out.from_version, 0);
+        assert_eq!(
+            out.value,
+            json!({"v": 2, "title": "untitled", "first": "Ada", "last": "Lovelace", "extra": {"keep": 1}})
+        );
+
+        let out = DEMO
+            .migrate(json!({"v": 1, "name": "Alan Turing", "title": "t"}))
+            .expect("migrate v1");
+        assert_eq!(out.from_version, 1);
+        assert_eq!(out.value["title"], "t");
+
+        let current = json!({"v": 2, "first": "x"});
+        let out = DEMO.migrate(current.clone()).expect("current");
+        assert!(!out.migrated());
+        assert_eq!(out.value, current);
+    }
+
+    #[test]
+    fn migrations_reject_newer_and_malformed_versions() {
+        let err = DEMO.migrate(json!({"v": 3})).expect_err("newer");
+        let typed = err
+            .downcast_ref::<UnsupportedSchemaVersion>()
+            .expect("typed error");
+        assert_eq!((typed.found, typed.supported), (3, 2));
+
+        assert!(DEMO.migrate(json!({"v": "two"})).is_err());
+        assert!(DEMO.migrate(json!([1, 2])).is_err());
+        let err = DEMO.migrate(json!({"v": 1})).expect_err("step failure");
+        assert!(format!("{err:#}").contains("demo v1 -> v2"));
+    }
+}
@@ -1,11 +1,20 @@
-use anyhow::{anyhow, Result};
+use crate::frontmatter::{
+    detect_line_ending, frontmatter_bounds, set_frontmatter_property, Frontmatter, YamlValue,
+};
+use crate::migrate::{MigrationStep, SchemaMigrations};
+use crate::ulid::Ulid;
+use anyhow::{anyhow, Context as _, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
 
 pub const NOTE_META_VERSION_V1: u32 = 1;
-static NOTE_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+pub const NOTE_META_VERSION_CURRENT: u32 = NOTE_META_VERSION_V1;
+pub const NOTE_META_MIGRATIONS: SchemaMigrations = SchemaMigrations {
+    schema: "note meta",
+    version_key: "version",
+    current: NOTE_META_VERSION_CURRENT,
+    steps: &[migrate_note_meta_v0_to_v1 as MigrationStep],
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct NoteMetaTarget {
@@ -107,6 +116,27 @@ impl NoteMetaV1 {
     }
 }
 
+/// Parses note meta JSON of any supported version, upgrading it in memory.
+///
+/// Returns the meta and the version it was stored as.
+pub fn parse_note_meta_json(content: &str) -> Result<(NoteMetaV1, u32)> {
+    let value = serde_json::from_str(content).with_context(|| "parse note meta json")?;
+    let outcome = NOTE_META_MIGRATIONS.migrate(value)?;
+    let meta: NoteMetaV1 =
+        serde_json::from_value(outcome.value).with_context(|| "decode migrated note meta")?;
+    meta.validate()?;
+    Ok((meta, outcome.from_version))
+}
+
+/// v0 is meta written before the `version` key existed; it already has the v1 shape,
+/// so stamping the version (done by the pipeline) is the whole upgrade.
+fn migrate_note_meta_v0_to_v1(object: &mut Map<String, Value>) -> Result<()> {
+    if !object.get("id").is_some_and(Value::is_string) {
+        return Err(anyhow!("unversioned note meta has no string id"));
+    }
+    Ok(())
+}
+
 pub fn normalize_note_id(note_id: &str) -> Result<String> {
     let id = note_id.trim();
     if id.is_empty() {
@@ -125,33 +155,31 @@ pub fn normalize_note_id(note_id: &str) -> Result<String> {
     Ok(id.to_string())
 }
 
+/// New note id: a ULID, monotonic within the process and random across machines.
 pub fn generate_note_id() -> String {
-    let millis = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|duration| duration.as_millis() as u64)
-        .unwrap_or_default();
-    let pid = std::process::id() as u64;
-    let seq = NOTE_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
-    format!("N{millis:011X}{pid:05X}{seq:04X}")
+    Ulid::generate().to_string()
 }
 
-pub fn extract_note_id_from_frontmatter(content: &str) -> Option<String> {
-    let (body_start, body_end) = frontmatter_body_bounds(content)?;
-    let frontmatter = &content[body_start..body_end];
-    for line in frontmatter.lines() {
-        let trimmed = line.trim();
-        let Some((key, value)) = trimmed.split_once(':') else {
-            continue;
-        };
-        if !key.trim().eq_ignore_ascii_case("id") {
-            continue;
-        }
-        let normalized = value.trim().trim_matches('"').trim_matches('\'').trim();
-        if let Ok(note_id) = normalize_note_id(normalized) {
-            return Some(note_id);
-        }
+/// Creation time encoded in a note id.
+///
+/// Understands ULIDs and the legacy `N{millis:011X}{pid:05X}{seq:04X}` ids; other ids
+/// carry no timestamp.
+pub fn note_id_timestamp_ms(note_id: &str) -> Option<u64> {
+    let id = note_id.trim();
+    if let Ok(ulid) = Ulid::parse(id) {
+        return Some(ulid.timestamp_ms());
     }
-    None
+    let hex = id.strip_prefix('N').filter(|rest| rest.len() == 20)?;
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u64::from_str_radix(&hex[..11], 16).ok()
+}
+
+pub fn extract_note_id_from_frontmatter(content: &str) -> Option<String> {
+    let frontmatter = Frontmatter::parse_lenient(content)?;
+    let raw = frontmatter.get("id")?.as_scalar_text()?;
+    normalize_note_id(&raw).ok()
 }
 
 pub fn ensure_frontmatter_note_id(
@@ -163,58 +191,34 @@ pub fn ensure_frontmatter_note_id(
     }
 
     let note_id = normalize_note_id(candidate_id)?;
-    let newline = detect_line_ending(content);
-
-    if let Some((_body_start, body_end, closing_start)) = frontmatter_bounds(content) {
-        let mut out = String::with_capacity(content.len() + note_id.len() + 16);
-        out.push_str(&content[..body_end]);
-        out.push_str(&format!("id: {note_id}{newline}"));
-        out.push_str(&content[closing_start..]);
-        return Ok((out, note_id, true));
-    }
-
-    let out = format!("---{newline}id: {note_id}{newline}---{newline}{content}");
+    let out = match set_frontmatter_property(content, "id", &YamlValue::String(note_id.clone())) {
+        Ok(out) => out,
+        // YAML the parser rejects still gets its id line before the closing fence, where
+        // the lenient reader finds it.
+        Err(err) => {
+            let Some(bounds) = frontmatter_bounds(content) else {
+                return Err(err);
+            };
+            let at = bounds.yaml.end;
+            let newline = detect_line_ending(content);
+            format!("{}id: {note_id}{newline}{}", &content[..at], &content[at..])
+        }
+    };
     Ok((out, note_id, true))
 }
 
-fn detect_line_ending(content: &str) -> &'static str {
-    if content.contains("\r\n") {
-        "\r\n"
-    } else {
-        "\n"
+/// Rewrites the value of the frontmatter `id:` property, keeping the rest of the note untouched.
+///
+/// Returns `None` when the note has no frontmatter `id:` property.
+pub fn replace_frontmatter_note_id(content: &str, new_id: &str) -> Result<Option<String>> {
+    let new_id = normalize_note_id(new_id)?;
+    let Some(frontmatter) = Frontmatter::parse(content)? else {
+        return Ok(None);
+    };
+    if frontmatter.entry("id").is_none() {
+        return Ok(None);
     }
-}
-
-fn frontmatter_body_bounds(content: &str) -> Option<(usize, usize)> {
-    let (body_start, body_end, _) = frontmatter_bounds(content)?;
-    Some((body_start, body_end))
-}
-
-fn frontmatter_bounds(content: &str) -> Option<(usize, usize, usize)> {
-    let first_line_end = content.find('\n')?;
-    if content[..first_line_end].trim_end_matches('\r') != "---" {
-        return None;
-    }
-
-    let body_start = first_line_end + 1;
-    let mut cursor = body_start;
-
-    while cursor <= content.len() {
-        let line_end = content[cursor..]
-            .find('\n')
-            .map(|rel| cursor + rel)
-            .unwrap_or(content.len());
-        let line = &content[cursor..line_end];
-        if line.trim_end_matches('\r') == "---" {
-            return Some((body_start, cursor, cursor));
-        }
-        if line_end == content.len() {
-            break;
-        }
-        cursor = line_end + 1;
-    }
-
-    None
+    set_frontmatter_property(content, "id", &YamlValue::String(new_id)).map(Some)
 }
 
 #[cfg(test)]
@@ -227,6 +231,23 @@ mod tests {
         assert!(normalize_note_id("01H_ABC-123").is_ok());
         assert!(normalize_note_id("../bad").is_err());
         assert!(normalize_note_id("bad value").is_err());
+        assert!(normalize_note_id("N0190A1B2C3D00A2B0001").is_ok());
+    }
+
+    #[test]
+    fn generated_note_ids_are_ulids_with_timestamps() {
+        let first = generate_note_id();
+        let second = generate_note_id();
+        assert_eq!(first.len(), 26);
+        assert!(first < second);
+        assert!(normalize_note_id(&first).is_ok());
+        assert!(note_id_timestamp_ms(&first).is_some_and(|ms| ms > 1_700_000_000_000));
+
+        assert_eq!(
+            note_id_timestamp_ms("N0190A1B2C3D00A2B0001"),
+            Some(0x0190A1B2C3D)
+        );
+        assert_eq!(note_id_timestamp_ms("01HABCDE"), None);
     }
 
     #[test]
@@ -274,6 +295,35 @@ mod tests {
         assert!(next.starts_with("---\nid: 01HNOTE\n---\n# Title\n"));
     }
 
+    #[test]
+    fn ensure_frontmatter_note_id_inserts_into_malformed_frontmatter() {
+        let content = "---\ntitle: [unclosed\n---\nbody\n";
+        let (next, id, changed) = ensure_frontmatter_note_id(content, "01HNOTE").expect("ensure");
+        assert!(changed);
+        assert_eq!(id, "01HNOTE");
+        assert_eq!(next, "---\ntitle: [unclosed\nid: 01HNOTE\n---\nbody\n");
+        assert_eq!(
+            extract_note_id_from_frontmatter(&next).as_deref(),
+            Some("01HNOTE")
+        );
+    }
+
+    #[test]
+    fn extract_note_id_survives_malformed_yaml() {
+        for content in [
+            "---\nid: N1\n\tkey: v\n---\n",
+            "---\nid: N1\nmeta: {a: 1\n---\n",
+            "---\nid: N1\ntitle: x\n- item\n---\n",
+            "---\ntags: [a\nid: \"N1\"\n---\n",
+        ] {
+            assert_eq!(
+                extract_note_id_from_frontmatter(content).as_deref(),
+                Some("N1"),
+                "{content:?}"
+            );
+        }
+    }
+
     #[test]
     fn ensure_frontmatter_note_id_keeps_existing_id() {
         let content = "---\nid: 01HOLD\n---\n# Title\n";
@@ -283,4 +333,44 @@ mod tests {
         assert_eq!(id, "01HOLD");
         assert_eq!(next, content);
     }
+
+    #[test]
+    fn replace_frontmatter_note_id_keeps_line_endings() {
+        let content = "---\r\ntitle: A\r\nid: N1\r\n---\r\nbody\r\n";
+        let out = replace_frontmatter_note_id(content, "N2")
+            .expect("replace")
+            .expect("has id");
+        assert_eq!(out, "---\r\ntitle: A\r\nid: N2\r\n---\r\nbody\r\n");
+        assert!(replace_frontmatter_note_id("no frontmatter", "N2")
+            .expect("replace")
+            .is_none());
+    }
+
+    #[test]
+    fn parse_note_meta_json_upgrades_unversioned_fixture() {
+        let fixture = r#"{
+  "id": "01HOLD",
+  "updatedAt": "2025-12-01T08:00:00Z",
+  "relations": [{"type": "xnote.related", "to": {"kind": "knowledge", "id": "01HOTHER"}}],
+  "ext": {"plugin.demo": {"color": "red"}},
+  "legacyFlag": true
+}"#;
+        let (meta, from_version) = parse_note_meta_json(fixture).expect("parse v0");
+        assert_eq!(from_version, 0);
+        assert_eq!(meta.version, NOTE_META_VERSION_CURRENT);
+        assert_eq!(meta.relations.len(), 1);
+        assert!(meta.ext.contains_key("plugin.demo"));
+        assert_eq!(meta.extra.get("legacyFlag"), Some(&Value::Bool(true)));
+
+        let current = meta.canonical_json().expect("json");
+        let (reparsed, from_version) = parse_note_meta_json(&current).expect("parse v1");
+        assert_eq!(from_version, NOTE_META_VERSION_CURRENT);
+        assert_eq!(reparsed, meta);
+
+        assert!(parse_note_meta_json(r#"{"relations": []}"#).is_err());
+        let err = parse_note_meta_json(r#"{"version": 99, "id": "N1"}"#).expect_err("newer");
+        assert!(err
+            .downcast_ref::<crate::migrate::UnsupportedSchemaVersion>()
+            .is_some());
+    }
 }
@@ -0,0 +1,981 @@
+//! Typed note properties and the property query language.
+//!
+//! Frontmatter values are typed by inference (`2026-11-01` is a date, `[[Note]]` a link)
+//! unless the vault declares a type for the key in `.xnote/settings.json`. Queries compare
+//! a property against a literal read in the property's own type:
+//!
+//! ```text
+//! status = draft AND (due < 2026-11-01 OR priority >= 2) SORT BY due DESC
+//! ```
+
+use crate::frontmatter::YamlValue;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PropertyType {
+    Text,
+    Number,
+    Date,
+    Datetime,
+    Bool,
+    List,
+    Link,
+}
+
+impl PropertyType {
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag.trim() {
+            "text" => Some(Self::Text),
+            "number" => Some(Self::Number),
+            "date" => Some(Self::Date),
+            "datetime" => Some(Self::Datetime),
+            "bool" => Some(Self::Bool),
+            "list" => Some(Self::List),
+            "link" => Some(Self::Link),
+            _ => None,
+        }
+    }
+
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Number => "number",
+            Self::Date => "date",
+            Self::Datetime => "datetime",
+            Self::Bool => "bool",
+            Self::List => "list",
+            Self::Link => "link",
+        }
+    }
+}
+
+/// Declared property types by lowercased key.
+pub type PropertyTypes = BTreeMap<String, PropertyType>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PropertyDate {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl PropertyDate {
+    /// Parses `YYYY-MM-DD`, rejecting days the month does not have.
+    pub fn parse(text: &str) -> Option<Self> {
+        let bytes = text.as_bytes();
+        if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+            return None;
+        }
+        let year = parse_digits(&text[..4])? as i32;
+        let month = parse_digits(&text[5..7])? as u8;
+        let day = parse_digits(&text[8..10])? as u8;
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Self { year, month, day })
+    }
+
+    /// Days since 1970-01-01.
+    pub fn days_since_epoch(self) -> i64 {
+        // Howard Hinnant's days_from_civil.
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from(self.month);
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+}
+
+impl std::fmt::Display for PropertyDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PropertyDateTime {
+    pub date: PropertyDate,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// UTC offset in minutes; `None` for a local time without a zone, compared as UTC.
+    pub offset_minutes: Option<i16>,
+}
+
+impl PropertyDateTime {
+    /// Parses `YYYY-MM-DD[T ]HH:MM[:SS[.frac]][Z|±HH:MM]`. Fractional seconds are dropped.
+    pub fn parse(text: &str) -> Option<Self> {
+        if text.len() < 16 || !text.is_char_boundary(10) {
+            return None;
+        }
+        let date = PropertyDate::parse(&text[..10])?;
+        let rest = text[10..].strip_prefix(['T', 't', ' '])?;
+        let (clock, offset_minutes) = if let Some(clock) = rest.strip_suffix(['Z', 'z']) {
+            (clock, Some(0))
+        } else if let Some(ix) = rest.rfind(['+', '-']) {
+            let zone = &rest[ix + 1..];
+            let (hours, minutes) = zone.split_once(':').unwrap_or((zone, "00"));
+            if hours.len() != 2 || minutes.len() != 2 {
+                return None;
+            }
+            let total = (parse_digits(hours)? * 60 + parse_digits(minutes)?) as i16;
+            let sign = if rest.as_bytes()[ix] == b'-' { -1 } else { 1 };
+            (&rest[..ix], Some(sign * total))
+        } else {
+            (rest, None)
+        };
+        let clock = clock.split_once('.').map_or(clock, |(whole, _)| whole);
+        let mut parts = clock.split(':');
+        let hour = parse_two_digits(parts.next()?)?;
+        let minute = parse_two_digits(parts.next()?)?;
+        let second = parts.next().map_or(Some(0), parse_two_digits)?;
+        if parts.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        Some(Self {
+            date,
+            hour,
+            minute,
+            second,
+            offset_minutes,
+        })
+    }
+
+    /// Seconds since the Unix epoch, in UTC.
+    pub fn timestamp(self) -> i64 {
+        self.date.days_since_epoch() * 86_400
+            + i64::from(self.hour) * 3_600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second)
+            - i64::from(self.offset_minutes.unwrap_or(0)) * 60
+    }
+
+    pub fn at_midnight(date: PropertyDate) -> Self {
+        Self {
+            date,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            offset_minutes: None,
+        }
+    }
+}
+
+impl std::fmt::Display for PropertyDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}",
+            self.date, self.hour, self.minute, self.second
+        )?;
+        match self.offset_minutes {
+            None => Ok(()),
+            Some(0) => f.write_str("Z"),
+            Some(offset) => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                let offset = offset.unsigned_abs();
+                write!(f, "{sign}{:02}:{:02}", offset / 60, offset % 60)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Text(String),
+    Number(f64),
+    Date(PropertyDate),
+    DateTime(PropertyDateTime),
+    Bool(bool),
+    List(Vec<PropertyValue>),
+    /// Target of a `[[wikilink]]`, without brackets, heading or display text.
+    Link(String),
+}
+
+impl PropertyValue {
+    pub fn property_type(&self) -> PropertyType {
+        match self {
+            Self::Text(_) => PropertyType::Text,
+            Self::Number(_) => PropertyType::Number,
+            Self::Date(_) => PropertyType::Date,
+            Self::DateTime(_) => PropertyType::Datetime,
+            Self::Bool(_) => PropertyType::Bool,
+            Self::List(_) => PropertyType::List,
+            Self::Link(_) => PropertyType::Link,
+        }
+    }
+
+    /// Types a frontmatter value by its shape; `None` for nulls and nested maps.
+    pub fn infer(value: &YamlValue) -> Option<Self> {
+        match value {
+            YamlValue::Null | YamlValue::Map(_) => None,
+            YamlValue::Bool(b) => Some(Self::Bool(*b)),
+            YamlValue::Number(text) => Some(
+                text.parse()
+                    .map(Self::Number)
+                    .unwrap_or_else(|_| Self::Text(text.clone())),
+            ),
+            YamlValue::String(text) => Some(infer_text(text)),
+            YamlValue::List(items) => {
+                Some(Self::List(items.iter().filter_map(Self::infer).collect()))
+            }
+        }
+    }
+
+    /// Reads a frontmatter value as the declared type; `None` when it does not fit.
+    pub fn coerce(value: &YamlValue, ty: PropertyType) -> Option<Self> {
+        if ty == PropertyType::List {
+            return match value {
+                YamlValue::Null => Some(Self::List(Vec::new())),
+                YamlValue::List(_) => Self::infer(value),
+                scalar => Self::infer(scalar).map(|item| Self::List(vec![item])),
+            };
+        }
+        if let YamlValue::List(_) | YamlValue::Map(_) = value {
+            return (ty == PropertyType::Text).then(|| Self::Text(value.to_flat_string()));
+        }
+        let text = value.as_scalar_text()?;
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        match ty {
+            PropertyType::Text => Some(Self::Text(text.to_string())),
+            PropertyType::Number => text.parse().ok().map(Self::Number),
+            PropertyType::Date => PropertyDate::parse(text)
+                .or_else(|| PropertyDateTime::parse(text).map(|dt| dt.date))
+                .map(Self::Date),
+            PropertyType::Datetime => parse_datetime_or_date(text).map(Self::DateTime),
+            PropertyType::Bool => parse_bool(text).map(Self::Bool),
+            PropertyType::Link => link_target(text)
+                .or_else(|| Some(text.to_string()))
+                .map(Self::Link),
+            PropertyType::List => None,
+        }
+    }
+
+    /// Orders values of the same type; values of different types order by type.
+    pub fn sort_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Text(a), Self::Text(b)) | (Self::Link(a), Self::Link(b)) => a
+                .to_lowercase()
+                .cmp(&b.to_lowercase())
+                .then_with(|| a.cmp(b)),
+            (Self::Number(a), Self::Number(b)) => a.total_cmp(b),
+            (Self::Date(a), Self::Date(b)) => a.cmp(b),
+            (Self::DateTime(a), Self::DateTime(b)) => a.timestamp().cmp(&b.timestamp()),
+            (Self::Date(a), Self::DateTime(b)) => PropertyDateTime::at_midnight(*a)
+                .timestamp()
+                .cmp(&b.timestamp()),
+            (Self::DateTime(a), Self::Date(b)) => a
+                .timestamp()
+                .cmp(&PropertyDateTime::at_midnight(*b).timestamp()),
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::List(a), Self::List(b)) => {
+                for (x, y) in a.iter().zip(b) {
+                    let ord = x.sort_cmp(y);
+                    if ord != Ordering::Equal {
+                        return ord;
+                    }
+                }
+                a.len().cmp(&b.len())
+            }
+            _ => type_rank(self).cmp(&type_rank(other)),
+        }
+    }
+
+    /// Compares a scalar value with a query literal read in the value's type; `None` when
+    /// the literal cannot be read that way.
+    fn cmp_literal(&self, literal: &str) -> Option<Ordering> {
+        match self {
+            Self::Text(text) => Some(text.to_lowercase().cmp(&literal.to_lowercase())),
+            Self::Number(n) => literal.parse::<f64>().ok().map(|lit| n.total_cmp(&lit)),
+            Self::Date(date) => {
+                if let Some(lit) = PropertyDate::parse(literal) {
+                    Some(date.cmp(&lit))
+                } else {
+                    PropertyDateTime::parse(literal).map(|lit| {
+                        PropertyDateTime::at_midnight(*date)
+                            .timestamp()
+                            .cmp(&lit.timestamp())
+                    })
+                }
+            }
+            Self::DateTime(dt) => {
+                parse_datetime_or_date(literal).map(|lit| dt.timestamp().cmp(&lit.timestamp()))
+            }
+            Self::Bool(b) => parse_bool(literal).map(|lit| b.cmp(&lit)),
+            Self::Link(target) => {
+                let lit = link_target(literal).unwrap_or_else(|| literal.to_string());
+                Some(target.to_lowercase().cmp(&lit.to_lowercase()))
+            }
+            Self::List(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Text(text) => f.write_str(text),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Date(date) => write!(f, "{date}"),
+            Self::DateTime(dt) => write!(f, "{dt}"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::List(items) => {
+                for (ix, item) in items.iter().enumerate() {
+                    if ix > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                Ok(())
+            }
+            Self::Link(target) => write!(f, "[[{target}]]"),
+        }
+    }
+}
+
+/// Lowercased key under which a property is indexed and queried.
+pub fn property_key(key: &str) -> String {
+    key.trim().to_lowercase()
+}
+
+/// Types every top-level frontmatter property, using `declared` where the vault has a type.
+///
+/// Values that do not fit their declared type are left out.
+pub fn typed_properties(
+    entries: &[(String, YamlValue)],
+    declared: &PropertyTypes,
+) -> BTreeMap<String, PropertyValue> {
+    let mut out = BTreeMap::new();
+    for (key, value) in entries {
+        let key = property_key(key);
+        if key.is_empty() || out.contains_key(&key) {
+            continue;
+        }
+        let typed = match declared.get(&key) {
+            Some(ty) => PropertyValue::coerce(value, *ty),
+            None => PropertyValue::infer(value),
+        };
+        if let Some(typed) = typed {
+            out.insert(key, typed);
+        }
+    }
+    out
+}
+
+fn infer_text(text: &str) -> PropertyValue {
+    let trimmed = text.trim();
+    if let Some(date) = PropertyDate::parse(trimmed) {
+        return PropertyValue::Date(date);
+    }
+    if let Some(dt) = PropertyDateTime::parse(trimmed) {
+        return PropertyValue::DateTime(dt);
+    }
+    if let Some(target) = link_target(trimmed) {
+        return PropertyValue::Link(target);
+    }
+    PropertyValue::Text(text.to_string())
+}
+
+/// `Note` from `[[Note]]`, `[[Note#Heading]]` or `[[Note|Shown]]`.
+fn link_target(text: &str) -> Option<String> {
+    let inner = text.strip_prefix("[[")?.strip_suffix("]]")?;
+    if inner.contains("[[") || inner.contains("]]") {
+        return None;
+    }
+    let target = inner.split('|').next().unwrap_or(inner);
+    let target = target.split('#').next().unwrap_or(target).trim();
+    (!target.is_empty()).then(|| target.to_string())
+}
+
+fn parse_datetime_or_date(text: &str) -> Option<PropertyDateTime> {
+    PropertyDateTime::parse(text)
+        .or_else(|| PropertyDate::parse(text).map(PropertyDateTime::at_midnight))
+}
+
+fn parse_bool(text: &str) -> Option<bool> {
+    match text.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_digits(text: &str) -> Option<u32> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn parse_two_digits(text: &str) -> Option<u8> {
+    (text.len() == 2)
+        .then(|| parse_digits(text))
+        .flatten()
+        .map(|n| n as u8)
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+fn type_rank(value: &PropertyValue) -> u8 {
+    match value {
+        PropertyValue::Bool(_) => 0,
+        PropertyValue::Number(_) => 1,
+        PropertyValue::Date(_) | PropertyValue::DateTime(_) => 2,
+        PropertyValue::Text(_) => 3,
+        PropertyValue::Link(_) => 4,
+        PropertyValue::List(_) => 5,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PropertyOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl PropertyOp {
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+
+    fn accepts(self, ord: Ordering) -> bool {
+        match self {
+            Self::Eq => ord == Ordering::Equal,
+            Self::Ne => ord != Ordering::Equal,
+            Self::Lt => ord == Ordering::Less,
+            Self::Le => ord != Ordering::Greater,
+            Self::Gt => ord == Ordering::Greater,
+            Self::Ge => ord != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropertyComparison {
+    /// Lowercased property key.
+    pub key: String,
+    pub op: PropertyOp,
+    pub literal: String,
+}
+
+impl PropertyComparison {
+    /// Whether a note with `value` for this key (or none) passes.
+    ///
+    /// A missing property, or one the literal cannot be compared with, only passes `!=`.
+    /// Lists pass when any item does; for `!=` no item may equal the literal.
+    pub fn matches(&self, value: Option<&PropertyValue>) -> bool {
+        let Some(value) = value else {
+            return self.op == PropertyOp::Ne;
+        };
+        match value {
+            PropertyValue::List(items) if self.op == PropertyOp::Ne => !items
+                .iter()
+                .any(|item| item.cmp_literal(&self.literal) == Some(Ordering::Equal)),
+            PropertyValue::List(items) => items.iter().any(|item| {
+                item.cmp_literal(&self.literal)
+                    .is_some_and(|ord| self.op.accepts(ord))
+            }),
+            scalar => match scalar.cmp_literal(&self.literal) {
+                Some(ord) => self.op.accepts(ord),
+                None => self.op == PropertyOp::Ne,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PropertyExpr {
+    /// A bare key: the note has the property.
+    Exists(String),
+    Compare(PropertyComparison),
+    Not(Box<PropertyExpr>),
+    And(Vec<PropertyExpr>),
+    Or(Vec<PropertyExpr>),
+}
+
+impl PropertyExpr {
+    pub fn matches(&self, properties: &BTreeMap<String, PropertyValue>) -> bool {
+        match self {
+            Self::Exists(key) => properties.contains_key(key),
+            Self::Compare(cmp) => cmp.matches(properties.get(&cmp.key)),
+            Self::Not(inner) => !inner.matches(properties),
+            Self::And(parts) => parts.iter().all(|part| part.matches(properties)),
+            Self::Or(parts) => parts.iter().any(|part| part.matches(properties)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropertySort {
+    /// Lowercased property key.
+    pub key: String,
+    pub descending: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PropertyQuery {
+    /// `None` matches every note.
+    pub filter: Option<PropertyExpr>,
+    pub sort: Vec<PropertySort>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropertyQueryError {
+    pub message: String,
+    /// Byte range of the offending token in the query text.
+    pub span: Range<usize>,
+}
+
+impl std::fmt::Display for PropertyQueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "property query error at {}..{}: {}",
+            self.span.start, self.span.end, self.message
+        )
+    }
+}
+
+impl std::error::Error for PropertyQueryError {}
+
+impl PropertyQuery {
+    /// Parses `<filter> [SORT BY key [ASC|DESC], ...]`; keywords are case-insensitive.
+    pub fn parse(text: &str) -> Result<Self, PropertyQueryError> {
+        let tokens = lex(text)?;
+        let mut parser = QueryParser {
+            tokens,
+            pos: 0,
+            end: text.len(),
+        };
+        let filter = if parser.at_end() || parser.at_keyword("SORT") {
+            None
+        } else {
+            Some(parser.or_expr()?)
+        };
+        let mut sort = Vec::new();
+        if parser.at_keyword("SORT") {
+            parser.pos += 1;
+            parser.expect_keyword("BY")?;
+            loop {
+                let key = parser.word("property to sort by")?;
+                let descending = if parser.at_keyword("DESC") {
+                    parser.pos += 1;
+                    true
+                } else {
+                    if parser.at_keyword("ASC") {
+                        parser.pos += 1;
+                    }
+                    false
+                };
+                sort.push(PropertySort {
+                    key: property_key(&key),
+                    descending,
+                });
+                if !parser.at(&Tok::Comma) {
+                    break;
+                }
+                parser.pos += 1;
+            }
+        }
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(PropertyQueryError {
+                message: "unexpected token".to_string(),
+                span: token.span.clone(),
+            });
+        }
+        Ok(Self { filter, sort })
+    }
+
+    pub fn matches(&self, properties: &BTreeMap<String, PropertyValue>) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(properties))
+    }
+
+    /// Orders two notes' properties by the sort keys; notes missing a key come last.
+    pub fn cmp_properties(
+        &self,
+        a: &BTreeMap<String, PropertyValue>,
+        b: &BTreeMap<String, PropertyValue>,
+    ) -> Ordering {
+        for sort in &self.sort {
+            let ord = match (a.get(&sort.key), b.get(&sort.key)) {
+                (Some(x), Some(y)) if sort.descending => y.sort_cmp(x),
+                (Some(x), Some(y)) => x.sort_cmp(y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Tok {
+    Word(String),
+    Quoted(String),
+    Op(PropertyOp),
+    LParen,
+    RParen,
+    Comma,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    tok: Tok,
+    span: Range<usize>,
+}
+
+fn lex(text: &str) -> Result<Vec<Token>, PropertyQueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let single = match ch {
+            '(' => Some(Tok::LParen),
+            ')' => Some(Tok::RParen),
+            ',' => Some(Tok::Comma),
+            _ => None,
+        };
+        if let Some(tok) = single {
+            chars.next();
+            tokens.push(Token {
+                tok,
+                span: start..start + 1,
+            });
+            continue;
+        }
+        if matches!(ch, '=' | '!' | '<' | '>') {
+            chars.next();
+            let followed_by_eq = chars.peek().is_some_and(|&(_, c)| c == '=');
+            let (op, len) = match (ch, followed_by_eq) {
+                ('=', true) => (PropertyOp::Eq, 2),
+                ('=', false) => (PropertyOp::Eq, 1),
+                ('!', true) => (PropertyOp::Ne, 2),
+                ('<', true) => (PropertyOp::Le, 2),
+                ('<', false) => (PropertyOp::Lt, 1),
+                ('>', true) => (PropertyOp::Ge, 2),
+                ('>', false) => (PropertyOp::Gt, 1),
+                _ => {
+                    return Err(PropertyQueryError {
+                        message: "expected `!=`".to_string(),
+                        span: start..start + 1,
+                    })
+                }
+            };
+            if len == 2 {
+                chars.next();
+            }
+            tokens.push(Token {
+                tok: Tok::Op(op),
+                span: start..start + len,
+            });
+            continue;
+        }
+        if ch == '"' || ch == '\'' {
+            chars.next();
+            let mut value = String::new();
+            let mut closed = None;
+            while let Some((ix, c)) = chars.next() {
+                if c == ch {
+                    closed = Some(ix + 1);
+                    break;
+                }
+                if c == '\\' {
+                    if let Some((_, escaped)) = chars.next() {
+                        value.push(escaped);
+                    }
+                    continue;
+                }
+                value.push(c);
+            }
+            let Some(end) = closed else {
+                return Err(PropertyQueryError {
+                    message: "unterminated quoted value".to_string(),
+                    span: start..text.len(),
+                });
+            };
+            tokens.push(Token {
+                tok: Tok::Quoted(value),
+                span: start..end,
+            });
+            continue;
+        }
+        let mut end = start;
+        while let Some(&(ix, c)) = chars.peek() {
+            if c.is_whitespace() || "()=!<>,\"'".contains(c) {
+                break;
+            }
+            end = ix + c.len_utf8();
+            chars.next();
+        }
+        tokens.push(Token {
+            tok: Tok::Word(text[start..end].to_string()),
+            span: start..end,
+        });
+    }
+    Ok(tokens)
+}
+
+struct QueryParser {
+    tokens: Vec<Token>,
+    pos: usize,
+    end: usize,
+}
+
+impl QueryParser {
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn at(&self, tok: &Tok) -> bool {
+        self.tokens.get(self.pos).is_some_and(|t| &t.tok == tok)
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(
+            self.tokens.get(self.pos).map(|t| &t.tok),
+            Some(Tok::Word(word)) if word.eq_ignore_ascii_case(keyword)
+        )
+    }
+
+    fn error_here(&self, message: String) -> PropertyQueryError {
+        let span = self
+            .tokens
+            .get(self.pos)
+            .map_or(self.end..self.end, |t| t.span.clone());
+        PropertyQueryError { message, span }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), PropertyQueryError> {
+        if !self.at_keyword(keyword) {
+            return Err(self.error_here(format!("expected `{keyword}`")));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn word(&mut self, what: &str) -> Result<String, PropertyQueryError> {
+        match self.tokens.get(self.pos).map(|t| &t.tok) {
+            Some(Tok::Word(word)) | Some(Tok::Quoted(word)) => {
+                let word = word.clone();
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(self.error_here(format!("expected {what}"))),
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<PropertyExpr, PropertyQueryError> {
+        let mut parts = vec![self.and_expr()?];
+        while self.at_keyword("OR") {
+            self.pos += 1;
+            parts.push(self.and_expr()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            PropertyExpr::Or(parts)
+        })
+    }
+
+    fn and_expr(&mut self) -> Result<PropertyExpr, PropertyQueryError> {
+        let mut parts = vec![self.unary()?];
+        while self.at_keyword("AND") {
+            self.pos += 1;
+            parts.push(self.unary()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            PropertyExpr::And(parts)
+        })
+    }
+
+    fn unary(&mut self) -> Result<PropertyExpr, PropertyQueryError> {
+        if self.at_keyword("NOT") {
+            self.pos += 1;
+            return Ok(PropertyExpr::Not(Box::new(self.unary()?)));
+        }
+        if self.at(&Tok::LParen) {
+            self.pos += 1;
+            let inner = self.or_expr()?;
+            if !self.at(&Tok::RParen) {
+                return Err(self.error_here("expected `)`".to_string()));
+            }
+            self.pos += 1;
+            return Ok(inner);
+        }
+        if ["AND", "OR", "SORT"].iter().any(|kw| self.at_keyword(kw)) {
+            return Err(self.error_here("expected a property".to_string()));
+        }
+        let key = property_key(&self.word("a property")?);
+        let Some(Tok::Op(op)) = self.tokens.get(self.pos).map(|t| t.tok.clone()) else {
+            return Ok(PropertyExpr::Exists(key));
+        };
+        self.pos += 1;
+        let literal = self.word("a value")?;
+        Ok(PropertyExpr::Compare(PropertyComparison {
+            key,
+            op,
+            literal,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::Frontmatter;
+
+    fn props(yaml: &str, declared: &PropertyTypes) -> BTreeMap<String, PropertyValue> {
+        let fm = Frontmatter::parse(&format!("---\n{yaml}---\n"))
+            .expect("parse")
+            .expect("frontmatter");
+        let entries = fm
+            .entries
+            .into_iter()
+            .map(|e| (e.key, e.value))
+            .collect::<Vec<_>>();
+        typed_properties(&entries, declared)
+    }
+
+    #[test]
+    fn typed_properties_infer_and_apply_declared_types() {
+        let yaml = "Status: draft\ndue: 2026-10-31\nstart: 2026-10-01T09:30:00+02:00\npriority: 2\ndone: false\ntags: [a, 2026-01-02]\nproject: \"[[Apollo#Goals|the project]]\"\ncode: 2026-02-30\nempty:\n";
+        let inferred = props(yaml, &PropertyTypes::new());
+        assert_eq!(inferred["status"], PropertyValue::Text("draft".into()));
+        assert_eq!(inferred["due"].property_type(), PropertyType::Date);
+        assert_eq!(inferred["due"].to_string(), "2026-10-31");
+        assert_eq!(inferred["start"].to_string(), "2026-10-01T09:30:00+02:00");
+        assert_eq!(inferred["priority"], PropertyValue::Number(2.0));
+        assert_eq!(inferred["done"], PropertyValue::Bool(false));
+        assert_eq!(inferred["tags"].to_string(), "a, 2026-01-02");
+        assert_eq!(inferred["project"], PropertyValue::Link("Apollo".into()));
+        assert_eq!(inferred["code"], PropertyValue::Text("2026-02-30".into()));
+        assert!(!inferred.contains_key("empty"));
+
+        let declared = PropertyTypes::from([
+            ("due".to_string(), PropertyType::Text),
+            ("priority".to_string(), PropertyType::List),
+            ("status".to_string(), PropertyType::Number),
+            ("start".to_string(), PropertyType::Date),
+            ("empty".to_string(), PropertyType::List),
+        ]);
+        let typed = props(yaml, &declared);
+        assert_eq!(typed["due"], PropertyValue::Text("2026-10-31".into()));
+        assert_eq!(
+            typed["priority"],
+            PropertyValue::List(vec![PropertyValue::Number(2.0)])
+        );
+        assert!(!typed.contains_key("status"));
+        assert_eq!(typed["start"].to_string(), "2026-10-01");
+        assert_eq!(typed["empty"], PropertyValue::List(Vec::new()));
+
+        let utc = PropertyDateTime::parse("2026-10-01T07:30Z").expect("utc");
+        let local = PropertyDateTime::parse("2026-10-01T09:30:00+02:00").expect("offset");
+        assert_eq!(utc.timestamp(), local.timestamp());
+        assert_eq!(
+            PropertyDate::parse("1970-01-01").map(|d| d.days_since_epoch()),
+            Some(0)
+        );
+        assert_eq!(
+            PropertyDate::parse("2024-02-29").map(|d| d.days_since_epoch()),
+            Some(19_782)
+        );
+    }
+
+    #[test]
+    fn property_query_parses_and_matches() {
+        let query = PropertyQuery::parse(
+            "status = draft AND (due < 2026-11-01 OR NOT priority) sort by due desc, Title",
+        )
+        .expect("parse");
+        assert_eq!(
+            query.sort,
+            vec![
+                PropertySort {
+                    key: "due".into(),
+                    descending: true
+                },
+                PropertySort {
+                    key: "title".into(),
+                    descending: false
+                },
+            ]
+        );
+
+        let note = props(
+            "status: Draft\ndue: 2026-10-31\npriority: 1\n",
+            &PropertyTypes::new(),
+        );
+        assert!(query.matches(&note));
+        let late = props(
+            "status: draft\ndue: 2026-11-02\npriority: 1\n",
+            &PropertyTypes::new(),
+        );
+        assert!(!query.matches(&late));
+        let unprioritized = props("status: draft\ndue: 2027-01-01\n", &PropertyTypes::new());
+        assert!(query.matches(&unprioritized));
+
+        let tagged = props(
+            "tags: [rust, cli]\nrank: 3\nat: 2026-10-01T12:00Z\n",
+            &PropertyTypes::new(),
+        );
+        let check = |text: &str| PropertyQuery::parse(text).expect(text).matches(&tagged);
+        assert!(check("tags = RUST"));
+        assert!(!check("tags != rust"));
+        assert!(check("tags != go"));
+        assert!(check("rank >= 3 AND rank < 10"));
+        assert!(!check("rank = three"));
+        assert!(check("missing != x"));
+        assert!(!check("missing = x"));
+        assert!(check("at > 2026-10-01 AND at < '2026-10-01T13:00:00Z'"));
+        assert!(PropertyQuery::parse("").expect("empty").matches(&tagged));
+
+        let err = PropertyQuery::parse("status = draft AND").expect_err("dangling and");
+        assert_eq!(err.span, 18..18);
+        let err = PropertyQuery::parse("(status = draft").expect_err("open paren");
+        assert_eq!(err.span, 15..15);
+        let err = PropertyQuery::parse("status ! draft").expect_err("bang");
+        assert_eq!(err.span, 7..8);
+        let err = PropertyQuery::parse("a = b c").expect_err("trailing");
+        assert_eq!(err.span, 6..7);
+        assert!(PropertyQuery::parse("a = 'open").is_err());
+    }
+}
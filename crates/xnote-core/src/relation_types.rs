@@ -0,0 +1,437 @@
+//! Registry of known note meta relation types.
+//!
+//! Built-in `xnote.*` types are always present; vaults add their own through the
+//! `relations.types` section of `.xnote/settings.json`.
+
+use crate::note_meta::NoteMetaV1;
+use crate::relations::RelationNodeKind;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+const BUILTIN_PREFIX: &str = "xnote.";
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationCardinality {
+    /// A note may declare at most one relation of this type.
+    One,
+    #[default]
+    Many,
+}
+
+impl RelationCardinality {
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::One => "one",
+            Self::Many => "many",
+        }
+    }
+}
+
+/// How [`NoteMetaV1::validate_with_registry`] treats relations the registry rejects.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationValidationMode {
+    /// Report issues but accept the meta.
+    #[default]
+    Warn,
+    /// Reject the meta on the first issue.
+    Strict,
+}
+
+impl RelationValidationMode {
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::Warn => "warn",
+            Self::Strict => "strict",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelationTypeDef {
+    pub id: String,
+    pub label: String,
+    /// Label shown on the target's side, e.g. "Cited by" for "Cites".
+    pub inverse_label: String,
+    /// Kinds allowed to declare the relation; empty allows every kind.
+    #[serde(default)]
+    pub source_kinds: Vec<RelationNodeKind>,
+    /// Kinds the relation may point at; empty allows every kind.
+    #[serde(default)]
+    pub target_kinds: Vec<RelationNodeKind>,
+    #[serde(default)]
+    pub cardinality: RelationCardinality,
+}
+
+impl RelationTypeDef {
+    pub fn allows_source(&self, kind: RelationNodeKind) -> bool {
+        self.source_kinds.is_empty() || self.source_kinds.contains(&kind)
+    }
+
+    pub fn allows_target(&self, kind: RelationNodeKind) -> bool {
+        self.target_kinds.is_empty() || self.target_kinds.contains(&kind)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let id = self.id.trim();
+        if id.is_empty() {
+            return Err(anyhow!("relation type id cannot be empty"));
+        }
+        if id
+            .chars()
+            .any(|ch| !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '-' || ch == '_'))
+        {
+            return Err(anyhow!(
+                "relation type id must contain only [A-Za-z0-9._-], got: {id}"
+            ));
+        }
+        if self.label.trim().is_empty() || self.inverse_label.trim().is_empty() {
+            return Err(anyhow!(
+                "relation type {id} needs a label and an inverse label"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelationIssueKind {
+    UnknownType,
+    SourceKindNotAllowed,
+    TargetKindNotAllowed,
+    CardinalityExceeded,
+}
+
+impl RelationIssueKind {
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::UnknownType => "unknown_type",
+            Self::SourceKindNotAllowed => "source_kind_not_allowed",
+            Self::TargetKindNotAllowed => "target_kind_not_allowed",
+            Self::CardinalityExceeded => "cardinality_exceeded",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RelationIssue {
+    /// Position of the offending relation in `NoteMetaV1::relations`.
+    pub relation_index: usize,
+    pub relation_type: String,
+    pub kind: RelationIssueKind,
+    pub message: String,
+}
+
+/// Returned (inside `anyhow::Error`) when strict validation rejects a meta.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidRelations {
+    pub note_id: String,
+    pub issues: Vec<RelationIssue>,
+}
+
+impl std::fmt::Display for InvalidRelations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "note meta {} has invalid relations: ", self.note_id)?;
+        for (ix, issue) in self.issues.iter().enumerate() {
+            if ix > 0 {
+                f.write_str("; ")?;
+            }
+            f.write_str(&issue.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidRelations {}
+
+#[derive(Clone, Debug)]
+pub struct RelationTypeRegistry {
+    types: BTreeMap<String, RelationTypeDef>,
+}
+
+impl Default for RelationTypeRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl RelationTypeRegistry {
+    pub fn builtin() -> Self {
+        use RelationCardinality::{Many, One};
+        use RelationNodeKind::{Info, Knowledge, Resource};
+
+        let def = |id: &str,
+                   label: &str,
+                   inverse_label: &str,
+                   target_kinds: &[RelationNodeKind],
+                   cardinality| RelationTypeDef {
+            id: id.to_string(),
+            label: label.to_string(),
+            inverse_label: inverse_label.to_string(),
+            source_kinds: vec![Knowledge],
+            target_kinds: target_kinds.to_vec(),
+            cardinality,
+        };
+        let types = [
+            def(
+                "xnote.references",
+                "References",
+                "Referenced by",
+                &[Knowledge],
+                Many,
+            ),
+            def("xnote.related", "Related to", "Related to", &[], Many),
+            def("xnote.explains", "Explains", "Explained by", &[], Many),
+            def("xnote.cites", "Cites", "Cited by", &[Resource, Info], Many),
+            def("xnote.quotes", "Quotes", "Quoted in", &[Info], Many),
+            def("xnote.parent", "Parent", "Child", &[Knowledge], One),
+        ];
+        Self {
+            types: types.into_iter().map(|t| (t.id.clone(), t)).collect(),
+        }
+    }
+
+    /// Built-in types plus `user_types`; the `xnote.` prefix is reserved.
+    pub fn with_user_types(user_types: &[RelationTypeDef]) -> Result<Self> {
+        let mut registry = Self::builtin();
+        for def in user_types {
+            def.validate()?;
+            let id = def.id.trim();
+            if id.starts_with(BUILTIN_PREFIX) {
+                return Err(anyhow!(
+                    "relation type {id} uses the reserved {BUILTIN_PREFIX} prefix"
+                ));
+            }
+            if registry.types.contains_key(id) {
+                return Err(anyhow!("relation type {id} is declared twice"));
+            }
+            let mut def = def.clone();
+            def.id = id.to_string();
+            registry.types.insert(def.id.clone(), def);
+        }
+        Ok(registry)
+    }
+
+    pub fn get(&self, relation_type: &str) -> Option<&RelationTypeDef> {
+        self.types.get(relation_type.trim())
+    }
+
+    pub fn is_builtin(&self, relation_type: &str) -> bool {
+        relation_type.trim().starts_with(BUILTIN_PREFIX) && self.get(relation_type).is_some()
+    }
+
+    /// Every registered type, sorted by id.
+    pub fn types(&self) -> impl Iterator<Item = &RelationTypeDef> {
+        self.types.values()
+    }
+
+    /// Registry as a JSON array for AI tools and the UI; each entry carries a `builtin` flag.
+    pub fn export_json(&self) -> Result<String> {
+        let mut out = Vec::with_capacity(self.types.len());
+        for def in self.types.values() {
+            let mut value = serde_json::to_value(def)?;
+            if let Value::Object(object) = &mut value {
+                object.insert("builtin".to_string(), Value::from(self.is_builtin(&def.id)));
+            }
+            out.push(value);
+        }
+        let mut json = serde_json::to_string_pretty(&out)?;
+        json.push('\n');
+        Ok(json)
+    }
+
+    /// Every registry violation in `meta.relations`, in declaration order.
+    pub fn check_meta(&self, meta: &NoteMetaV1) -> Vec<RelationIssue> {
+        let mut issues = Vec::new();
+        let mut seen = HashMap::<&str, usize>::new();
+        for (relation_index, relation) in meta.relations.iter().enumerate() {
+            let relation_type = relation.relation_type.trim();
+            let mut issue = |kind, message: String| {
+                issues.push(RelationIssue {
+                    relation_index,
+                    relation_type: relation_type.to_string(),
+                    kind,
+                    message,
+                })
+            };
+
+            let Some(def) = self.get(relation_type) else {
+                issue(
+                    RelationIssueKind::UnknownType,
+                    format!("unknown relation type {relation_type}"),
+                );
+                continue;
+            };
+            if !def.allows_source(RelationNodeKind::Knowledge) {
+                issue(
+                    RelationIssueKind::SourceKindNotAllowed,
+                    format!("relation {relation_type} cannot be declared by a note"),
+                );
+            }
+            if let Some(kind) = RelationNodeKind::from_tag(&relation.to.kind) {
+                if !def.allows_target(kind) {
+                    issue(
+                        RelationIssueKind::TargetKindNotAllowed,
+                        format!("relation {relation_type} cannot point at {}", kind.as_tag()),
+                    );
+                }
+            }
+            let count = seen.entry(relation_type).or_default();
+            *count += 1;
+            if def.cardinality == RelationCardinality::One && *count == 2 {
+                issue(
+                    RelationIssueKind::CardinalityExceeded,
+                    format!("relation {relation_type} allows only one target"),
+                );
+            }
+        }
+        issues
+    }
+}
+
+impl NoteMetaV1 {
+    /// Runs [`NoteMetaV1::validate`] and then checks relation types against `registry`.
+    ///
+    /// `validate` alone stays schema-only so metas using another vault's types still
+    /// load. In warn mode the issues are returned; in strict mode any issue is an
+    /// [`InvalidRelations`] error.
+    pub fn validate_with_registry(
+        &self,
+        registry: &RelationTypeRegistry,
+        mode: RelationValidationMode,
+    ) -> Result<Vec<RelationIssue>> {
+        self.validate()?;
+        let issues = registry.check_meta(self);
+        if mode == RelationValidationMode::Strict && !issues.is_empty() {
+            return Err(InvalidRelations {
+                note_id: self.id.clone(),
+                issues,
+            }
+            .into());
+        }
+        Ok(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_meta::{NoteMetaRelation, NoteMetaTarget};
+    use serde_json::Map;
+
+    fn relation(relation_type: &str, kind: &str, id: &str) -> NoteMetaRelation {
+        NoteMetaRelation {
+            relation_type: relation_type.to_string(),
+            to: NoteMetaTarget {
+                kind: kind.to_string(),
+                id: id.to_string(),
+                anchor: None,
+                extra: Map::new(),
+            },
+            note: None,
+            created_at: None,
+            created_by: None,
+            extra: Map::new(),
+        }
+    }
+
+    fn user_type(id: &str) -> RelationTypeDef {
+        RelationTypeDef {
+            id: id.to_string(),
+            label: "Blocks".to_string(),
+            inverse_label: "Blocked by".to_string(),
+            source_kinds: Vec::new(),
+            target_kinds: vec![RelationNodeKind::Knowledge],
+            cardinality: RelationCardinality::Many,
+        }
+    }
+
+    #[test]
+    fn registry_merges_user_types_and_rejects_reserved_or_duplicate_ids() {
+        let registry =
+            RelationTypeRegistry::with_user_types(&[user_type("task.blocks")]).expect("registry");
+        assert!(registry.is_builtin("xnote.cites"));
+        assert!(!registry.is_builtin("task.blocks"));
+        assert_eq!(
+            registry
+                .get("task.blocks")
+                .map(|t| t.inverse_label.as_str()),
+            Some("Blocked by")
+        );
+
+        let exported: Value =
+            serde_json::from_str(&registry.export_json().expect("export")).expect("parse");
+        let cites = exported
+            .as_array()
+            .expect("array")
+            .iter()
+            .find(|t| t["id"] == "xnote.cites")
+            .expect("cites");
+        assert_eq!(cites["inverse_label"], "Cited by");
+        assert_eq!(
+            cites["target_kinds"],
+            serde_json::json!(["resource", "info"])
+        );
+        assert_eq!(cites["builtin"], true);
+
+        assert!(RelationTypeRegistry::with_user_types(&[user_type("xnote.blocks")]).is_err());
+        assert!(RelationTypeRegistry::with_user_types(&[
+            user_type("task.blocks"),
+            user_type("task.blocks")
+        ])
+        .is_err());
+        let mut unlabeled = user_type("task.blocks");
+        unlabeled.inverse_label.clear();
+        assert!(RelationTypeRegistry::with_user_types(&[unlabeled]).is_err());
+    }
+
+    #[test]
+    fn validate_with_registry_warns_or_rejects() {
+        let registry = RelationTypeRegistry::builtin();
+        let mut meta = NoteMetaV1::new("N1").expect("meta");
+        meta.relations
+            .push(relation("xnote.explains", "knowledge", "N2"));
+        meta.relations
+            .push(relation("xnote.explain", "knowledge", "N2"));
+        meta.relations
+            .push(relation("xnote.cites", "knowledge", "N3"));
+        meta.relations
+            .push(relation("xnote.parent", "knowledge", "N4"));
+        meta.relations
+            .push(relation("xnote.parent", "knowledge", "N5"));
+
+        let issues = meta
+            .validate_with_registry(&registry, RelationValidationMode::Warn)
+            .expect("warn mode accepts");
+        let kinds = issues
+            .iter()
+            .map(|i| (i.relation_index, i.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (1, RelationIssueKind::UnknownType),
+                (2, RelationIssueKind::TargetKindNotAllowed),
+                (4, RelationIssueKind::CardinalityExceeded),
+            ]
+        );
+
+        let err = meta
+            .validate_with_registry(&registry, RelationValidationMode::Strict)
+            .expect_err("strict mode rejects");
+        let typed = err.downcast_ref::<InvalidRelations>().expect("typed error");
+        assert_eq!(typed.issues.len(), 3);
+        assert!(err
+            .to_string()
+            .contains("unknown relation type xnote.explain"));
+
+        meta.relations.truncate(1);
+        assert!(meta
+            .validate_with_registry(&registry, RelationValidationMode::Strict)
+            .expect("valid")
+            .is_empty());
+    }
+}
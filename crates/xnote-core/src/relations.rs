@@ -0,0 +1,470 @@
+//! Typed relation edges from `.xnote/meta/*.json`, indexed both ways.
+
+use crate::knowledge::KnowledgeIndex;
+use crate::note_meta::{NoteMetaRelation, NoteMetaV1};
+use crate::vault::Vault;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationNodeKind {
+    Knowledge,
+    Resource,
+    Info,
+}
+
+impl RelationNodeKind {
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag.trim() {
+            "knowledge" => Some(Self::Knowledge),
+            "resource" => Some(Self::Resource),
+            "info" => Some(Self::Info),
+            _ => None,
+        }
+    }
+
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::Knowledge => "knowledge",
+            Self::Resource => "resource",
+            Self::Info => "info",
+        }
+    }
+}
+
+/// One end of a relation: a note id, resource id or info id.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RelationNode {
+    pub kind: RelationNodeKind,
+    pub id: String,
+}
+
+impl RelationNode {
+    pub fn new(kind: RelationNodeKind, id: impl Into<String>) -> Self {
+        Self {
+            kind,
+            id: id.into(),
+        }
+    }
+
+    pub fn knowledge(note_id: impl Into<String>) -> Self {
+        Self::new(RelationNodeKind::Knowledge, note_id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RelationEdge {
+    /// Id of the note whose meta declares the relation.
+    pub source: String,
+    pub relation_type: String,
+    pub target: RelationNode,
+    pub anchor: Option<String>,
+    pub note: Option<String>,
+}
+
+/// Filter for [`RelationIndex::query`]; `None` fields match anything.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RelationQuery {
+    pub source: Option<String>,
+    pub target_kind: Option<RelationNodeKind>,
+    pub target_id: Option<String>,
+    pub relation_type: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RelationIndex {
+    forward: HashMap<String, Vec<RelationEdge>>,
+    reverse: HashMap<RelationNode, Vec<RelationEdge>>,
+}
+
+impl RelationIndex {
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn rebuild_from_vault(vault: &Vault) -> Result<Self> {
+        let mut index = Self::default();
+        for meta in vault.list_note_metas()? {
+            index.upsert_meta(&meta);
+        }
+        Ok(index)
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.forward.values().map(Vec::len).sum()
+    }
+
+    /// Replaces the edges declared by `meta.id`.
+    pub fn upsert_meta(&mut self, meta: &NoteMetaV1) {
+        self.remove_source(&meta.id);
+        let edges = meta
+            .relations
+            .iter()
+            .filter_map(|relation| edge_from_relation(&meta.id, relation))
+            .collect::<Vec<_>>();
+        if edges.is_empty() {
+            return;
+        }
+        for edge in &edges {
+            self.reverse
+                .entry(edge.target.clone())
+                .or_default()
+                .push(edge.clone());
+        }
+        self.forward.insert(meta.id.clone(), edges);
+    }
+
+    pub fn remove_source(&mut self, note_id: &str) {
+        let Some(edges) = self.forward.remove(note_id) else {
+            return;
+        };
+        for edge in edges {
+            if let Some(incoming) = self.reverse.get_mut(&edge.target) {
+                incoming.retain(|e| e.source != note_id);
+                if incoming.is_empty() {
+                    self.reverse.remove(&edge.target);
+                }
+            }
+        }
+    }
+
+    /// Re-reads one note's meta after it changed on disk, dropping its edges if it is gone.
+    pub fn reload_note_meta(&mut self, vault: &Vault, note_id: &str) -> Result<()> {
+        match vault.load_note_meta(note_id)? {
+            Some(meta) => self.upsert_meta(&meta),
+            None => self.remove_source(note_id),
+        }
+        Ok(())
+    }
+
+    pub fn outgoing(&self, source: &str, relation_type: Option<&str>) -> Vec<RelationEdge> {
+        self.forward
+            .get(source)
+            .into_iter()
+            .flatten()
+            .filter(|edge| relation_type.is_none_or(|t| edge.relation_type == t))
+            .cloned()
+            .collect()
+    }
+
+    pub fn incoming(
+        &self,
+        target: &RelationNode,
+        relation_type: Option<&str>,
+    ) -> Vec<RelationEdge> {
+        let mut out = self
+            .reverse
+            .get(target)
+            .into_iter()
+            .flatten()
+            .filter(|edge| relation_type.is_none_or(|t| edge.relation_type == t))
+            .cloned()
+            .collect::<Vec<_>>();
+        out.sort_by(|a, b| a.source.cmp(&b.source));
+        out
+    }
+
+    /// Edges matching every set field of `query`, ordered by source then declaration order.
+    pub fn query(&self, query: &RelationQuery) -> Vec<RelationEdge> {
+        let matches = |edge: &&RelationEdge| {
+            query.source.as_ref().is_none_or(|s| &edge.source == s)
+                && query.target_kind.is_none_or(|k| edge.target.kind == k)
+                && query
+                    .target_id
+                    .as_ref()
+                    .is_none_or(|id| &edge.target.id == id)
+                && query
+                    .relation_type
+                    .as_ref()
+                    .is_none_or(|t| &edge.relation_type == t)
+        };
+
+        if let Some(source) = query.source.as_ref() {
+            return self
+                .forward
+                .get(source)
+                .into_iter()
+                .flatten()
+                .filter(matches)
+                .cloned()
+                .collect();
+        }
+        if let (Some(kind), Some(id)) = (query.target_kind, query.target_id.as_ref()) {
+            return self
+                .incoming(&RelationNode::new(kind, id.clone()), None)
+                .into_iter()
+                .filter(|edge| matches(&edge))
+                .collect();
+        }
+
+        let mut sources = self.forward.keys().collect::<Vec<_>>();
+        sources.sort();
+        sources
+            .into_iter()
+            .flat_map(|source| self.forward[source].iter())
+            .filter(matches)
+            .cloned()
+            .collect()
+    }
+
+    /// Relation types in use with their edge counts, sorted by type.
+    pub fn relation_types(&self) -> Vec<(String, usize)> {
+        let mut counts = HashMap::<&str, usize>::new();
+        for edge in self.forward.values().flatten() {
+            *counts.entry(edge.relation_type.as_str()).or_default() += 1;
+        }
+        let mut out = counts
+            .into_iter()
+            .map(|(t, n)| (t.to_string(), n))
+            .collect::<Vec<_>>();
+        out.sort();
+        out
+    }
+
+    /// Merges wikilink edges from `knowledge` with relation edges into one graph.
+    ///
+    /// Notes are keyed by path; relation ends whose note id matches no indexed note
+    /// stay as [`GraphNodeId::UnresolvedNote`].
+    pub fn graph_view(&self, knowledge: &KnowledgeIndex) -> GraphView {
+        let mut nodes = BTreeSet::new();
+        let mut edges = BTreeSet::new();
+
+        for path in knowledge.all_paths_sorted() {
+            nodes.insert(GraphNodeId::Note(path.clone()));
+            let Some(summary) = knowledge.note_summary(&path) else {
+                continue;
+            };
+            for link in &summary.links {
+                let Some(target) = knowledge.resolve_link_target(link) else {
+                    continue;
+                };
+                if target == path {
+                    continue;
+                }
+                edges.insert(GraphEdge {
+                    from: GraphNodeId::Note(path.clone()),
+                    to: GraphNodeId::Note(target),
+                    kind: GraphEdgeKind::Link,
+                });
+            }
+        }
+
+        let note_node = |note_id: &str| match knowledge.path_for_note_id(note_id) {
+            Some(path) => GraphNodeId::Note(path),
+            None => GraphNodeId::UnresolvedNote(note_id.to_string()),
+        };
+        for edge in self.forward.values().flatten() {
+            let from = note_node(&edge.source);
+            let to = match edge.target.kind {
+                RelationNodeKind::Knowledge => note_node(&edge.target.id),
+                RelationNodeKind::Resource => GraphNodeId::Resource(edge.target.id.clone()),
+                RelationNodeKind::Info => GraphNodeId::Info(edge.target.id.clone()),
+            };
+            nodes.insert(from.clone());
+            nodes.insert(to.clone());
+            edges.insert(GraphEdge {
+                from,
+                to,
+                kind: GraphEdgeKind::Relation(edge.relation_type.clone()),
+            });
+        }
+
+        GraphView {
+            nodes: nodes.into_iter().collect(),
+            edges: edges.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GraphNodeId {
+    /// Indexed note, by vault-relative path.
+    Note(String),
+    /// Note id referenced by a relation but not present in the index.
+    UnresolvedNote(String),
+    Resource(String),
+    Info(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GraphEdgeKind {
+    Link,
+    Relation(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphEdge {
+    pub from: GraphNodeId,
+    pub to: GraphNodeId,
+    pub kind: GraphEdgeKind,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphView {
+    /// Sorted and deduplicated.
+    pub nodes: Vec<GraphNodeId>,
+    /// Sorted and deduplicated.
+    pub edges: Vec<GraphEdge>,
+}
+
+fn edge_from_relation(source: &str, relation: &NoteMetaRelation) -> Option<RelationEdge> {
+    let kind = RelationNodeKind::from_tag(&relation.to.kind)?;
+    let id = relation.to.id.trim();
+    if id.is_empty() {
+        return None;
+    }
+    Some(RelationEdge {
+        source: source.to_string(),
+        relation_type: relation.relation_type.trim().to_string(),
+        target: RelationNode::new(kind, id),
+        anchor: relation.to.anchor.clone(),
+        note: relation.note.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_meta::NoteMetaTarget;
+    use serde_json::Map;
+    use std::fs;
+
+    fn relation(relation_type: &str, kind: &str, id: &str) -> NoteMetaRelation {
+        NoteMetaRelation {
+            relation_type: relation_type.to_string(),
+            to: NoteMetaTarget {
+                kind: kind.to_string(),
+                id: id.to_string(),
+                anchor: None,
+                extra: Map::new(),
+            },
+            note: None,
+            created_at: None,
+            created_by: None,
+            extra: Map::new(),
+        }
+    }
+
+    #[test]
+    fn relation_index_answers_forward_reverse_and_filtered_queries() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_relations_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes")).expect("create notes");
+        fs::write(
+            temp_dir.join("notes/A.md"),
+            "---\nid: N1\n---\n# A\n[[B]]\n",
+        )
+        .expect("A");
+        fs::write(temp_dir.join("notes/B.md"), "---\nid: N2\n---\n# B\n").expect("B");
+        let vault = Vault::open(&temp_dir).expect("open vault");
+
+        let mut a = NoteMetaV1::new("N1").expect("meta");
+        a.relations
+            .push(relation("xnote.explains", "knowledge", "N2"));
+        a.relations.push(relation("xnote.cites", "resource", "R1"));
+        vault.save_note_meta(&a).expect("save A");
+        let mut c = NoteMetaV1::new("N3").expect("meta");
+        c.relations
+            .push(relation("xnote.explains", "knowledge", "N2"));
+        c.relations.push(relation("xnote.quotes", "info", "F1"));
+        vault.save_note_meta(&c).expect("save C");
+
+        let mut index = RelationIndex::rebuild_from_vault(&vault).expect("index");
+        assert_eq!(index.edge_count(), 4);
+
+        let explainers = index
+            .incoming(&RelationNode::knowledge("N2"), Some("xnote.explains"))
+            .into_iter()
+            .map(|e| e.source)
+            .collect::<Vec<_>>();
+        assert_eq!(explainers, vec!["N1", "N3"]);
+        assert_eq!(index.outgoing("N1", Some("xnote.cites")).len(), 1);
+
+        let resources = index.query(&RelationQuery {
+            target_kind: Some(RelationNodeKind::Resource),
+            ..RelationQuery::default()
+        });
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].target.id, "R1");
+        assert_eq!(
+            index.relation_types(),
+            vec![
+                ("xnote.cites".to_string(), 1),
+                ("xnote.explains".to_string(), 2),
+                ("xnote.quotes".to_string(), 1),
+            ]
+        );
+
+        a.relations.truncate(1);
+        vault.save_note_meta(&a).expect("update A");
+        index.reload_note_meta(&vault, "N1").expect("reload");
+        assert!(index
+            .incoming(&RelationNode::new(RelationNodeKind::Resource, "R1"), None)
+            .is_empty());
+        vault.delete_note_meta("N3").expect("delete C");
+        index
+            .reload_note_meta(&vault, "N3")
+            .expect("reload deleted");
+        assert_eq!(index.edge_count(), 1);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn graph_view_merges_wikilinks_and_relations() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_relations_graph_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes")).expect("create notes");
+        fs::write(
+            temp_dir.join("notes/A.md"),
+            "---\nid: N1\n---\n# A\n[[B]]\n",
+        )
+        .expect("A");
+        fs::write(temp_dir.join("notes/B.md"), "---\nid: N2\n---\n# B\n").expect("B");
+        let vault = Vault::open(&temp_dir).expect("open vault");
+        let knowledge = KnowledgeIndex::rebuild_from_vault(&vault).expect("knowledge");
+
+        let mut index = RelationIndex::empty();
+        let mut a = NoteMetaV1::new("N1").expect("meta");
+        a.relations
+            .push(relation("xnote.explains", "knowledge", "N2"));
+        a.relations
+            .push(relation("xnote.related", "knowledge", "N404"));
+        a.relations.push(relation("xnote.cites", "resource", "R1"));
+        index.upsert_meta(&a);
+
+        let graph = index.graph_view(&knowledge);
+        let note = |p: &str| GraphNodeId::Note(p.to_string());
+        assert_eq!(
+            graph.nodes,
+            vec![
+                note("notes/A.md"),
+                note("notes/B.md"),
+                GraphNodeId::UnresolvedNote("N404".to_string()),
+                GraphNodeId::Resource("R1".to_string()),
+            ]
+        );
+        let edges = graph
+            .edges
+            .iter()
+            .map(|e| (&e.from, &e.to, &e.kind))
+            .collect::<Vec<_>>();
+        assert!(edges.contains(&(
+            &note("notes/A.md"),
+            &note("notes/B.md"),
+            &GraphEdgeKind::Link
+        )));
+        assert!(edges.contains(&(
+            &note("notes/A.md"),
+            &note("notes/B.md"),
+            &GraphEdgeKind::Relation("xnote.explains".to_string())
+        )));
+        assert_eq!(graph.edges.len(), 4);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
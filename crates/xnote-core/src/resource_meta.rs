@@ -0,0 +1,343 @@
+use crate::paths::normalize_vault_rel_path;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const RESOURCE_META_VERSION_V1: u32 = 1;
+pub const RESOURCE_RATING_MAX: u8 = 5;
+static RESOURCE_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResourceDimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Rectangle in fractions of the resource's width and height, each in `0.0..=1.0`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ResourceRegion {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ResourceAnnotation {
+    pub id: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<ResourceRegion>,
+    /// 1-based page for paged documents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// Offset into audio or video.
+    #[serde(rename = "timeMs", default, skip_serializing_if = "Option::is_none")]
+    pub time_ms: Option<u64>,
+    #[serde(rename = "createdAt", default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ResourceMetaV1 {
+    pub version: u32,
+    pub id: String,
+    /// Vault-relative POSIX path of the described file, e.g. `attachments/ab/ab12….png`.
+    pub path: String,
+    #[serde(rename = "sourceUrl", default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub annotations: Vec<ResourceAnnotation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    /// Dominant colors as `#RRGGBB` or `#RRGGBBAA`.
+    #[serde(default)]
+    pub palette: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<ResourceDimensions>,
+    #[serde(
+        rename = "durationMs",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub duration_ms: Option<u64>,
+    #[serde(rename = "updatedAt", default)]
+    pub updated_at: String,
+    #[serde(default)]
+    pub ext: Map<String, Value>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ResourceMetaV1 {
+    pub fn new(resource_id: impl Into<String>, path: &str) -> Result<Self> {
+        let id = normalize_resource_id(&resource_id.into())?;
+        Ok(Self {
+            version: RESOURCE_META_VERSION_V1,
+            id,
+            path: normalize_vault_rel_path(path)?,
+            source_url: None,
+            tags: Vec::new(),
+            annotations: Vec::new(),
+            rating: None,
+            palette: Vec::new(),
+            dimensions: None,
+            duration_ms: None,
+            updated_at: String::new(),
+            ext: Map::new(),
+            extra: Map::new(),
+        })
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.version != RESOURCE_META_VERSION_V1 {
+            return Err(anyhow!(
+                "unsupported resource meta version: {}",
+                self.version
+            ));
+        }
+
+        normalize_resource_id(&self.id)?;
+        if normalize_vault_rel_path(&self.path)? != self.path {
+            return Err(anyhow!(
+                "resource meta path is not a normalized vault path: {}",
+                self.path
+            ));
+        }
+
+        if let Some(url) = self.source_url.as_deref() {
+            if !has_url_scheme(url) {
+                return Err(anyhow!("resource source url has no scheme: {url}"));
+            }
+        }
+
+        if self.tags.iter().any(|tag| tag.trim().is_empty()) {
+            return Err(anyhow!("resource meta tag is empty"));
+        }
+
+        if let Some(rating) = self.rating {
+            if rating > RESOURCE_RATING_MAX {
+                return Err(anyhow!(
+                    "resource rating must be at most {RESOURCE_RATING_MAX}, got: {rating}"
+                ));
+            }
+        }
+
+        for color in &self.palette {
+            if !is_hex_color(color) {
+                return Err(anyhow!(
+                    "resource palette color must be #RRGGBB, got: {color}"
+                ));
+            }
+        }
+
+        if let Some(dimensions) = self.dimensions {
+            if dimensions.width == 0 || dimensions.height == 0 {
+                return Err(anyhow!("resource dimensions must be non-zero"));
+            }
+        }
+
+        let mut annotation_ids = HashSet::new();
+        for annotation in &self.annotations {
+            normalize_resource_id(&annotation.id)?;
+            if !annotation_ids.insert(annotation.id.as_str()) {
+                return Err(anyhow!(
+                    "duplicate resource annotation id: {}",
+                    annotation.id
+                ));
+            }
+            if let Some(region) = annotation.region {
+                let in_unit = |v: f64| (0.0..=1.0).contains(&v);
+                if !(in_unit(region.x)
+                    && in_unit(region.y)
+                    && in_unit(region.width)
+                    && in_unit(region.height)
+                    && in_unit(region.x + region.width)
+                    && in_unit(region.y + region.height))
+                {
+                    return Err(anyhow!(
+                        "resource annotation {} region is outside the resource",
+                        annotation.id
+                    ));
+                }
+            }
+            if annotation.page == Some(0) {
+                return Err(anyhow!(
+                    "resource annotation {} page is 1-based",
+                    annotation.id
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn canonical_json(&self) -> Result<String> {
+        self.validate()?;
+        let mut out = serde_json::to_string_pretty(self)?;
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    pub fn annotation(&self, annotation_id: &str) -> Option<&ResourceAnnotation> {
+        self.annotations.iter().find(|a| a.id == annotation_id)
+    }
+}
+
+pub fn normalize_resource_id(resource_id: &str) -> Result<String> {
+    let id = resource_id.trim();
+    if id.is_empty() {
+        return Err(anyhow!("resource id cannot be empty"));
+    }
+
+    if id
+        .chars()
+        .any(|ch| !(ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'))
+    {
+        return Err(anyhow!(
+            "resource id must contain only [A-Za-z0-9_-], got: {id}"
+        ));
+    }
+
+    Ok(id.to_string())
+}
+
+pub fn generate_resource_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default();
+    let pid = std::process::id() as u64;
+    let seq = RESOURCE_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("R{millis:011X}{pid:05X}{seq:04X}")
+}
+
+pub(crate) fn has_url_scheme(url: &str) -> bool {
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return false;
+    };
+    !rest.is_empty()
+        && scheme.starts_with(|ch: char| ch.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '+' | '-' | '.'))
+}
+
+fn is_hex_color(color: &str) -> bool {
+    let Some(hex) = color.strip_prefix('#') else {
+        return false;
+    };
+    matches!(hex.len(), 6 | 8) && hex.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resource_meta_roundtrip_keeps_unknown_fields() {
+        let mut meta = ResourceMetaV1::new("R1", "attachments/shot.png").expect("new");
+        meta.source_url = Some("https://example.com/shot.png".to_string());
+        meta.tags.push("diagram".to_string());
+        meta.rating = Some(4);
+        meta.palette = vec!["#1E90FF".to_string(), "#00000080".to_string()];
+        meta.dimensions = Some(ResourceDimensions {
+            width: 640,
+            height: 480,
+        });
+        meta.annotations.push(ResourceAnnotation {
+            id: "a1".to_string(),
+            text: "legend".to_string(),
+            region: Some(ResourceRegion {
+                x: 0.5,
+                y: 0.25,
+                width: 0.5,
+                height: 0.5,
+            }),
+            page: None,
+            time_ms: None,
+            created_at: None,
+            extra: Map::new(),
+        });
+
+        let mut value = serde_json::to_value(&meta).expect("to value");
+        value["futureField"] = Value::from(7);
+        let parsed: ResourceMetaV1 = serde_json::from_value(value).expect("parse");
+        assert_eq!(parsed.extra.get("futureField"), Some(&Value::from(7)));
+
+        let json = parsed.canonical_json().expect("json");
+        assert!(json.contains("\"sourceUrl\""));
+        assert!(json.contains("\"futureField\": 7"));
+        let reparsed: ResourceMetaV1 = serde_json::from_str(&json).expect("reparse");
+        assert_eq!(reparsed, parsed);
+        assert_eq!(
+            reparsed.annotation("a1").map(|a| a.text.as_str()),
+            Some("legend")
+        );
+    }
+
+    #[test]
+    fn resource_meta_validation_rejects_bad_fields() {
+        let base = ResourceMetaV1::new("R1", "attachments/a.png").expect("new");
+        assert!(base.validate().is_ok());
+        assert!(ResourceMetaV1::new("../R1", "attachments/a.png").is_err());
+
+        let mut meta = base.clone();
+        meta.rating = Some(6);
+        assert!(meta.validate().is_err());
+
+        let mut meta = base.clone();
+        meta.palette.push("red".to_string());
+        assert!(meta.validate().is_err());
+
+        let mut meta = base.clone();
+        meta.source_url = Some("example.com".to_string());
+        assert!(meta.validate().is_err());
+
+        let mut meta = base.clone();
+        meta.path = "attachments\\a.png".to_string();
+        assert!(meta.validate().is_err());
+
+        let mut meta = base.clone();
+        meta.dimensions = Some(ResourceDimensions {
+            width: 0,
+            height: 10,
+        });
+        assert!(meta.validate().is_err());
+
+        let annotation = ResourceAnnotation {
+            id: "a1".to_string(),
+            text: String::new(),
+            region: Some(ResourceRegion {
+                x: 0.8,
+                y: 0.0,
+                width: 0.5,
+                height: 0.1,
+            }),
+            page: None,
+            time_ms: None,
+            created_at: None,
+            extra: Map::new(),
+        };
+        let mut meta = base.clone();
+        meta.annotations.push(annotation.clone());
+        assert!(meta.validate().is_err());
+
+        let mut meta = base;
+        let mut annotation = annotation;
+        annotation.region = None;
+        meta.annotations.push(annotation.clone());
+        meta.annotations.push(annotation);
+        assert!(meta.validate().is_err());
+    }
+}
@@ -0,0 +1,542 @@
+//! The full-text search query language.
+//!
+//! Whitespace-separated parts must all match. `OR` (upper case) joins alternatives and
+//! binds looser than the implicit `AND`; parentheses group; a leading `-` negates the part
+//! it is attached to. `"quoted text"` is a phrase. `name:value` parts filter on note
+//! fields (`tag:`, `path:`, `title:`, `id:`, `has:link`, `links-to:`, `linked-from:`), with
+//! a quoted value when it holds spaces; other words with a colon, like `TODO:` or
+//! `std::fs`, are searched as written. `[key op value]` compares a frontmatter property
+//! and `[key]` requires it, using the property query syntax.
+
+use crate::properties::{PropertyExpr, PropertyQuery};
+use std::ops::Range;
+
+const FILTER_NAMES: [&str; 7] = [
+    "tag",
+    "path",
+    "title",
+    "id",
+    "has",
+    "links-to",
+    "linked-from",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueryFilter {
+    /// The note has the tag or one nested under it (`tag:a` matches `a/b`).
+    Tag(String),
+    /// The vault-relative path contains the text.
+    Path(String),
+    /// The title contains the text.
+    Title(String),
+    /// The frontmatter id equals the text.
+    Id(String),
+    HasLink,
+    /// The note links to the note the target resolves to.
+    LinksTo(String),
+    /// The note is linked from the note the source resolves to.
+    LinkedFrom(String),
+    Property(PropertyExpr),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchExpr {
+    /// A bare word as typed; matched as search terms.
+    Term(String),
+    /// Quoted text as typed; matched verbatim in the note content.
+    Phrase(String),
+    Filter(QueryFilter),
+    Not(Box<SearchExpr>),
+    And(Vec<SearchExpr>),
+    Or(Vec<SearchExpr>),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// `None` for a blank query.
+    pub expr: Option<SearchExpr>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchQueryErrorKind {
+    UnterminatedQuote,
+    /// `[` without a closing `]`.
+    UnterminatedProperty,
+    /// `(` without a closing `)`.
+    UnclosedGroup,
+    /// `)` without an opening `(`.
+    UnexpectedClose,
+    /// Nothing to apply `-`, `OR`, `AND` or `()` to.
+    MissingOperand,
+    /// A filter with an empty quoted value, as in `tag:""`.
+    EmptyValue(String),
+    InvalidValue {
+        field: String,
+        value: String,
+    },
+    InvalidProperty(String),
+    /// A regex-mode query that is not a valid pattern.
+    InvalidRegex(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchQueryError {
+    pub kind: SearchQueryErrorKind,
+    /// Byte range of the offending text in the query.
+    pub span: Range<usize>,
+}
+
+impl std::fmt::Display for SearchQueryErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnterminatedQuote => write!(f, "unterminated quote"),
+            Self::UnterminatedProperty => write!(f, "expected `]`"),
+            Self::UnclosedGroup => write!(f, "expected `)`"),
+            Self::UnexpectedClose => write!(f, "unmatched `)`"),
+            Self::MissingOperand => write!(f, "expected a search term"),
+            Self::EmptyValue(name) => write!(f, "expected a value after `{name}:`"),
+            Self::InvalidValue { field, value } => write!(f, "invalid `{field}:` value `{value}`"),
+            Self::InvalidProperty(message) => write!(f, "property filter: {message}"),
+            Self::InvalidRegex(message) => write!(f, "invalid regex: {message}"),
+        }
+    }
+}
+
+impl std::fmt::Display for SearchQueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "search query error at {}..{}: {}",
+            self.span.start, self.span.end, self.kind
+        )
+    }
+}
+
+impl std::error::Error for SearchQueryError {}
+
+impl SearchQuery {
+    pub fn parse(text: &str) -> Result<Self, SearchQueryError> {
+        let tokens = lex(text)?;
+        if tokens.is_empty() {
+            return Ok(Self::default());
+        }
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: text.len(),
+        };
+        let expr = parser.or_expr()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(SearchQueryError {
+                kind: SearchQueryErrorKind::UnexpectedClose,
+                span: token.span.clone(),
+            });
+        }
+        Ok(Self { expr: Some(expr) })
+    }
+
+    /// Words and phrases outside any negation: what a matching note is expected to
+    /// contain, for ranking and highlighting.
+    pub fn positive_text(&self) -> Vec<&SearchExpr> {
+        fn walk<'a>(expr: &'a SearchExpr, out: &mut Vec<&'a SearchExpr>) {
+            match expr {
+                SearchExpr::Term(_) | SearchExpr::Phrase(_) => out.push(expr),
+                SearchExpr::And(parts) | SearchExpr::Or(parts) => {
+                    parts.iter().for_each(|part| walk(part, out));
+                }
+                SearchExpr::Filter(_) | SearchExpr::Not(_) => {}
+            }
+        }
+        let mut out = Vec::new();
+        if let Some(expr) = &self.expr {
+            walk(expr, &mut out);
+        }
+        out
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Tok {
+    Word(String),
+    Phrase(String),
+    Filter(QueryFilter),
+    Minus,
+    Or,
+    And,
+    LParen,
+    RParen,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    tok: Tok,
+    span: Range<usize>,
+}
+
+fn error(kind: SearchQueryErrorKind, span: Range<usize>) -> SearchQueryError {
+    SearchQueryError { kind, span }
+}
+
+fn lex(text: &str) -> Result<Vec<Token>, SearchQueryError> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while let Some(ch) = text[pos..].chars().next() {
+        let start = pos;
+        if ch.is_whitespace() {
+            pos += ch.len_utf8();
+            continue;
+        }
+        let (tok, end) = match ch {
+            '(' => (Tok::LParen, start + 1),
+            ')' => (Tok::RParen, start + 1),
+            '-' => {
+                let next = text[start + 1..].chars().next();
+                if next.is_none_or(|c| c.is_whitespace() || c == ')') {
+                    return Err(error(
+                        SearchQueryErrorKind::MissingOperand,
+                        start..start + 1,
+                    ));
+                }
+                (Tok::Minus, start + 1)
+            }
+            '"' => {
+                let (phrase, end) = lex_quoted(text, start)?;
+                (Tok::Phrase(phrase), end)
+            }
+            '[' => lex_property(text, start)?,
+            _ => lex_word(text, start)?,
+        };
+        tokens.push(Token {
+            tok,
+            span: start..end,
+        });
+        pos = end;
+    }
+    Ok(tokens)
+}
+
+/// The unescaped text of the quote opening at `start`, and the offset past its end.
+fn lex_quoted(text: &str, start: usize) -> Result<(String, usize), SearchQueryError> {
+    let mut value = String::new();
+    let mut chars = text[start + 1..].char_indices();
+    while let Some((ix, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, start + 1 + ix + 1)),
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            _ => value.push(c),
+        }
+    }
+    Err(error(
+        SearchQueryErrorKind::UnterminatedQuote,
+        start..text.len(),
+    ))
+}
+
+fn lex_property(text: &str, start: usize) -> Result<(Tok, usize), SearchQueryError> {
+    let mut quote = None;
+    let mut close = None;
+    for (ix, c) in text[start + 1..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ']') => {
+                close = Some(start + 1 + ix);
+                break;
+            }
+            (None, _) => {}
+        }
+    }
+    let Some(close) = close else {
+        return Err(error(
+            SearchQueryErrorKind::UnterminatedProperty,
+            start..text.len(),
+        ));
+    };
+    let inner_start = start + 1;
+    let parsed = PropertyQuery::parse(&text[inner_start..close]).map_err(|err| {
+        error(
+            SearchQueryErrorKind::InvalidProperty(err.message),
+            inner_start + err.span.start..inner_start + err.span.end,
+        )
+    })?;
+    let span = start..close + 1;
+    if !parsed.sort.is_empty() {
+        return Err(error(
+            SearchQueryErrorKind::InvalidProperty("sorting is not supported here".to_string()),
+            span,
+        ));
+    }
+    let Some(filter) = parsed.filter else {
+        return Err(error(SearchQueryErrorKind::MissingOperand, span));
+    };
+    Ok((Tok::Filter(QueryFilter::Property(filter)), close + 1))
+}
+
+fn lex_word(text: &str, start: usize) -> Result<(Tok, usize), SearchQueryError> {
+    let end = text[start..]
+        .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"'))
+        .map_or(text.len(), |len| start + len);
+    let word = &text[start..end];
+    match word {
+        "OR" => return Ok((Tok::Or, end)),
+        "AND" => return Ok((Tok::And, end)),
+        _ => {}
+    }
+
+    // Only the known filter names make a filter, and only with a value; `TODO:`,
+    // `std::fs` and URLs stay words.
+    let filter = word.split_once(':').filter(|(name, value)| {
+        FILTER_NAMES
+            .iter()
+            .any(|known| name.eq_ignore_ascii_case(known))
+            && (!value.is_empty() || text[end..].starts_with('"'))
+    });
+    let Some((name, value)) = filter else {
+        return Ok((Tok::Word(word.to_string()), end));
+    };
+    let name_span = start..start + name.len();
+    let (value, end) = if value.is_empty() && text[end..].starts_with('"') {
+        lex_quoted(text, end)?
+    } else {
+        (value.to_string(), end)
+    };
+    let value = value.trim();
+    let name = name.to_ascii_lowercase();
+    if value.is_empty() {
+        return Err(error(SearchQueryErrorKind::EmptyValue(name), start..end));
+    }
+
+    let filter = match name.as_str() {
+        "tag" => QueryFilter::Tag(value.trim_start_matches('#').to_lowercase()),
+        "path" => QueryFilter::Path(value.to_lowercase()),
+        "title" => QueryFilter::Title(value.to_lowercase()),
+        "id" => QueryFilter::Id(value.to_lowercase()),
+        "has" if value.eq_ignore_ascii_case("link") => QueryFilter::HasLink,
+        "has" => {
+            return Err(error(
+                SearchQueryErrorKind::InvalidValue {
+                    field: name,
+                    value: value.to_string(),
+                },
+                name_span.end + 1..end,
+            ))
+        }
+        "links-to" => QueryFilter::LinksTo(value.to_string()),
+        // `linked-from`, the last of `FILTER_NAMES`.
+        _ => QueryFilter::LinkedFrom(value.to_string()),
+    };
+    Ok((Tok::Filter(filter), end))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+
+    fn error_here(&self, kind: SearchQueryErrorKind) -> SearchQueryError {
+        let span = self
+            .tokens
+            .get(self.pos)
+            .map_or(self.end..self.end, |t| t.span.clone());
+        error(kind, span)
+    }
+
+    fn or_expr(&mut self) -> Result<SearchExpr, SearchQueryError> {
+        let mut parts = vec![self.and_expr()?];
+        while self.peek() == Some(&Tok::Or) {
+            self.pos += 1;
+            parts.push(self.and_expr()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            SearchExpr::Or(parts)
+        })
+    }
+
+    fn and_expr(&mut self) -> Result<SearchExpr, SearchQueryError> {
+        let mut parts = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                None | Some(Tok::Or) | Some(Tok::RParen) => break,
+                Some(Tok::And) => self.pos += 1,
+                Some(_) => {}
+            }
+            parts.push(self.unary()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            SearchExpr::And(parts)
+        })
+    }
+
+    fn unary(&mut self) -> Result<SearchExpr, SearchQueryError> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Err(self.error_here(SearchQueryErrorKind::MissingOperand));
+        };
+        let expr = match token.tok {
+            Tok::Minus => {
+                self.pos += 1;
+                return Ok(SearchExpr::Not(Box::new(self.unary()?)));
+            }
+            Tok::LParen => {
+                self.pos += 1;
+                if self.peek() == Some(&Tok::RParen) {
+                    return Err(self.error_here(SearchQueryErrorKind::MissingOperand));
+                }
+                let inner = self.or_expr()?;
+                if self.peek() != Some(&Tok::RParen) {
+                    return Err(error(SearchQueryErrorKind::UnclosedGroup, token.span));
+                }
+                inner
+            }
+            Tok::RParen => return Err(error(SearchQueryErrorKind::UnexpectedClose, token.span)),
+            Tok::Or | Tok::And => {
+                return Err(error(SearchQueryErrorKind::MissingOperand, token.span))
+            }
+            Tok::Phrase(phrase) if phrase.trim().is_empty() => {
+                return Err(error(SearchQueryErrorKind::MissingOperand, token.span))
+            }
+            Tok::Word(word) => SearchExpr::Term(word),
+            Tok::Phrase(phrase) => SearchExpr::Phrase(phrase),
+            Tok::Filter(filter) => SearchExpr::Filter(filter),
+        };
+        self.pos += 1;
+        Ok(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::properties::{PropertyComparison, PropertyOp};
+
+    #[test]
+    fn parses_phrases_negation_groups_and_filters() {
+        let query = SearchQuery::parse(
+            r#"Rust "Borrow Checker" -draft (tag:#Lang OR path:"my notes/") [priority >= 2] has:link links-to:Home"#,
+        )
+        .expect("parse");
+        assert_eq!(
+            query.expr,
+            Some(SearchExpr::And(vec![
+                SearchExpr::Term("Rust".into()),
+                SearchExpr::Phrase("Borrow Checker".into()),
+                SearchExpr::Not(Box::new(SearchExpr::Term("draft".into()))),
+                SearchExpr::Or(vec![
+                    SearchExpr::Filter(QueryFilter::Tag("lang".into())),
+                    SearchExpr::Filter(QueryFilter::Path("my notes/".into())),
+                ]),
+                SearchExpr::Filter(QueryFilter::Property(PropertyExpr::Compare(
+                    PropertyComparison {
+                        key: "priority".into(),
+                        op: PropertyOp::Ge,
+                        literal: "2".into(),
+                    }
+                ))),
+                SearchExpr::Filter(QueryFilter::HasLink),
+                SearchExpr::Filter(QueryFilter::LinksTo("Home".into())),
+            ]))
+        );
+        assert_eq!(
+            query.positive_text(),
+            vec![
+                &SearchExpr::Term("Rust".into()),
+                &SearchExpr::Phrase("Borrow Checker".into()),
+            ]
+        );
+
+        let or = SearchQuery::parse("a b OR -c AND d https://x.dev 12:30").expect("parse");
+        assert_eq!(
+            or.expr,
+            Some(SearchExpr::Or(vec![
+                SearchExpr::And(vec![
+                    SearchExpr::Term("a".into()),
+                    SearchExpr::Term("b".into()),
+                ]),
+                SearchExpr::And(vec![
+                    SearchExpr::Not(Box::new(SearchExpr::Term("c".into()))),
+                    SearchExpr::Term("d".into()),
+                    SearchExpr::Term("https://x.dev".into()),
+                    SearchExpr::Term("12:30".into()),
+                ]),
+            ]))
+        );
+        assert_eq!(SearchQuery::parse("  ").expect("blank").expr, None);
+
+        // Colons outside the known filter names are part of the word.
+        for (text, words) in [
+            ("TODO: fix", vec!["TODO:", "fix"]),
+            ("std::fs", vec!["std::fs"]),
+            ("Re: hello", vec!["Re:", "hello"]),
+            ("tga:rust tag:", vec!["tga:rust", "tag:"]),
+            ("tag: rust", vec!["tag:", "rust"]),
+        ] {
+            let mut terms = words
+                .into_iter()
+                .map(|word| SearchExpr::Term(word.into()))
+                .collect::<Vec<_>>();
+            let expected = if terms.len() == 1 {
+                terms.remove(0)
+            } else {
+                SearchExpr::And(terms)
+            };
+            assert_eq!(
+                SearchQuery::parse(text).expect(text).expr,
+                Some(expected),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn reports_errors_with_spans() {
+        use SearchQueryErrorKind as K;
+        let cases = [
+            ("a \"open", K::UnterminatedQuote, 2..7),
+            ("(a OR b", K::UnclosedGroup, 0..1),
+            ("a ) b", K::UnexpectedClose, 2..3),
+            ("a OR", K::MissingOperand, 4..4),
+            ("OR a", K::MissingOperand, 0..2),
+            ("a - b", K::MissingOperand, 2..3),
+            ("()", K::MissingOperand, 1..2),
+            ("tag:\"\"", K::EmptyValue("tag".into()), 0..6),
+            (
+                "has:tags",
+                K::InvalidValue {
+                    field: "has".into(),
+                    value: "tags".into(),
+                },
+                4..8,
+            ),
+            ("x [due", K::UnterminatedProperty, 2..6),
+            (
+                "[due >]",
+                K::InvalidProperty("expected a value".into()),
+                6..6,
+            ),
+            ("[]", K::MissingOperand, 0..2),
+        ];
+        for (text, kind, span) in cases {
+            let err = SearchQuery::parse(text).expect_err(text);
+            assert_eq!((err.kind, err.span), (kind, span), "{text}");
+        }
+        let err = SearchQuery::parse("has:x").expect_err("invalid value");
+        assert_eq!(
+            err.to_string(),
+            "search query error at 4..5: invalid `has:` value `x`"
+        );
+    }
+}
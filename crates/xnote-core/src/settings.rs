@@ -1,5 +1,9 @@
 use crate::keybind::Keymap;
+use crate::knowledge::{CjkSegmentation, TextTokenizer};
+use crate::migrate::{MigrationStep, SchemaMigrations};
 use crate::plugin::PluginPolicy;
+use crate::properties::PropertyTypes;
+use crate::relation_types::{RelationTypeDef, RelationTypeRegistry, RelationValidationMode};
 use anyhow::{Context as _, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -7,6 +11,13 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 const DEFAULT_PLUGIN_RUNTIME_MODE: &str = "in_process";
+pub const SETTINGS_SCHEMA_VERSION_CURRENT: u32 = 1;
+pub const SETTINGS_MIGRATIONS: SchemaMigrations = SchemaMigrations {
+    schema: "settings",
+    version_key: "schema_version",
+    current: SETTINGS_SCHEMA_VERSION_CURRENT,
+    steps: &[migrate_settings_v0_to_v1 as MigrationStep],
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct AppSettings {
@@ -32,6 +43,12 @@ pub struct AppSettings {
     pub ai: AiSettings,
     #[serde(default)]
     pub window_layout: WindowLayoutSettings,
+    #[serde(default)]
+    pub relations: RelationSettings,
+    #[serde(default)]
+    pub properties: PropertySettings,
+    #[serde(default)]
+    pub search: SearchSettings,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -98,6 +115,55 @@ pub struct FilesLinksSettings {
     pub prefer_wikilink_titles: bool,
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelationSettings {
+    #[serde(default)]
+    pub validation: RelationValidationMode,
+    /// User-defined relation types, added to the built-in `xnote.*` ones.
+    #[serde(default)]
+    pub types: Vec<RelationTypeDef>,
+}
+
+impl RelationSettings {
+    pub fn registry(&self) -> Result<RelationTypeRegistry> {
+        RelationTypeRegistry::with_user_types(&self.types)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PropertySettings {
+    /// Declared types by property key; other properties are typed from their YAML.
+    #[serde(default)]
+    pub types: PropertyTypes,
+}
+
+impl PropertySettings {
+    /// Declarations keyed the way the index looks properties up.
+    pub fn normalized_types(&self) -> PropertyTypes {
+        self.types
+            .iter()
+            .map(|(key, ty)| (crate::properties::property_key(key), *ty))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SearchSettings {
+    #[serde(default)]
+    pub cjk_segmentation: CjkSegmentation,
+    #[serde(default)]
+    pub stem_latin: bool,
+}
+
+impl SearchSettings {
+    pub fn tokenizer(&self) -> TextTokenizer {
+        TextTokenizer {
+            cjk: self.cjk_segmentation,
+            stem_latin: self.stem_latin,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct WindowLayoutSettings {
     #[serde(default)]
@@ -230,6 +296,9 @@ impl Default for AppSettings {
             plugin_policy: AppPluginPolicy::default(),
             ai: AiSettings::default(),
             window_layout: WindowLayoutSettings::default(),
+            relations: RelationSettings::default(),
+            properties: PropertySettings::default(),
+            search: SearchSettings::default(),
         }
     }
 }
@@ -337,6 +406,19 @@ impl AppSettings {
         }
         merged.ai.vcp_sync_ws = overlay.ai.vcp_sync_ws;
         merged.window_layout.merge_overlay(&overlay.window_layout);
+        merged.relations.validation = overlay.relations.validation;
+        for def in &overlay.relations.types {
+            merged
+                .relations
+                .types
+                .retain(|existing| existing.id != def.id);
+            merged.relations.types.push(def.clone());
+        }
+        merged
+            .properties
+            .types
+            .extend(overlay.properties.types.clone());
+        merged.search = overlay.search.clone();
         merged
     }
 }
@@ -442,16 +524,73 @@ pub fn save_project_settings(project_root: &Path, settings: &AppSettings) -> Res
     save_settings_to_path(&project_settings_path(project_root), settings)
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SettingsMigration {
+    pub from_version: u32,
+    pub to_version: u32,
+    /// Copy of the original file, written before the upgrade.
+    pub backup_path: Option<PathBuf>,
+}
+
+/// Upgrades a settings file on disk, keeping the original as `<file>.v<N>.bak`.
+///
+/// Returns `None` when the file is missing or already current.
+pub fn migrate_settings_file(path: &Path, dry_run: bool) -> Result<Option<SettingsMigration>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let (settings, from_version) = parse_settings_file(path)?;
+    if from_version == SETTINGS_SCHEMA_VERSION_CURRENT {
+        return Ok(None);
+    }
+
+    let mut backup_path = None;
+    if !dry_run {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "settings.json".to_string());
+        let backup = path.with_file_name(format!("{file_name}.v{from_version}.bak"));
+        fs::copy(path, &backup)
+            .with_context(|| format!("back up settings file: {}", path.display()))?;
+        save_settings_to_path(path, &settings)?;
+        backup_path = Some(backup);
+    }
+    Ok(Some(SettingsMigration {
+        from_version,
+        to_version: SETTINGS_SCHEMA_VERSION_CURRENT,
+        backup_path,
+    }))
+}
+
 fn load_settings_from_path(path: &Path) -> Result<AppSettings> {
     if !path.exists() {
         return Ok(AppSettings::default());
     }
 
+    let (settings, _stored_version) = parse_settings_file(path)?;
+    Ok(settings)
+}
+
+fn parse_settings_file(path: &Path) -> Result<(AppSettings, u32)> {
     let raw = fs::read_to_string(path)
         .with_context(|| format!("read settings file: {}", path.display()))?;
-    let settings: AppSettings = serde_json::from_str(&raw)
+    let value = serde_json::from_str(&raw)
         .with_context(|| format!("parse settings file: {}", path.display()))?;
-    Ok(settings)
+    let outcome = SETTINGS_MIGRATIONS
+        .migrate(value)
+        .with_context(|| format!("migrate settings file: {}", path.display()))?;
+    let settings: AppSettings = serde_json::from_value(outcome.value)
+        .with_context(|| format!("parse settings file: {}", path.display()))?;
+    Ok((settings, outcome.from_version))
+}
+
+/// v0 settings predate `schema_version`; the fields are unchanged, so stamping the
+/// version is the whole upgrade.
+fn migrate_settings_v0_to_v1(
+    _object: &mut serde_json::Map<String, serde_json::Value>,
+) -> Result<()> {
+    Ok(())
 }
 
 fn save_settings_to_path(path: &Path, settings: &AppSettings) -> Result<()> {
@@ -466,7 +605,7 @@ fn save_settings_to_path(path: &Path, settings: &AppSettings) -> Result<()> {
 }
 
 const fn default_schema_version() -> u32 {
-    1
+    SETTINGS_SCHEMA_VERSION_CURRENT
 }
 
 fn default_locale_string() -> String {
@@ -592,6 +731,42 @@ mod tests {
         let _ = fs::remove_dir_all(&dir);
     }
 
+    #[test]
+    fn unversioned_settings_fixture_loads_and_migrates_with_backup() {
+        let dir = temp_dir("migrate");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create dir");
+        let path = settings_path(&dir);
+        let fixture = r#"{"locale": "zh-CN", "appearance": {"theme": "dark"}}"#;
+        fs::write(&path, fixture).expect("write fixture");
+
+        let loaded = load_settings(&dir).expect("load v0");
+        assert_eq!(loaded.schema_version, SETTINGS_SCHEMA_VERSION_CURRENT);
+        assert_eq!(loaded.appearance.theme, "dark");
+
+        let dry = migrate_settings_file(&path, true)
+            .expect("dry run")
+            .expect("needs migration");
+        assert_eq!((dry.from_version, dry.backup_path), (0, None));
+        assert_eq!(fs::read_to_string(&path).expect("read"), fixture);
+
+        let done = migrate_settings_file(&path, false)
+            .expect("migrate")
+            .expect("migrated");
+        let backup = done.backup_path.expect("backup");
+        assert!(backup.ends_with("settings.json.v0.bak"));
+        assert_eq!(fs::read_to_string(&backup).expect("backup"), fixture);
+        assert_eq!(load_settings(&dir).expect("reload"), loaded);
+        assert!(migrate_settings_file(&path, false)
+            .expect("rerun")
+            .is_none());
+
+        fs::write(&path, r#"{"schema_version": 9}"#).expect("newer");
+        assert!(load_settings(&dir).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn build_keymap_from_overrides_and_context_rules() {
         let mut settings = AppSettings::default();
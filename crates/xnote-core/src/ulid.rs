@@ -0,0 +1,180 @@
+//! ULIDs (<https://github.com/ulid/spec>): 48-bit millisecond timestamp plus 80 random
+//! bits, written as 26 Crockford base32 characters that sort by creation time.
+
+use anyhow::{anyhow, Result};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const ULID_LEN: usize = 26;
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const RANDOM_BITS: u32 = 80;
+const RANDOM_MASK: u128 = (1u128 << RANDOM_BITS) - 1;
+const TIMESTAMP_MAX: u64 = (1u64 << 48) - 1;
+
+/// Last id handed out by [`Ulid::generate`], so ids from one process never go backwards.
+static LAST_ULID: Mutex<Option<Ulid>> = Mutex::new(None);
+static ENTROPY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ulid(u128);
+
+impl Ulid {
+    pub fn from_parts(timestamp_ms: u64, random: u128) -> Self {
+        Self((u128::from(timestamp_ms.min(TIMESTAMP_MAX)) << RANDOM_BITS) | (random & RANDOM_MASK))
+    }
+
+    pub const fn from_u128(value: u128) -> Self {
+        Self(value)
+    }
+
+    pub const fn to_u128(self) -> u128 {
+        self.0
+    }
+
+    pub const fn timestamp_ms(self) -> u64 {
+        (self.0 >> RANDOM_BITS) as u64
+    }
+
+    pub const fn random(self) -> u128 {
+        self.0 & RANDOM_MASK
+    }
+
+    /// New id for the current time, strictly greater than every id this process made before.
+    pub fn generate() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or_default();
+        let mut last = LAST_ULID.lock().unwrap_or_else(|err| err.into_inner());
+        let next = Self::next_after(*last, now, random_bits);
+        *last = Some(next);
+        next
+    }
+
+    /// Monotonic step: within the millisecond of `previous` (or when the clock went
+    /// back) the random part of `previous` is incremented instead of drawn again.
+    /// Overflowing the random part moves on to the next millisecond.
+    pub fn next_after(previous: Option<Self>, now_ms: u64, random: impl FnOnce() -> u128) -> Self {
+        match previous {
+            Some(prev) if now_ms <= prev.timestamp_ms() => {
+                if prev.random() == RANDOM_MASK {
+                    Self::from_parts(prev.timestamp_ms() + 1, 0)
+                } else {
+                    Self(prev.0 + 1)
+                }
+            }
+            _ => Self::from_parts(now_ms, random()),
+        }
+    }
+
+    /// Parses the canonical 26-character form, case-insensitively.
+    pub fn parse(text: &str) -> Result<Self> {
+        let bytes = text.trim().as_bytes();
+        if bytes.len() != ULID_LEN {
+            return Err(anyhow!("ulid must be {ULID_LEN} characters, got: {text}"));
+        }
+        if bytes[0] > b'7' {
+            return Err(anyhow!("ulid timestamp overflows 48 bits: {text}"));
+        }
+        let mut value = 0u128;
+        for &byte in bytes {
+            let digit = decode_crockford(byte)
+                .ok_or_else(|| anyhow!("ulid has a non-base32 character: {text}"))?;
+            value = (value << 5) | u128::from(digit);
+        }
+        Ok(Self(value))
+    }
+}
+
+impl std::fmt::Display for Ulid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = [0u8; ULID_LEN];
+        let mut value = self.0;
+        for slot in out.iter_mut().rev() {
+            *slot = CROCKFORD[(value & 0x1F) as usize];
+            value >>= 5;
+        }
+        f.write_str(std::str::from_utf8(&out).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+impl std::str::FromStr for Ulid {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text)
+    }
+}
+
+fn decode_crockford(byte: u8) -> Option<u8> {
+    let upper = byte.to_ascii_uppercase();
+    CROCKFORD
+        .iter()
+        .position(|&c| c == upper)
+        .map(|ix| ix as u8)
+}
+
+/// 80 bits from the std hasher, whose keys are seeded from OS randomness per process.
+fn random_bits() -> u128 {
+    let state = RandomState::new();
+    let draw = |salt: u64| {
+        let mut hasher = state.build_hasher();
+        hasher.write_u64(ENTROPY_COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u64(salt);
+        hasher.write_u128(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos())
+                .unwrap_or_default(),
+        );
+        hasher.finish()
+    };
+    let high = u128::from(draw(0));
+    let low = u128::from(draw(1));
+    ((high << 64) | low) & RANDOM_MASK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ulid_roundtrips_and_recovers_timestamp() {
+        let ulid = Ulid::from_parts(1_469_922_850_259, 0x0123_4567_89AB_CDEF_0123);
+        let text = ulid.to_string();
+        assert_eq!(text.len(), ULID_LEN);
+        assert_eq!(&text[..10], "01ARZ3NDEK");
+        assert_eq!(Ulid::parse(&text).expect("parse"), ulid);
+        assert_eq!(Ulid::parse(&text.to_lowercase()).expect("lowercase"), ulid);
+        assert_eq!(ulid.timestamp_ms(), 1_469_922_850_259);
+
+        assert!(Ulid::parse("01ARZ3NDEK").is_err());
+        assert!(Ulid::parse("01ARZ3NDEKTSV4RRFFQ69G5FAU").is_err());
+        assert!(Ulid::parse("81ARZ3NDEKTSV4RRFFQ69G5FAV").is_err());
+    }
+
+    #[test]
+    fn ulid_is_monotonic_within_a_millisecond() {
+        let first = Ulid::next_after(None, 1_000, || 41);
+        let second = Ulid::next_after(Some(first), 1_000, || unreachable!());
+        assert_eq!(second.random(), 42);
+        assert!(second > first);
+
+        let clock_went_back = Ulid::next_after(Some(second), 999, || unreachable!());
+        assert!(clock_went_back > second);
+        assert_eq!(clock_went_back.timestamp_ms(), 1_000);
+
+        let full = Ulid::from_parts(1_000, RANDOM_MASK);
+        let rolled = Ulid::next_after(Some(full), 1_000, || unreachable!());
+        assert_eq!((rolled.timestamp_ms(), rolled.random()), (1_001, 0));
+
+        let fresh = Ulid::next_after(Some(second), 1_001, || 7);
+        assert_eq!((fresh.timestamp_ms(), fresh.random()), (1_001, 7));
+
+        let generated = (0..64).map(|_| Ulid::generate()).collect::<Vec<_>>();
+        assert!(generated.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
@@ -1,15 +1,69 @@
-use crate::note_meta::{normalize_note_id, NoteMetaV1};
+use crate::atomic_write::{write_atomic, AtomicWriteOutcome};
+use crate::note_meta::{normalize_note_id, parse_note_meta_json, NoteMetaV1};
 use crate::paths::{
     join_inside, normalize_folder_rel_path, normalize_vault_rel_path, to_posix_path,
 };
+use crate::relation_types::{RelationTypeRegistry, RelationValidationMode};
+use crate::settings::{
+    load_project_settings, project_settings_path, PropertySettings, RelationSettings,
+    SearchSettings,
+};
 use anyhow::{Context as _, Result};
 use ignore::WalkBuilder;
 use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod attachments;
+pub mod check;
+mod conflict;
+#[cfg(unix)]
+mod coordination;
+mod history;
+mod info_meta;
+mod lock;
+mod migrate;
+mod moves;
+mod replace;
+mod resource_meta;
+mod resources;
+mod trash;
+
+pub use attachments::{
+    relative_link_path, AttachmentBlob, AttachmentGcReport, AttachmentImport, AttachmentIndex,
+    ATTACHMENT_INDEX_VERSION_V1,
+};
+pub use conflict::{NoteDiskState, NoteWriteConflict, NoteWriteExpectation};
+#[cfg(unix)]
+pub use coordination::{VaultCoordinationEvent, VaultCoordinationMessage, VaultCoordinator};
+pub use history::{HistoryRetention, NoteSaveSource, NoteVersion, HISTORY_INDEX_VERSION_V1};
+pub use lock::{
+    VaultLockHeld, VaultLockOptions, VaultLockOwner, VaultSessionLock, VAULT_LOCK_VERSION_V1,
+};
+pub use migrate::{MetaMigrationEntry, MetaMigrationFailure, MetaMigrationReport};
+pub use moves::{NoteContentChange, OrderFileChange, VaultMoveChangeSet, VaultMoveKind};
+pub use replace::{
+    NoteReplacePreview, ReplaceChangeSet, ReplaceEdit, ReplacePreview, ReplacedNote,
+};
+pub use resource_meta::ResolvedResource;
+pub use resources::{ResourceEntry, ResourceMediaKind};
+pub use trash::{TrashEntryKind, TrashManifest, TrashRestoreOutcome, TRASH_MANIFEST_VERSION_V1};
 
 #[derive(Debug, Clone)]
 pub struct Vault {
     root: PathBuf,
+    history_retention: HistoryRetention,
+    /// Relation validation for meta saves, shared by clones of the vault.
+    relation_types: Arc<Mutex<Option<CachedRelationTypes>>>,
+}
+
+/// Relation types loaded from `.xnote/settings.json`, with the file state they came from.
+#[derive(Debug)]
+struct CachedRelationTypes {
+    /// Modification time and length of the settings file; `None` when it was absent.
+    stamp: Option<(Option<SystemTime>, u64)>,
+    types: Arc<(RelationTypeRegistry, RelationValidationMode)>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -22,6 +76,8 @@ pub struct NoteEntry {
 pub struct VaultScan {
     pub notes: Vec<NoteEntry>,
     pub folders: Vec<String>,
+    /// Non-markdown files; only filled by [`Vault::fast_scan_with_resources`].
+    pub resources: Vec<ResourceEntry>,
 }
 
 impl Vault {
@@ -30,7 +86,11 @@ impl Vault {
         if !root.is_dir() {
             anyhow::bail!("vault root is not a directory");
         }
-        Ok(Self { root })
+        Ok(Self {
+            root,
+            history_retention: HistoryRetention::default(),
+            relation_types: Arc::default(),
+        })
     }
 
     pub fn root(&self) -> &Path {
@@ -57,8 +117,18 @@ impl Vault {
 
     /// Stage A+: fast scan for markdown files and folder paths.
     pub fn fast_scan_notes_and_folders(&self) -> Result<VaultScan> {
+        self.scan_vault_tree(false)
+    }
+
+    /// Stage A+ plus a [`ResourceEntry`] for every other visible file.
+    pub fn fast_scan_with_resources(&self) -> Result<VaultScan> {
+        self.scan_vault_tree(true)
+    }
+
+    fn scan_vault_tree(&self, include_resources: bool) -> Result<VaultScan> {
         let mut entries = Vec::new();
         let mut folders = BTreeSet::new();
+        let mut resources = Vec::new();
 
         let mut builder = WalkBuilder::new(&self.root);
         builder
@@ -93,10 +163,10 @@ impl Vault {
                 continue;
             }
 
-            if path
+            let is_note = path
                 .extension()
-                .is_none_or(|ext| ext.to_string_lossy().to_lowercase() != "md")
-            {
+                .is_some_and(|ext| ext.to_string_lossy().to_lowercase() == "md");
+            if !is_note && !include_resources {
                 continue;
             }
 
@@ -107,13 +177,22 @@ impl Vault {
                 continue;
             }
 
-            entries.push(NoteEntry { path: rel_posix });
+            if is_note {
+                entries.push(NoteEntry { path: rel_posix });
+            } else {
+                resources.push(ResourceEntry::from_metadata(
+                    rel_posix,
+                    dent.metadata().ok(),
+                ));
+            }
         }
 
         entries.sort_by(|a, b| a.path.cmp(&b.path));
+        resources.sort_by(|a, b| a.path.cmp(&b.path));
         Ok(VaultScan {
             notes: entries,
             folders: folders.into_iter().collect(),
+            resources,
         })
     }
 
@@ -124,13 +203,40 @@ impl Vault {
     }
 
     pub fn write_note(&self, note_path: &str, content: &str) -> Result<()> {
+        self.write_note_with_outcome(note_path, content)?;
+        Ok(())
+    }
+
+    /// Atomically writes a note and reports what the write replaced.
+    pub fn write_note_with_outcome(
+        &self,
+        note_path: &str,
+        content: &str,
+    ) -> Result<AtomicWriteOutcome> {
+        self.write_note_with_source(note_path, content, NoteSaveSource::User)
+    }
+
+    /// Atomically writes a note and records the saved content in `.xnote/history`.
+    ///
+    /// On-disk content that history has not seen yet is recorded first as an
+    /// [`NoteSaveSource::External`] version so the write never discards it.
+    pub fn write_note_with_source(
+        &self,
+        note_path: &str,
+        content: &str,
+        source: NoteSaveSource,
+    ) -> Result<AtomicWriteOutcome> {
         let rel = normalize_vault_rel_path(note_path)?;
         let full = join_inside(&self.root, &rel)?;
-        if let Some(parent) = full.parent() {
-            std::fs::create_dir_all(parent).with_context(|| "create note parent dir")?;
+        // History is best-effort on both sides of the write: an unreadable history index
+        // must not keep the note from being saved.
+        if let Ok(previous) = std::fs::read_to_string(&full) {
+            let _ = self.record_note_version(&rel, &previous, NoteSaveSource::External);
         }
-        std::fs::write(&full, content).with_context(|| format!("write note: {rel}"))?;
-        Ok(())
+        let outcome = write_atomic(&full, content.as_bytes())
+            .with_context(|| format!("write note: {rel}"))?;
+        let _ = self.record_note_version(&rel, content, source);
+        Ok(outcome)
     }
 
     pub fn order_file_path(&self, folder: &str) -> Result<PathBuf> {
@@ -159,12 +265,8 @@ impl Vault {
     pub fn save_folder_order(&self, folder: &str, ordered_paths: &[String]) -> Result<()> {
         let folder_norm = normalize_folder_rel_path(folder)?;
         let order_path = self.order_file_path(&folder_norm)?;
-        if let Some(parent) = order_path.parent() {
-            std::fs::create_dir_all(parent).with_context(|| "create order dir")?;
-        }
-
         let content = format_order_md(&folder_norm, ordered_paths);
-        std::fs::write(&order_path, content)
+        write_atomic(&order_path, content.as_bytes())
             .with_context(|| format!("write order file: {:?}", order_path))?;
         Ok(())
     }
@@ -186,21 +288,27 @@ impl Vault {
             }
         };
 
-        let meta: NoteMetaV1 = serde_json::from_str(&content)
-            .with_context(|| format!("parse note meta json: {:?}", path))?;
-        meta.validate()
-            .with_context(|| format!("validate note meta file: {:?}", path))?;
+        let (meta, _stored_version) = parse_note_meta_json(&content)
+            .with_context(|| format!("load note meta file: {:?}", path))?;
         Ok(Some(meta))
     }
 
+    /// Saves the meta; vaults whose settings ask for strict relation validation reject
+    /// relations the relation type registry does not allow.
+    ///
+    /// Settings are only re-read when the settings file changes. Settings that fail to load
+    /// fall back to the built-in types in warn mode, so they never block a save.
     pub fn save_note_meta(&self, note_meta: &NoteMetaV1) -> Result<()> {
-        note_meta.validate()?;
-        let path = self.note_meta_file_path(&note_meta.id)?;
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent).with_context(|| "create note meta parent dir")?;
+        let types = self.relation_validation();
+        let (registry, mode) = &*types;
+        if *mode == RelationValidationMode::Strict {
+            note_meta.validate_with_registry(registry, *mode)?;
+        } else {
+            note_meta.validate()?;
         }
+        let path = self.note_meta_file_path(&note_meta.id)?;
         let content = note_meta.canonical_json()?;
-        std::fs::write(&path, content)
+        write_atomic(&path, content.as_bytes())
             .with_context(|| format!("write note meta file: {:?}", path))?;
         Ok(())
     }
@@ -213,6 +321,101 @@ impl Vault {
             Err(err) => Err(err).with_context(|| format!("delete note meta file: {:?}", path)),
         }
     }
+
+    /// Relation settings from `.xnote/settings.json`, or the defaults when it is absent.
+    pub fn relation_settings(&self) -> Result<RelationSettings> {
+        Ok(load_project_settings(&self.root)?
+            .map(|settings| settings.relations)
+            .unwrap_or_default())
+    }
+
+    /// Built-in plus vault-defined relation types and the vault's validation mode.
+    pub fn relation_type_registry(&self) -> Result<(RelationTypeRegistry, RelationValidationMode)> {
+        let settings = self.relation_settings()?;
+        let registry = settings
+            .registry()
+            .with_context(|| "load relation types from vault settings")?;
+        Ok((registry, settings.validation))
+    }
+
+    fn relation_validation(&self) -> Arc<(RelationTypeRegistry, RelationValidationMode)> {
+        let stamp = std::fs::metadata(project_settings_path(&self.root))
+            .ok()
+            .map(|m| (m.modified().ok(), m.len()));
+        let mut cached = self
+            .relation_types
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if let Some(cached) = cached.as_ref().filter(|cached| cached.stamp == stamp) {
+            return cached.types.clone();
+        }
+        let types = Arc::new(self.relation_type_registry().unwrap_or_else(|_| {
+            (
+                RelationTypeRegistry::builtin(),
+                RelationValidationMode::Warn,
+            )
+        }));
+        *cached = Some(CachedRelationTypes {
+            stamp,
+            types: types.clone(),
+        });
+        types
+    }
+
+    /// Property type declarations from `.xnote/settings.json`, or none when it is absent.
+    pub fn property_settings(&self) -> Result<PropertySettings> {
+        Ok(load_project_settings(&self.root)?
+            .map(|settings| settings.properties)
+            .unwrap_or_default())
+    }
+
+    /// Search tokenizer options from `.xnote/settings.json`, or the defaults.
+    pub fn search_settings(&self) -> Result<SearchSettings> {
+        Ok(load_project_settings(&self.root)?
+            .map(|settings| settings.search)
+            .unwrap_or_default())
+    }
+
+    /// Every loadable note meta record in `.xnote/meta`, sorted by id.
+    ///
+    /// Files that fail to load are skipped; [`Vault::check`] reports them.
+    pub fn list_note_metas(&self) -> Result<Vec<NoteMetaV1>> {
+        let mut out = Vec::new();
+        for path in list_json_files(&self.root.join(".xnote").join("meta"))? {
+            let Some(note_id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if let Ok(Some(meta)) = self.load_note_meta(note_id) {
+                out.push(meta);
+            }
+        }
+        out.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(out)
+    }
+}
+
+/// Milliseconds since the Unix epoch, for timestamps stored under `.xnote`.
+fn now_epoch_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// `.json` files directly in `dir`, sorted; none when `dir` does not exist.
+fn list_json_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("read dir: {:?}", dir)),
+    };
+    let mut out = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    out.sort();
+    Ok(out)
 }
 
 pub fn parse_order_md(content: &str) -> Vec<String> {
@@ -250,6 +453,19 @@ pub fn format_order_md(folder: &str, ordered_paths: &[String]) -> String {
     s
 }
 
+/// Opens an empty vault in a fresh temp dir `xnote_core_<name>_<pid>`, with `dirs` created.
+#[cfg(test)]
+pub(crate) fn test_vault(name: &str, dirs: &[&str]) -> (PathBuf, Vault) {
+    let temp_dir = std::env::temp_dir().join(format!("xnote_core_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    std::fs::create_dir_all(&temp_dir).expect("create vault dir");
+    for dir in dirs {
+        std::fs::create_dir_all(temp_dir.join(dir)).expect("create vault subdir");
+    }
+    let vault = Vault::open(&temp_dir).expect("open vault");
+    (temp_dir, vault)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -382,6 +598,12 @@ mod tests {
         assert_eq!(loaded.relations.len(), 1);
         assert_eq!(loaded.pins.notes, vec!["01HTARGET"]);
 
+        fs::write(project_settings_path(&temp_dir), "{ not json").expect("break settings");
+        assert!(vault.relation_type_registry().is_err());
+        vault
+            .save_note_meta(&meta)
+            .expect("malformed settings do not block meta saves");
+
         vault.delete_note_meta("01HNOTE").expect("delete note meta");
         assert!(vault
             .load_note_meta("01HNOTE")
@@ -0,0 +1,477 @@
+//! Content-addressed attachment store.
+//!
+//! Blobs live at `attachments/<hh>/<sha256>.<ext>` so plain relative links from notes
+//! keep working in any markdown tool. `.xnote/attachments.json` maps each hash to the
+//! human-readable names it was imported under.
+
+use super::resource_meta::ResourceLookup;
+use super::{now_epoch_ms, Vault};
+use crate::atomic_write::{content_hash, write_atomic};
+use crate::knowledge::collect_link_targets_with_embeds;
+use crate::paths::join_inside;
+use anyhow::{anyhow, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const ATTACHMENT_INDEX_VERSION_V1: u32 = 1;
+const ATTACHMENT_INDEX_FILE: &str = "attachments.json";
+const ATTACHMENTS_DIR: &str = "attachments";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AttachmentBlob {
+    pub hash: String,
+    /// Vault-relative POSIX path of the stored blob.
+    pub path: String,
+    pub size: u64,
+    /// Names the blob was imported under, oldest first.
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(rename = "importedAtMs")]
+    pub imported_at_ms: u64,
+}
+
+impl AttachmentBlob {
+    /// The first name the blob was imported under, or its file name.
+    pub fn display_name(&self) -> &str {
+        self.names
+            .first()
+            .map(String::as_str)
+            .unwrap_or_else(|| self.path.rsplit('/').next().unwrap_or(&self.path))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AttachmentIndex {
+    pub version: u32,
+    #[serde(default)]
+    pub blobs: BTreeMap<String, AttachmentBlob>,
+}
+
+impl Default for AttachmentIndex {
+    fn default() -> Self {
+        Self {
+            version: ATTACHMENT_INDEX_VERSION_V1,
+            blobs: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttachmentImport {
+    pub blob: AttachmentBlob,
+    /// True when identical content was already stored and no new file was written.
+    pub deduplicated: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AttachmentGcReport {
+    /// Reference count per blob hash, from note links and meta.
+    pub ref_counts: BTreeMap<String, usize>,
+    /// Unreferenced blobs; deleted unless the run was a dry run.
+    pub unreferenced: Vec<AttachmentBlob>,
+}
+
+impl Vault {
+    pub fn attachment_index_path(&self) -> PathBuf {
+        self.root.join(".xnote").join(ATTACHMENT_INDEX_FILE)
+    }
+
+    pub fn load_attachment_index(&self) -> Result<AttachmentIndex> {
+        let path = self.attachment_index_path();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(AttachmentIndex::default())
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("read attachment index: {:?}", path))
+            }
+        };
+        let index: AttachmentIndex = serde_json::from_str(&content)
+            .with_context(|| format!("parse attachment index: {:?}", path))?;
+        if index.version != ATTACHMENT_INDEX_VERSION_V1 {
+            return Err(anyhow!(
+                "unsupported attachment index version: {}",
+                index.version
+            ));
+        }
+        Ok(index)
+    }
+
+    fn save_attachment_index(&self, index: &AttachmentIndex) -> Result<()> {
+        let path = self.attachment_index_path();
+        let mut content = serde_json::to_string_pretty(index)?;
+        content.push('\n');
+        write_atomic(&path, content.as_bytes())
+            .with_context(|| format!("write attachment index: {:?}", path))?;
+        Ok(())
+    }
+
+    /// Stores `bytes` under their content hash, reusing an existing blob with the same content.
+    pub fn import_attachment_bytes(&self, name: &str, bytes: &[u8]) -> Result<AttachmentImport> {
+        let name = name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) {
+            return Err(anyhow!(
+                "attachment name must be a plain file name: {name:?}"
+            ));
+        }
+
+        let hash = content_hash(bytes);
+        let mut index = self.load_attachment_index()?;
+
+        if let Some(blob) = index.blobs.get_mut(&hash) {
+            let full = join_inside(&self.root, &blob.path)?;
+            if full.is_file() {
+                if !blob.names.iter().any(|n| n == name) {
+                    blob.names.push(name.to_string());
+                }
+                let blob = blob.clone();
+                self.save_attachment_index(&index)?;
+                return Ok(AttachmentImport {
+                    blob,
+                    deduplicated: true,
+                });
+            }
+        }
+
+        let path = blob_rel_path(&hash, &attachment_extension(name));
+        let full = join_inside(&self.root, &path)?;
+        let deduplicated = full.is_file();
+        if !deduplicated {
+            write_atomic(&full, bytes).with_context(|| format!("write attachment: {path}"))?;
+        }
+
+        let blob = index
+            .blobs
+            .entry(hash.clone())
+            .and_modify(|blob| {
+                blob.path = path.clone();
+                if !blob.names.iter().any(|n| n == name) {
+                    blob.names.push(name.to_string());
+                }
+            })
+            .or_insert_with(|| AttachmentBlob {
+                hash,
+                path: path.clone(),
+                size: bytes.len() as u64,
+                names: vec![name.to_string()],
+                imported_at_ms: now_epoch_ms(),
+            })
+            .clone();
+        self.save_attachment_index(&index)?;
+        Ok(AttachmentImport { blob, deduplicated })
+    }
+
+    /// Imports a file from outside the vault, named after its file name.
+    pub fn import_attachment_file(&self, source: &Path) -> Result<AttachmentImport> {
+        let name = source
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("attachment source has no utf-8 file name: {:?}", source))?;
+        let bytes =
+            std::fs::read(source).with_context(|| format!("read attachment: {:?}", source))?;
+        self.import_attachment_bytes(name, &bytes)
+    }
+
+    /// Finds a stored blob by a name it was imported under, most recent import first.
+    pub fn find_attachment_by_name(&self, name: &str) -> Result<Option<AttachmentBlob>> {
+        let index = self.load_attachment_index()?;
+        Ok(index
+            .blobs
+            .into_values()
+            .filter(|blob| blob.names.iter().any(|n| n == name))
+            .max_by_key(|blob| blob.imported_at_ms))
+    }
+
+    /// Counts references to every stored blob from note links and note meta.
+    ///
+    /// Notes reference a blob through any link or embed whose file name is the blob's
+    /// file name; note meta references it through a pinned resource or a `resource`
+    /// relation that resolves to the blob, and a resource meta record through its path.
+    ///
+    /// Fails when a note or note meta cannot be read, since counts without its references
+    /// would let [`Vault::gc_attachments`] delete blobs still in use.
+    pub fn attachment_ref_counts(&self) -> Result<BTreeMap<String, usize>> {
+        let index = self.load_attachment_index()?;
+        let mut counts = index
+            .blobs
+            .keys()
+            .map(|hash| (hash.clone(), 0usize))
+            .collect::<BTreeMap<_, _>>();
+        let mut by_key = BTreeMap::<String, String>::new();
+        for blob in index.blobs.values() {
+            let file_name = blob.path.rsplit('/').next().unwrap_or(&blob.path);
+            by_key.insert(file_name.to_string(), blob.hash.clone());
+            by_key.insert(blob.path.clone(), blob.hash.clone());
+            by_key.insert(blob.hash.clone(), blob.hash.clone());
+        }
+        if by_key.is_empty() {
+            return Ok(counts);
+        }
+
+        for note in self.fast_scan_notes()? {
+            let content = self
+                .read_note(&note.path)
+                .with_context(|| format!("count attachment refs in note: {}", note.path))?;
+            for target in collect_link_targets_with_embeds(&content) {
+                let file_name = target.rsplit(['/', '\\']).next().unwrap_or(&target);
+                if let Some(hash) = by_key.get(file_name) {
+                    *counts.entry(hash.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        let resources = ResourceLookup::load(self)?;
+        for meta in resources.metas() {
+            if let Some(hash) = by_key.get(&meta.path) {
+                *counts.entry(hash.clone()).or_default() += 1;
+            }
+        }
+
+        let meta_dir = self.root.join(".xnote").join("meta");
+        let entries = match std::fs::read_dir(&meta_dir) {
+            Ok(entries) => entries.flatten().collect::<Vec<_>>(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err).with_context(|| format!("read meta dir: {:?}", meta_dir)),
+        };
+        for entry in entries {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Some(note_id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Some(meta) = self
+                .load_note_meta(note_id)
+                .with_context(|| format!("count attachment refs in meta: {:?}", path))?
+            else {
+                continue;
+            };
+            let relation_ids = meta
+                .relations
+                .iter()
+                .filter(|r| r.to.kind.trim() == "resource")
+                .map(|r| r.to.id.as_str());
+            for id in meta
+                .pins
+                .resources
+                .iter()
+                .map(String::as_str)
+                .chain(relation_ids)
+            {
+                let Some(resolved) = resources.resolve(self, id) else {
+                    continue;
+                };
+                if let Some(hash) = by_key.get(&resolved.path) {
+                    *counts.entry(hash.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        Ok(counts)
+    }
+
+    /// Removes blobs nothing references, or only reports them when `dry_run` is set.
+    ///
+    /// Nothing is removed when any note or note meta cannot be read.
+    pub fn gc_attachments(&self, dry_run: bool) -> Result<AttachmentGcReport> {
+        let ref_counts = self.attachment_ref_counts()?;
+        let mut index = self.load_attachment_index()?;
+        let unreferenced = index
+            .blobs
+            .values()
+            .filter(|blob| ref_counts.get(&blob.hash).copied().unwrap_or_default() == 0)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !dry_run && !unreferenced.is_empty() {
+            for blob in &unreferenced {
+                let full = join_inside(&self.root, &blob.path)?;
+                match std::fs::remove_file(&full) {
+                    Ok(()) => {}
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => {
+                        return Err(err)
+                            .with_context(|| format!("remove attachment blob: {}", blob.path))
+                    }
+                }
+                if let Some(shard) = full.parent() {
+                    // Only succeeds once the shard directory is empty.
+                    let _ = std::fs::remove_dir(shard);
+                }
+                index.blobs.remove(&blob.hash);
+            }
+            self.save_attachment_index(&index)?;
+        }
+
+        Ok(AttachmentGcReport {
+            ref_counts,
+            unreferenced,
+        })
+    }
+}
+
+/// Relative link from the folder of `note_path` to `target_path`, both vault-relative.
+pub fn relative_link_path(note_path: &str, target_path: &str) -> String {
+    let note_dir = note_path
+        .rsplit_once('/')
+        .map(|(dir, _)| dir.split('/').collect::<Vec<_>>())
+        .unwrap_or_default();
+    let target = target_path.split('/').collect::<Vec<_>>();
+    let common = note_dir
+        .iter()
+        .zip(target.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut parts = vec![".."; note_dir.len() - common];
+    parts.extend(&target[common..]);
+    parts.join("/")
+}
+
+fn blob_rel_path(hash: &str, extension: &str) -> String {
+    if extension.is_empty() {
+        format!("{ATTACHMENTS_DIR}/{}/{hash}", &hash[..2])
+    } else {
+        format!("{ATTACHMENTS_DIR}/{}/{hash}.{extension}", &hash[..2])
+    }
+}
+
+fn attachment_extension(name: &str) -> String {
+    Path::new(name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .filter(|ext| ext.len() <= 16 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_meta::NoteMetaV1;
+    use crate::resource_meta::ResourceMetaV1;
+    use std::fs;
+
+    #[test]
+    fn import_dedupes_by_content_and_gc_keeps_referenced_blobs() {
+        let temp_dir = std::env::temp_dir().join(format!(
+            "xnote_core_vault_attachments_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes/deep")).expect("create notes");
+        let vault = Vault::open(&temp_dir).expect("open vault");
+
+        let first = vault
+            .import_attachment_bytes("Screenshot.PNG", b"image-a")
+            .expect("import a");
+        assert!(!first.deduplicated);
+        assert!(first.blob.path.ends_with(".png"));
+        let again = vault
+            .import_attachment_bytes("copy.png", b"image-a")
+            .expect("import a again");
+        assert!(again.deduplicated);
+        assert_eq!(again.blob.path, first.blob.path);
+        assert_eq!(again.blob.names, vec!["Screenshot.PNG", "copy.png"]);
+        assert_eq!(
+            vault
+                .find_attachment_by_name("copy.png")
+                .expect("find")
+                .map(|b| b.hash),
+            Some(first.blob.hash.clone())
+        );
+
+        let pinned = vault
+            .import_attachment_bytes("paper.pdf", b"pdf")
+            .expect("import pdf");
+        let orphan = vault
+            .import_attachment_bytes("unused.zip", b"zip")
+            .expect("import zip");
+
+        let link = relative_link_path("notes/deep/A.md", &first.blob.path);
+        assert!(link.starts_with("../../attachments/"));
+        fs::write(
+            temp_dir.join("notes/deep/A.md"),
+            format!("---\nid: N1\n---\n![shot]({link})\n"),
+        )
+        .expect("write note");
+        let mut meta = NoteMetaV1::new("N1").expect("meta");
+        meta.pins.resources.push(pinned.blob.hash.clone());
+        vault.save_note_meta(&meta).expect("save meta");
+        let described = vault
+            .import_attachment_bytes("photo.jpg", b"jpg")
+            .expect("import jpg");
+        vault
+            .save_resource_meta(
+                &ResourceMetaV1::new("R1", &described.blob.path).expect("resource meta"),
+            )
+            .expect("save resource meta");
+
+        let dry = vault.gc_attachments(true).expect("dry gc");
+        assert_eq!(dry.ref_counts.get(&first.blob.hash), Some(&1));
+        assert_eq!(dry.ref_counts.get(&pinned.blob.hash), Some(&1));
+        assert_eq!(dry.ref_counts.get(&described.blob.hash), Some(&1));
+        assert_eq!(dry.unreferenced, vec![orphan.blob.clone()]);
+        assert!(join_inside(&temp_dir, &orphan.blob.path)
+            .expect("path")
+            .is_file());
+
+        vault.gc_attachments(false).expect("gc");
+        assert!(!join_inside(&temp_dir, &orphan.blob.path)
+            .expect("path")
+            .exists());
+        assert!(join_inside(&temp_dir, &first.blob.path)
+            .expect("path")
+            .is_file());
+        let index = vault.load_attachment_index().expect("index");
+        assert_eq!(index.blobs.len(), 3);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn gc_keeps_blobs_when_a_meta_cannot_be_read() {
+        let temp_dir = std::env::temp_dir().join(format!(
+            "xnote_core_vault_attachments_unreadable_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("notes")).expect("create notes");
+        let vault = Vault::open(&temp_dir).expect("open vault");
+
+        let pinned = vault
+            .import_attachment_bytes("paper.pdf", b"pdf")
+            .expect("import pdf");
+        let mut meta = NoteMetaV1::new("N1").expect("meta");
+        meta.pins.resources.push(pinned.blob.hash.clone());
+        vault.save_note_meta(&meta).expect("save meta");
+        let meta_path = vault.note_meta_file_path("N1").expect("meta path");
+        let saved = fs::read_to_string(&meta_path).expect("read meta");
+        fs::write(&meta_path, &saved[..saved.len() / 2]).expect("truncate meta");
+
+        vault.gc_attachments(false).expect_err("unreadable meta");
+        assert!(join_inside(&temp_dir, &pinned.blob.path)
+            .expect("path")
+            .is_file());
+        assert_eq!(vault.load_attachment_index().expect("index").blobs.len(), 1);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn relative_link_path_walks_up_from_note_folder() {
+        assert_eq!(
+            relative_link_path("A.md", "attachments/ab/x.png"),
+            "attachments/ab/x.png"
+        );
+        assert_eq!(
+            relative_link_path("notes/sub/A.md", "attachments/ab/x.png"),
+            "../../attachments/ab/x.png"
+        );
+        assert_eq!(
+            relative_link_path("attachments/notes.md", "attachments/ab/x.png"),
+            "ab/x.png"
+        );
+    }
+}
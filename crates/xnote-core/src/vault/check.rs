@@ -0,0 +1,885 @@
+//! Vault integrity check ("fsck") over notes, `.xnote/meta` and `.xnote/order`.
+
+use super::resource_meta::ResourceLookup;
+use super::{NoteSaveSource, Vault};
+use crate::info_meta::InfoMetaV1;
+use crate::note_meta::{
+    extract_note_id_from_frontmatter, generate_note_id, parse_note_meta_json,
+    replace_frontmatter_note_id, NOTE_META_VERSION_CURRENT,
+};
+use crate::paths::{join_inside, to_posix_path};
+use crate::relation_types::{RelationTypeRegistry, RelationValidationMode};
+use crate::resource_meta::ResourceMetaV1;
+use anyhow::{Context as _, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl CheckSeverity {
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckFindingKind {
+    /// Note that cannot be read as UTF-8 text, so its frontmatter id is unknown.
+    UnreadableNote,
+    /// Meta file whose id matches no note's frontmatter `id:`.
+    OrphanMeta,
+    /// Meta file that cannot be parsed or fails validation.
+    InvalidMeta,
+    /// Meta file stored under a name that differs from its `id`.
+    MetaFileNameMismatch,
+    /// Meta file readable only through an in-memory schema migration.
+    OutdatedMetaVersion,
+    /// Resource meta whose `path` no longer exists.
+    OrphanResourceMeta,
+    DuplicateNoteId,
+    StaleOrderEntry,
+    /// Order file for a folder that no longer exists.
+    OrphanOrderFile,
+    UnknownRelationTarget,
+    /// Relation the relation type registry does not allow; an error in strict mode.
+    InvalidRelationType,
+    UnknownPinnedNote,
+    UnknownPinnedResource,
+    UnknownPinnedInfo,
+}
+
+impl CheckFindingKind {
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::UnreadableNote => "unreadable_note",
+            Self::OrphanMeta => "orphan_meta",
+            Self::InvalidMeta => "invalid_meta",
+            Self::MetaFileNameMismatch => "meta_file_name_mismatch",
+            Self::OutdatedMetaVersion => "outdated_meta_version",
+            Self::OrphanResourceMeta => "orphan_resource_meta",
+            Self::DuplicateNoteId => "duplicate_note_id",
+            Self::StaleOrderEntry => "stale_order_entry",
+            Self::OrphanOrderFile => "orphan_order_file",
+            Self::UnknownRelationTarget => "unknown_relation_target",
+            Self::InvalidRelationType => "invalid_relation_type",
+            Self::UnknownPinnedNote => "unknown_pinned_note",
+            Self::UnknownPinnedResource => "unknown_pinned_resource",
+            Self::UnknownPinnedInfo => "unknown_pinned_info",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckFinding {
+    pub severity: CheckSeverity,
+    pub kind: CheckFindingKind,
+    /// Vault-relative POSIX path of the file the finding is about.
+    pub path: String,
+    pub detail: String,
+    /// Whether repair mode fixes this finding.
+    pub repairable: bool,
+    /// Set once repair mode has fixed the finding.
+    pub repaired: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CheckOptions {
+    /// Remove orphan meta and order files, re-id duplicate notes and prune stale order entries.
+    pub repair: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    pub notes_scanned: usize,
+    pub meta_files_scanned: usize,
+    pub order_files_scanned: usize,
+    pub findings: Vec<CheckFinding>,
+}
+
+impl CheckReport {
+    pub fn count(&self, severity: CheckSeverity) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == severity)
+            .count()
+    }
+
+    /// True when no unrepaired finding of `Warning` or higher remains.
+    pub fn is_clean(&self) -> bool {
+        self.findings
+            .iter()
+            .all(|f| f.repaired || f.severity < CheckSeverity::Warning)
+    }
+}
+
+impl Vault {
+    /// Scans the vault for integrity problems, repairing the fixable ones when asked to.
+    pub fn check(&self, options: CheckOptions) -> Result<CheckReport> {
+        let mut report = CheckReport::default();
+
+        let scan = self.fast_scan_notes_and_folders()?;
+        report.notes_scanned = scan.notes.len();
+
+        let mut paths_by_id = BTreeMap::<String, Vec<String>>::new();
+        for note in &scan.notes {
+            let full = join_inside(&self.root, &note.path)?;
+            let content = match std::fs::read_to_string(&full) {
+                Ok(content) => content,
+                Err(err) => {
+                    report.findings.push(CheckFinding {
+                        severity: CheckSeverity::Error,
+                        kind: CheckFindingKind::UnreadableNote,
+                        path: note.path.clone(),
+                        detail: err.to_string(),
+                        repairable: false,
+                        repaired: false,
+                    });
+                    continue;
+                }
+            };
+            if let Some(note_id) = extract_note_id_from_frontmatter(&content) {
+                paths_by_id
+                    .entry(note_id)
+                    .or_default()
+                    .push(note.path.clone());
+            }
+        }
+
+        self.check_duplicate_ids(&paths_by_id, options, &mut report)?;
+        let note_ids = paths_by_id.keys().cloned().collect::<HashSet<_>>();
+        let all_note_ids_known = !report
+            .findings
+            .iter()
+            .any(|f| f.kind == CheckFindingKind::UnreadableNote);
+        let resources = ResourceLookup::load(self)?;
+        self.check_resource_meta_files(&mut report)?;
+        let info_ids = self.check_info_meta_files(&mut report)?;
+        let (relation_types, relation_mode) = self.relation_type_registry()?;
+        let targets = MetaTargets {
+            note_ids: &note_ids,
+            all_note_ids_known,
+            resources: &resources,
+            info_ids: &info_ids,
+            relation_types: &relation_types,
+            relation_mode,
+        };
+        self.check_meta_files(&targets, options, &mut report)?;
+
+        let existing = scan
+            .notes
+            .iter()
+            .map(|n| n.path.clone())
+            .chain(scan.folders.iter().cloned())
+            .collect::<HashSet<_>>();
+        self.check_order_files(&existing, options, &mut report)?;
+
+        report.findings.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        Ok(report)
+    }
+
+    fn check_duplicate_ids(
+        &self,
+        paths_by_id: &BTreeMap<String, Vec<String>>,
+        options: CheckOptions,
+        report: &mut CheckReport,
+    ) -> Result<()> {
+        for (note_id, paths) in paths_by_id {
+            if paths.len() < 2 {
+                continue;
+            }
+            // The first path in scan order keeps the id and with it the meta file.
+            for path in &paths[1..] {
+                let mut finding = CheckFinding {
+                    severity: CheckSeverity::Error,
+                    kind: CheckFindingKind::DuplicateNoteId,
+                    path: path.clone(),
+                    detail: format!("note id {note_id} is also used by {}", paths[0]),
+                    repairable: true,
+                    repaired: false,
+                };
+                if options.repair {
+                    let content = self.read_note(path)?;
+                    let new_id = generate_note_id();
+                    if let Some(next) = replace_frontmatter_note_id(&content, &new_id)? {
+                        self.write_note_with_source(path, &next, NoteSaveSource::User)?;
+                        finding
+                            .detail
+                            .push_str(&format!("; reassigned to {new_id}"));
+                        finding.repaired = true;
+                    }
+                }
+                report.findings.push(finding);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_meta_files(
+        &self,
+        targets: &MetaTargets<'_>,
+        options: CheckOptions,
+        report: &mut CheckReport,
+    ) -> Result<()> {
+        let meta_dir = self.root.join(".xnote").join("meta");
+        for path in list_files_with_suffix(&meta_dir, ".json", false)? {
+            report.meta_files_scanned += 1;
+            let rel = self.vault_rel_posix(&path);
+            let file_id = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string();
+
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| parse_note_meta_json(&content));
+            let meta = match parsed {
+                Ok((meta, stored_version)) => {
+                    if stored_version < NOTE_META_VERSION_CURRENT {
+                        report.findings.push(CheckFinding {
+                            severity: CheckSeverity::Info,
+                            kind: CheckFindingKind::OutdatedMetaVersion,
+                            path: rel.clone(),
+                            detail: format!(
+                                "stored as version {stored_version}, current is {NOTE_META_VERSION_CURRENT}"
+                            ),
+                            repairable: false,
+                            repaired: false,
+                        });
+                    }
+                    meta
+                }
+                Err(err) => {
+                    report.findings.push(CheckFinding {
+                        severity: CheckSeverity::Error,
+                        kind: CheckFindingKind::InvalidMeta,
+                        path: rel,
+                        detail: format!("{err:#}"),
+                        repairable: false,
+                        repaired: false,
+                    });
+                    continue;
+                }
+            };
+
+            if meta.id != file_id {
+                report.findings.push(CheckFinding {
+                    severity: CheckSeverity::Warning,
+                    kind: CheckFindingKind::MetaFileNameMismatch,
+                    path: rel.clone(),
+                    detail: format!("meta id {} is stored as {file_id}.json", meta.id),
+                    repairable: false,
+                    repaired: false,
+                });
+            }
+
+            if !targets.note_ids.contains(&meta.id) {
+                let mut finding = CheckFinding {
+                    severity: CheckSeverity::Warning,
+                    kind: CheckFindingKind::OrphanMeta,
+                    path: rel,
+                    detail: format!("no note has frontmatter id {}", meta.id),
+                    repairable: targets.all_note_ids_known,
+                    repaired: false,
+                };
+                if !targets.all_note_ids_known {
+                    finding
+                        .detail
+                        .push_str("; kept because some notes could not be read");
+                } else if options.repair {
+                    std::fs::remove_file(&path)
+                        .with_context(|| format!("remove orphan meta: {:?}", path))?;
+                    finding.repaired = true;
+                }
+                report.findings.push(finding);
+                continue;
+            }
+
+            let relation_severity = match targets.relation_mode {
+                RelationValidationMode::Warn => CheckSeverity::Warning,
+                RelationValidationMode::Strict => CheckSeverity::Error,
+            };
+            for issue in targets.relation_types.check_meta(&meta) {
+                report.findings.push(CheckFinding {
+                    severity: relation_severity,
+                    kind: CheckFindingKind::InvalidRelationType,
+                    path: rel.clone(),
+                    detail: issue.message,
+                    repairable: false,
+                    repaired: false,
+                });
+            }
+            for relation in &meta.relations {
+                let (known, target_label) = match relation.to.kind.trim() {
+                    "knowledge" => (targets.note_ids.contains(&relation.to.id), "note"),
+                    "resource" => (
+                        targets.resources.resolve(self, &relation.to.id).is_some(),
+                        "resource",
+                    ),
+                    "info" => (targets.info_ids.contains(relation.to.id.trim()), "info"),
+                    _ => continue,
+                };
+                if known {
+                    continue;
+                }
+                report.findings.push(CheckFinding {
+                    severity: CheckSeverity::Warning,
+                    kind: CheckFindingKind::UnknownRelationTarget,
+                    path: rel.clone(),
+                    detail: format!(
+                        "relation {} points at unknown {target_label} {}",
+                        relation.relation_type, relation.to.id
+                    ),
+                    repairable: false,
+                    repaired: false,
+                });
+            }
+            for pinned in &meta.pins.notes {
+                if targets.note_ids.contains(pinned) {
+                    continue;
+                }
+                report.findings.push(CheckFinding {
+                    severity: CheckSeverity::Info,
+                    kind: CheckFindingKind::UnknownPinnedNote,
+                    path: rel.clone(),
+                    detail: format!("pinned note {pinned} does not exist"),
+                    repairable: false,
+                    repaired: false,
+                });
+            }
+            for pinned in &meta.pins.resources {
+                if targets.resources.resolve(self, pinned).is_some() {
+                    continue;
+                }
+                report.findings.push(CheckFinding {
+                    severity: CheckSeverity::Info,
+                    kind: CheckFindingKind::UnknownPinnedResource,
+                    path: rel.clone(),
+                    detail: format!("pinned resource {pinned} does not exist"),
+                    repairable: false,
+                    repaired: false,
+                });
+            }
+            for pinned in &meta.pins.infos {
+                if targets.info_ids.contains(pinned.trim()) {
+                    continue;
+                }
+                report.findings.push(CheckFinding {
+                    severity: CheckSeverity::Info,
+                    kind: CheckFindingKind::UnknownPinnedInfo,
+                    path: rel.clone(),
+                    detail: format!("pinned info {pinned} does not exist"),
+                    repairable: false,
+                    repaired: false,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Resource meta is never removed by repair: its annotations outlive a moved file.
+    fn check_resource_meta_files(&self, report: &mut CheckReport) -> Result<()> {
+        for path in list_files_with_suffix(&self.resource_meta_dir(), ".json", false)? {
+            report.meta_files_scanned += 1;
+            let rel = self.vault_rel_posix(&path);
+            let file_id = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string();
+
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(serde_json::from_str::<ResourceMetaV1>(&content)?))
+                .and_then(|meta| meta.validate().map(|_| meta));
+            let meta = match parsed {
+                Ok(meta) => meta,
+                Err(err) => {
+                    report.findings.push(CheckFinding {
+                        severity: CheckSeverity::Error,
+                        kind: CheckFindingKind::InvalidMeta,
+                        path: rel,
+                        detail: format!("{err:#}"),
+                        repairable: false,
+                        repaired: false,
+                    });
+                    continue;
+                }
+            };
+
+            if meta.id != file_id {
+                report.findings.push(CheckFinding {
+                    severity: CheckSeverity::Warning,
+                    kind: CheckFindingKind::MetaFileNameMismatch,
+                    path: rel.clone(),
+                    detail: format!("resource meta id {} is stored as {file_id}.json", meta.id),
+                    repairable: false,
+                    repaired: false,
+                });
+            }
+
+            if !join_inside(&self.root, &meta.path)?.is_file() {
+                report.findings.push(CheckFinding {
+                    severity: CheckSeverity::Warning,
+                    kind: CheckFindingKind::OrphanResourceMeta,
+                    path: rel,
+                    detail: format!("resource {} does not exist", meta.path),
+                    repairable: false,
+                    repaired: false,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates info records and returns the ids of the valid ones.
+    fn check_info_meta_files(&self, report: &mut CheckReport) -> Result<HashSet<String>> {
+        let mut info_ids = HashSet::new();
+        for path in list_files_with_suffix(&self.info_meta_dir(), ".json", false)? {
+            report.meta_files_scanned += 1;
+            let rel = self.vault_rel_posix(&path);
+            let file_id = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string();
+
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(serde_json::from_str::<InfoMetaV1>(&content)?))
+                .and_then(|meta| meta.validate().map(|_| meta));
+            let meta = match parsed {
+                Ok(meta) => meta,
+                Err(err) => {
+                    report.findings.push(CheckFinding {
+                        severity: CheckSeverity::Error,
+                        kind: CheckFindingKind::InvalidMeta,
+                        path: rel,
+                        detail: format!("{err:#}"),
+                        repairable: false,
+                        repaired: false,
+                    });
+                    continue;
+                }
+            };
+
+            if meta.id != file_id {
+                report.findings.push(CheckFinding {
+                    severity: CheckSeverity::Warning,
+                    kind: CheckFindingKind::MetaFileNameMismatch,
+                    path: rel,
+                    detail: format!("info meta id {} is stored as {file_id}.json", meta.id),
+                    repairable: false,
+                    repaired: false,
+                });
+                continue;
+            }
+            info_ids.insert(meta.id);
+        }
+        Ok(info_ids)
+    }
+
+    fn check_order_files(
+        &self,
+        existing: &HashSet<String>,
+        options: CheckOptions,
+        report: &mut CheckReport,
+    ) -> Result<()> {
+        let order_dir = self.root.join(".xnote").join("order");
+        for path in list_files_with_suffix(&order_dir, ".order.md", true)? {
+            report.order_files_scanned += 1;
+            let rel = self.vault_rel_posix(&path);
+            let folder = path
+                .strip_prefix(&order_dir)
+                .ok()
+                .and_then(|p| to_posix_path(p).ok())
+                .and_then(|p| p.strip_suffix(".order.md").map(str::to_string))
+                .unwrap_or_default();
+
+            if !existing.contains(&folder) {
+                let mut finding = CheckFinding {
+                    severity: CheckSeverity::Warning,
+                    kind: CheckFindingKind::OrphanOrderFile,
+                    path: rel,
+                    detail: format!("folder {folder} does not exist"),
+                    repairable: true,
+                    repaired: false,
+                };
+                if options.repair {
+                    std::fs::remove_file(&path)
+                        .with_context(|| format!("remove orphan order file: {:?}", path))?;
+                    finding.repaired = true;
+                }
+                report.findings.push(finding);
+                continue;
+            }
+
+            let entries = self.load_folder_order(&folder)?;
+            let stale = entries
+                .iter()
+                .filter(|entry| !existing.contains(entry.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+            if stale.is_empty() {
+                continue;
+            }
+            if options.repair {
+                let kept = entries
+                    .iter()
+                    .filter(|entry| existing.contains(entry.as_str()))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                self.save_folder_order(&folder, &kept)?;
+            }
+            for entry in stale {
+                report.findings.push(CheckFinding {
+                    severity: CheckSeverity::Warning,
+                    kind: CheckFindingKind::StaleOrderEntry,
+                    path: rel.clone(),
+                    detail: format!("order entry {entry} does not exist"),
+                    repairable: true,
+                    repaired: options.repair,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn vault_rel_posix(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .ok()
+            .and_then(|p| to_posix_path(p).ok())
+            .unwrap_or_else(|| path.to_string_lossy().to_string())
+    }
+}
+
+/// Ids that note meta relations and pins may point at, and the relation types they may use.
+struct MetaTargets<'a> {
+    note_ids: &'a HashSet<String>,
+    /// False when a note could not be read; its meta then looks orphaned and must be kept.
+    all_note_ids_known: bool,
+    resources: &'a ResourceLookup,
+    info_ids: &'a HashSet<String>,
+    relation_types: &'a RelationTypeRegistry,
+    relation_mode: RelationValidationMode,
+}
+
+fn list_files_with_suffix(dir: &Path, suffix: &str, recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(out),
+        Err(err) => return Err(err).with_context(|| format!("read dir: {:?}", dir)),
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if recursive {
+                out.extend(list_files_with_suffix(&path, suffix, recursive)?);
+            }
+            continue;
+        }
+        let matches = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|name| name.ends_with(suffix) && !name.starts_with('.'));
+        if matches {
+            out.push(path);
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::info_meta::InfoKind;
+    use crate::note_meta::{NoteMetaRelation, NoteMetaTarget, NoteMetaV1};
+    use crate::relation_types::{RelationCardinality, RelationTypeDef};
+    use crate::settings::{save_project_settings, AppSettings};
+    use crate::vault::parse_order_md;
+    use crate::vault::test_vault;
+    use serde_json::Map;
+    use std::fs;
+
+    #[test]
+    fn check_reports_and_repairs_vault_problems() {
+        let (temp_dir, vault) = test_vault("vault_check_repair", &["notes"]);
+        vault
+            .ensure_knowledge_structure()
+            .expect("ensure structure");
+        fs::write(temp_dir.join("notes/A.md"), "---\nid: N1\n---\n# A\n").expect("A");
+        fs::write(temp_dir.join("notes/B.md"), "---\nid: N1\n---\n# B\n").expect("B");
+
+        let mut meta = NoteMetaV1::new("N1").expect("meta");
+        meta.relations.push(NoteMetaRelation {
+            relation_type: "xnote.related".to_string(),
+            to: NoteMetaTarget {
+                kind: "knowledge".to_string(),
+                id: "N404".to_string(),
+                anchor: None,
+                extra: Map::new(),
+            },
+            note: None,
+            created_at: None,
+            created_by: None,
+            extra: Map::new(),
+        });
+        vault.save_note_meta(&meta).expect("save meta");
+        vault
+            .save_note_meta(&NoteMetaV1::new("N2").expect("orphan meta"))
+            .expect("save orphan");
+        fs::write(
+            temp_dir.join(".xnote/meta/ai_tool_audit.jsonl"),
+            "{\"ignored\":true}\n",
+        )
+        .expect("audit log");
+        vault
+            .save_folder_order(
+                "notes",
+                &["notes/B.md".to_string(), "notes/Gone.md".to_string()],
+            )
+            .expect("order");
+        vault
+            .save_folder_order("missing", &["missing/X.md".to_string()])
+            .expect("orphan order");
+
+        let report = vault.check(CheckOptions::default()).expect("check");
+        let kinds = report.findings.iter().map(|f| f.kind).collect::<Vec<_>>();
+        assert_eq!(report.meta_files_scanned, 2);
+        assert_eq!(report.count(CheckSeverity::Error), 1);
+        for kind in [
+            CheckFindingKind::DuplicateNoteId,
+            CheckFindingKind::OrphanMeta,
+            CheckFindingKind::UnknownRelationTarget,
+            CheckFindingKind::StaleOrderEntry,
+            CheckFindingKind::OrphanOrderFile,
+        ] {
+            assert!(kinds.contains(&kind), "missing finding {kind:?}");
+        }
+        assert!(!report.is_clean());
+
+        let repaired = vault.check(CheckOptions { repair: true }).expect("repair");
+        assert!(repaired
+            .findings
+            .iter()
+            .filter(|f| f.repairable)
+            .all(|f| f.repaired));
+
+        let b = vault.read_note("notes/B.md").expect("read B");
+        let b_id = extract_note_id_from_frontmatter(&b).expect("B id");
+        assert_ne!(b_id, "N1");
+        assert!(vault.load_note_meta("N2").expect("load").is_none());
+        assert_eq!(
+            parse_order_md(
+                &fs::read_to_string(vault.order_file_path("notes").expect("p")).expect("read")
+            ),
+            vec!["notes/B.md".to_string()]
+        );
+        assert!(!vault.order_file_path("missing").expect("p").exists());
+
+        let after = vault.check(CheckOptions::default()).expect("recheck");
+        let remaining = after.findings.iter().map(|f| f.kind).collect::<Vec<_>>();
+        assert_eq!(remaining, vec![CheckFindingKind::UnknownRelationTarget]);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn check_keeps_meta_of_unreadable_notes() {
+        let (temp_dir, vault) = test_vault("vault_check_unreadable", &["notes"]);
+        vault
+            .ensure_knowledge_structure()
+            .expect("ensure structure");
+        let mut latin1 = b"---\nid: N1\n---\ncaf".to_vec();
+        latin1.push(0xE9);
+        fs::write(temp_dir.join("notes/A.md"), latin1).expect("A");
+        vault
+            .save_note_meta(&NoteMetaV1::new("N1").expect("meta"))
+            .expect("save meta");
+
+        let report = vault.check(CheckOptions { repair: true }).expect("repair");
+        let unreadable = report
+            .findings
+            .iter()
+            .find(|f| f.kind == CheckFindingKind::UnreadableNote)
+            .expect("unreadable finding");
+        assert_eq!(unreadable.path, "notes/A.md");
+        let orphan = report
+            .findings
+            .iter()
+            .find(|f| f.kind == CheckFindingKind::OrphanMeta)
+            .expect("orphan finding");
+        assert!(!orphan.repairable && !orphan.repaired);
+        assert!(vault.load_note_meta("N1").expect("load").is_some());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn check_resolves_resource_and_info_targets() {
+        let (temp_dir, vault) = test_vault("vault_check_resources", &["notes"]);
+        vault
+            .ensure_knowledge_structure()
+            .expect("ensure structure");
+        fs::create_dir_all(temp_dir.join("attachments")).expect("attachments");
+        fs::write(temp_dir.join("notes/A.md"), "---\nid: N1\n---\n# A\n").expect("A");
+        fs::write(temp_dir.join("attachments/a.png"), b"png").expect("png");
+        vault
+            .save_resource_meta(&ResourceMetaV1::new("R1", "attachments/a.png").expect("meta"))
+            .expect("save R1");
+        vault
+            .save_resource_meta(&ResourceMetaV1::new("R2", "attachments/gone.png").expect("meta"))
+            .expect("save R2");
+
+        let mut info = InfoMetaV1::new("F1", InfoKind::Snippet).expect("info");
+        info.body = "captured".to_string();
+        vault.save_info_meta(&info).expect("save info");
+
+        let mut meta = NoteMetaV1::new("N1").expect("meta");
+        for (kind, id) in [
+            ("resource", "R1"),
+            ("resource", "attachments/a.png"),
+            ("resource", "R404"),
+            ("info", "F1"),
+            ("info", "F404"),
+        ] {
+            meta.relations.push(NoteMetaRelation {
+                relation_type: "xnote.cites".to_string(),
+                to: NoteMetaTarget {
+                    kind: kind.to_string(),
+                    id: id.to_string(),
+                    anchor: None,
+                    extra: Map::new(),
+                },
+                note: None,
+                created_at: None,
+                created_by: None,
+                extra: Map::new(),
+            });
+        }
+        meta.pins.resources.push("R9".to_string());
+        meta.pins.infos.extend(["F1".to_string(), "F9".to_string()]);
+        vault.save_note_meta(&meta).expect("save meta");
+
+        let report = vault.check(CheckOptions { repair: true }).expect("check");
+        assert_eq!(report.meta_files_scanned, 4);
+        let findings = report
+            .findings
+            .iter()
+            .map(|f| (f.kind, f.detail.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    CheckFindingKind::UnknownRelationTarget,
+                    "relation xnote.cites points at unknown resource R404"
+                ),
+                (
+                    CheckFindingKind::UnknownRelationTarget,
+                    "relation xnote.cites points at unknown info F404"
+                ),
+                (
+                    CheckFindingKind::OrphanResourceMeta,
+                    "resource attachments/gone.png does not exist"
+                ),
+                (
+                    CheckFindingKind::UnknownPinnedResource,
+                    "pinned resource R9 does not exist"
+                ),
+                (
+                    CheckFindingKind::UnknownPinnedInfo,
+                    "pinned info F9 does not exist"
+                ),
+            ]
+        );
+        assert!(vault.load_resource_meta("R2").expect("load").is_some());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn check_and_save_apply_vault_relation_types() {
+        let (temp_dir, vault) = test_vault("vault_check_relation_types", &["notes"]);
+        vault
+            .ensure_knowledge_structure()
+            .expect("ensure structure");
+        fs::write(temp_dir.join("notes/A.md"), "---\nid: N1\n---\n# A\n").expect("A");
+        fs::write(temp_dir.join("notes/B.md"), "---\nid: N2\n---\n# B\n").expect("B");
+
+        let relation = |relation_type: &str| NoteMetaRelation {
+            relation_type: relation_type.to_string(),
+            to: NoteMetaTarget {
+                kind: "knowledge".to_string(),
+                id: "N2".to_string(),
+                anchor: None,
+                extra: Map::new(),
+            },
+            note: None,
+            created_at: None,
+            created_by: None,
+            extra: Map::new(),
+        };
+        let mut meta = NoteMetaV1::new("N1").expect("meta");
+        meta.relations.push(relation("task.blocks"));
+        meta.relations.push(relation("xnote.explain"));
+        vault.save_note_meta(&meta).expect("warn mode saves");
+
+        let report = vault.check(CheckOptions::default()).expect("check");
+        let findings = report
+            .findings
+            .iter()
+            .map(|f| (f.severity, f.kind, f.detail.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    CheckSeverity::Warning,
+                    CheckFindingKind::InvalidRelationType,
+                    "unknown relation type task.blocks"
+                ),
+                (
+                    CheckSeverity::Warning,
+                    CheckFindingKind::InvalidRelationType,
+                    "unknown relation type xnote.explain"
+                ),
+            ]
+        );
+
+        let mut settings = AppSettings::default();
+        settings.relations.validation = RelationValidationMode::Strict;
+        settings.relations.types.push(RelationTypeDef {
+            id: "task.blocks".to_string(),
+            label: "Blocks".to_string(),
+            inverse_label: "Blocked by".to_string(),
+            source_kinds: Vec::new(),
+            target_kinds: Vec::new(),
+            cardinality: RelationCardinality::Many,
+        });
+        save_project_settings(&temp_dir, &settings).expect("settings");
+
+        let report = vault.check(CheckOptions::default()).expect("check");
+        assert_eq!(report.count(CheckSeverity::Error), 1);
+        assert!(vault.save_note_meta(&meta).is_err());
+        meta.relations.truncate(1);
+        vault.save_note_meta(&meta).expect("known type saves");
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
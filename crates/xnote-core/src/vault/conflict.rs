@@ -0,0 +1,197 @@
+use super::{NoteSaveSource, Vault};
+use crate::atomic_write::{content_hash, AtomicWriteOutcome};
+use crate::diff::{merge_three_way, ThreeWayMerge};
+use crate::paths::{join_inside, normalize_vault_rel_path};
+use anyhow::{Context as _, Result};
+use std::time::SystemTime;
+
+/// What the caller believes is on disk before a conditional write.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NoteWriteExpectation {
+    /// The note must not exist yet.
+    Missing,
+    ContentHash(String),
+    Modified(SystemTime),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteDiskState {
+    pub content_hash: String,
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+}
+
+/// Returned (inside `anyhow::Error`) when a conditional write finds the note changed on disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteWriteConflict {
+    pub note_path: String,
+    pub expected: NoteWriteExpectation,
+    /// Current on-disk state, `None` when the note was deleted.
+    pub actual: Option<NoteDiskState>,
+}
+
+impl std::fmt::Display for NoteWriteConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.actual {
+            Some(_) => write!(
+                f,
+                "note changed on disk since it was loaded: {}",
+                self.note_path
+            ),
+            None => write!(
+                f,
+                "note was removed since it was loaded: {}",
+                self.note_path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NoteWriteConflict {}
+
+impl Vault {
+    pub fn note_disk_state(&self, note_path: &str) -> Result<Option<NoteDiskState>> {
+        let rel = normalize_vault_rel_path(note_path)?;
+        let full = join_inside(&self.root, &rel)?;
+        let bytes = match std::fs::read(&full) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).with_context(|| format!("read note: {rel}")),
+        };
+        Ok(Some(NoteDiskState {
+            content_hash: content_hash(&bytes),
+            modified: std::fs::metadata(&full).and_then(|m| m.modified()).ok(),
+            len: bytes.len() as u64,
+        }))
+    }
+
+    /// Writes the note only if its on-disk state still matches `expected`.
+    ///
+    /// On mismatch nothing is written and the error downcasts to [`NoteWriteConflict`].
+    /// The check and the write are not atomic against other processes.
+    pub fn write_note_if_unchanged(
+        &self,
+        note_path: &str,
+        content: &str,
+        expected: &NoteWriteExpectation,
+    ) -> Result<AtomicWriteOutcome> {
+        let rel = normalize_vault_rel_path(note_path)?;
+        let actual = self.note_disk_state(&rel)?;
+        let matches = match (expected, actual.as_ref()) {
+            (NoteWriteExpectation::Missing, None) => true,
+            (NoteWriteExpectation::ContentHash(hash), Some(state)) => {
+                state.content_hash.eq_ignore_ascii_case(hash)
+            }
+            (NoteWriteExpectation::Modified(modified), Some(state)) => {
+                state.modified == Some(*modified)
+            }
+            _ => false,
+        };
+        if !matches {
+            return Err(NoteWriteConflict {
+                note_path: rel,
+                expected: expected.clone(),
+                actual,
+            }
+            .into());
+        }
+        self.write_note_with_source(&rel, content, NoteSaveSource::User)
+    }
+
+    /// Merges unsaved `ours` with the note's current disk content, using `base` as loaded.
+    pub fn merge_note_with_disk(
+        &self,
+        note_path: &str,
+        base: &str,
+        ours: &str,
+    ) -> Result<ThreeWayMerge> {
+        let theirs = self.read_note(note_path)?;
+        Ok(merge_three_way(base, ours, &theirs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::MergeHunk;
+    use crate::vault::test_vault;
+    use std::fs;
+
+    #[test]
+    fn conditional_write_rejects_changed_note_with_typed_conflict() {
+        let (temp_dir, vault) = test_vault("vault_conflict_hash", &["notes"]);
+        vault
+            .write_note_if_unchanged("notes/A.md", "one\n", &NoteWriteExpectation::Missing)
+            .expect("create");
+        let loaded = vault
+            .note_disk_state("notes/A.md")
+            .expect("state")
+            .expect("exists");
+
+        fs::write(temp_dir.join("notes/A.md"), "external\n").expect("external edit");
+
+        let err = vault
+            .write_note_if_unchanged(
+                "notes/A.md",
+                "two\n",
+                &NoteWriteExpectation::ContentHash(loaded.content_hash.clone()),
+            )
+            .expect_err("conflict");
+        let conflict = err
+            .downcast_ref::<NoteWriteConflict>()
+            .expect("typed conflict");
+        assert_eq!(
+            conflict.actual.as_ref().map(|s| s.content_hash.as_str()),
+            Some(content_hash(b"external\n").as_str())
+        );
+        assert_eq!(vault.read_note("notes/A.md").expect("read"), "external\n");
+
+        let err = vault
+            .write_note_if_unchanged("notes/A.md", "x\n", &NoteWriteExpectation::Missing)
+            .expect_err("exists conflict");
+        assert!(err.downcast_ref::<NoteWriteConflict>().is_some());
+
+        let current = vault
+            .note_disk_state("notes/A.md")
+            .expect("state")
+            .expect("exists");
+        vault
+            .write_note_if_unchanged(
+                "notes/A.md",
+                "two\n",
+                &NoteWriteExpectation::ContentHash(current.content_hash),
+            )
+            .expect("write after reload");
+        assert_eq!(vault.read_note("notes/A.md").expect("read"), "two\n");
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn merge_note_with_disk_uses_loaded_base() {
+        let (temp_dir, vault) = test_vault("vault_conflict_merge", &["notes"]);
+        let base = "# T\n\nalpha\n\nomega\n";
+        fs::write(temp_dir.join("notes/A.md"), "# T\n\nalpha\n\nOMEGA\n").expect("disk edit");
+
+        let merged = vault
+            .merge_note_with_disk("notes/A.md", base, "# T\n\nALPHA\n\nomega\n")
+            .expect("merge");
+        assert_eq!(
+            merged,
+            ThreeWayMerge::Merged("# T\n\nALPHA\n\nOMEGA\n".to_string())
+        );
+
+        let conflicted = vault
+            .merge_note_with_disk("notes/A.md", base, "# T\n\nalpha\n\nmine\n")
+            .expect("merge");
+        let ThreeWayMerge::Conflicted(hunks) = conflicted else {
+            panic!("expected conflict");
+        };
+        assert!(hunks.iter().any(|h| matches!(
+            h,
+            MergeHunk::Conflict { ours, theirs, .. } if ours == "mine\n" && theirs == "OMEGA\n"
+        )));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,337 @@
+//! Local coordination channel between XNote instances sharing a vault.
+//!
+//! The session lock owner hosts a Unix socket at `.xnote/session.sock`; other
+//! instances connect to it. Every message is one JSON line and is relayed by the
+//! host to all other participants.
+
+use super::lock::{next_instance_id, VaultSessionLock};
+use super::{now_epoch_ms, Vault};
+use anyhow::{anyhow, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead as _, BufReader, Write as _};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const VAULT_SOCKET_FILE: &str = "session.sock";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VaultCoordinationEvent {
+    NoteSaved {
+        path: String,
+        #[serde(rename = "contentHash")]
+        content_hash: String,
+    },
+    NoteMetaSaved {
+        #[serde(rename = "noteId")]
+        note_id: String,
+    },
+    FolderOrderSaved {
+        folder: String,
+    },
+    /// Paths the sender re-indexed, so receivers can refresh without rescanning.
+    IndexUpdated {
+        paths: Vec<String>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VaultCoordinationMessage {
+    /// Instance id of the sender.
+    pub from: String,
+    pub pid: u32,
+    #[serde(rename = "sentAtMs")]
+    pub sent_at_ms: u64,
+    pub event: VaultCoordinationEvent,
+}
+
+/// Write halves of connected peers, tagged with a per-connection id.
+type PeerList = Arc<Mutex<Vec<(u64, UnixStream)>>>;
+
+/// One participant in the coordination channel, either hosting it or connected to it.
+pub struct VaultCoordinator {
+    instance_id: String,
+    socket_path: PathBuf,
+    incoming: Receiver<VaultCoordinationMessage>,
+    peers: PeerList,
+    hosting: bool,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl Vault {
+    pub fn coordination_socket_path(&self) -> PathBuf {
+        self.root.join(".xnote").join(VAULT_SOCKET_FILE)
+    }
+
+    /// Hosts the coordination channel; only the session lock owner may do this.
+    pub fn host_coordination(&self, lock: &VaultSessionLock) -> Result<VaultCoordinator> {
+        let socket_path = self.coordination_socket_path();
+        // A leftover socket file belongs to a previous owner, which the lock proves is gone.
+        match std::fs::remove_file(&socket_path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| format!("remove stale socket: {:?}", socket_path))
+            }
+        }
+        let listener = UnixListener::bind(&socket_path)
+            .with_context(|| format!("bind coordination socket: {:?}", socket_path))?;
+
+        let (tx, incoming) = mpsc::channel();
+        let peers: PeerList = Arc::new(Mutex::new(Vec::new()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let accept_peers = peers.clone();
+        let accept_shutdown = shutdown.clone();
+        std::thread::spawn(move || {
+            let mut next_peer_id = 0u64;
+            for stream in listener.incoming() {
+                if accept_shutdown.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let Ok(writer) = stream.try_clone() else {
+                    continue;
+                };
+                next_peer_id += 1;
+                accept_peers
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push((next_peer_id, writer));
+                spawn_reader(
+                    stream,
+                    tx.clone(),
+                    Some((accept_peers.clone(), next_peer_id)),
+                );
+            }
+        });
+
+        Ok(VaultCoordinator {
+            instance_id: lock.instance_id().to_string(),
+            socket_path,
+            incoming,
+            peers,
+            hosting: true,
+            shutdown,
+        })
+    }
+
+    /// Connects to the channel hosted by the session lock owner.
+    pub fn connect_coordination(&self) -> Result<VaultCoordinator> {
+        let socket_path = self.coordination_socket_path();
+        let stream = UnixStream::connect(&socket_path)
+            .with_context(|| format!("connect coordination socket: {:?}", socket_path))?;
+        let writer = stream
+            .try_clone()
+            .with_context(|| "clone coordination stream")?;
+
+        let (tx, incoming) = mpsc::channel();
+        spawn_reader(stream, tx, None);
+
+        Ok(VaultCoordinator {
+            instance_id: next_instance_id(now_epoch_ms()),
+            socket_path,
+            incoming,
+            peers: Arc::new(Mutex::new(vec![(0, writer)])),
+            hosting: false,
+            shutdown: Arc::new(AtomicBool::new(false)),
+        })
+    }
+}
+
+impl VaultCoordinator {
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.hosting
+    }
+
+    /// Sends `event` to every other participant.
+    pub fn broadcast(&self, event: VaultCoordinationEvent) -> Result<()> {
+        let message = VaultCoordinationMessage {
+            from: self.instance_id.clone(),
+            pid: std::process::id(),
+            sent_at_ms: now_epoch_ms(),
+            event,
+        };
+        let mut line = serde_json::to_string(&message)?;
+        line.push('\n');
+
+        let mut peers = self.peers.lock().unwrap_or_else(|e| e.into_inner());
+        if !self.hosting && peers.is_empty() {
+            return Err(anyhow!("coordination host connection is closed"));
+        }
+        let before = peers.len();
+        peers.retain_mut(|(_, peer)| peer.write_all(line.as_bytes()).is_ok());
+        if !self.hosting && peers.len() < before {
+            return Err(anyhow!("coordination host connection is closed"));
+        }
+        Ok(())
+    }
+
+    pub fn try_recv(&self) -> Option<VaultCoordinationMessage> {
+        self.incoming.try_recv().ok()
+    }
+
+    /// Waits up to `timeout` for the next message; `Ok(None)` on timeout.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<VaultCoordinationMessage>> {
+        match self.incoming.recv_timeout(timeout) {
+            Ok(message) => Ok(Some(message)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(anyhow!("coordination channel is disconnected"))
+            }
+        }
+    }
+}
+
+impl Drop for VaultCoordinator {
+    fn drop(&mut self) {
+        let peers = std::mem::take(&mut *self.peers.lock().unwrap_or_else(|e| e.into_inner()));
+        for (_, peer) in peers {
+            let _ = peer.shutdown(std::net::Shutdown::Both);
+        }
+        if self.hosting {
+            self.shutdown.store(true, Ordering::Relaxed);
+            // Wake the accept loop so it observes the shutdown flag.
+            let _ = UnixStream::connect(&self.socket_path);
+            let _ = std::fs::remove_file(&self.socket_path);
+        }
+    }
+}
+
+/// Reads JSON lines from `stream`; on the host, relays each line to the other peers.
+fn spawn_reader(
+    stream: UnixStream,
+    tx: Sender<VaultCoordinationMessage>,
+    relay: Option<(PeerList, u64)>,
+) {
+    std::thread::spawn(move || {
+        let reader = BufReader::new(stream);
+        for line in reader.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let Ok(message) = serde_json::from_str::<VaultCoordinationMessage>(&line) else {
+                continue;
+            };
+            if let Some((peers, source_id)) = relay.as_ref() {
+                let framed = format!("{line}\n");
+                peers
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .retain_mut(|(peer_id, peer)| {
+                        peer_id == source_id || peer.write_all(framed.as_bytes()).is_ok()
+                    });
+            }
+            if tx.send(message).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::{VaultLockHeld, VaultLockOptions};
+    use std::fs;
+    use std::process::Command;
+
+    const CHILD_VAULT_ENV: &str = "XNOTE_CORE_COORDINATION_CHILD_VAULT";
+    const CHILD_TEST_NAME: &str = "vault::coordination::tests::coordination_child_process";
+
+    #[test]
+    fn coordination_relays_events_between_two_processes() {
+        let temp_dir = std::env::temp_dir().join(format!(
+            "xnote_core_vault_coordination_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).expect("create vault");
+        let vault = Vault::open(&temp_dir).expect("open vault");
+
+        let lock = vault
+            .acquire_session_lock(&VaultLockOptions::default())
+            .expect("acquire lock");
+        let host = vault.host_coordination(&lock).expect("host");
+        assert!(host.is_host());
+
+        let mut child = Command::new(std::env::current_exe().expect("current exe"))
+            .args([CHILD_TEST_NAME, "--exact", "--ignored", "--nocapture"])
+            .env(CHILD_VAULT_ENV, &temp_dir)
+            .spawn()
+            .expect("spawn child process");
+
+        let message = host
+            .recv_timeout(Duration::from_secs(20))
+            .expect("recv")
+            .expect("message from child");
+        assert_ne!(message.pid, std::process::id());
+        assert_eq!(
+            message.event,
+            VaultCoordinationEvent::NoteSaved {
+                path: "notes/A.md".to_string(),
+                content_hash: "abc".to_string(),
+            }
+        );
+
+        host.broadcast(VaultCoordinationEvent::IndexUpdated {
+            paths: vec!["notes/A.md".to_string()],
+        })
+        .expect("broadcast");
+
+        let status = child.wait().expect("wait child");
+        assert!(status.success(), "child process failed: {status}");
+
+        drop(host);
+        drop(lock);
+        assert!(!vault.coordination_socket_path().exists());
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    #[ignore = "spawned by coordination_relays_events_between_two_processes"]
+    fn coordination_child_process() {
+        let Ok(root) = std::env::var(CHILD_VAULT_ENV) else {
+            return;
+        };
+        let vault = Vault::open(root).expect("open vault");
+
+        let err = vault
+            .acquire_session_lock(&VaultLockOptions::default())
+            .expect_err("lock is held by the parent");
+        let held = err.downcast_ref::<VaultLockHeld>().expect("typed held");
+        let owner = held.owner.clone().expect("held owner");
+        assert_ne!(owner.pid, std::process::id());
+
+        let client = vault.connect_coordination().expect("connect");
+        assert!(!client.is_host());
+        client
+            .broadcast(VaultCoordinationEvent::NoteSaved {
+                path: "notes/A.md".to_string(),
+                content_hash: "abc".to_string(),
+            })
+            .expect("broadcast");
+
+        let message = client
+            .recv_timeout(Duration::from_secs(20))
+            .expect("recv")
+            .expect("message from host");
+        assert_eq!(owner.instance_id, message.from);
+        assert_eq!(
+            message.event,
+            VaultCoordinationEvent::IndexUpdated {
+                paths: vec!["notes/A.md".to_string()],
+            }
+        );
+    }
+}
@@ -0,0 +1,428 @@
+use super::{list_json_files, now_epoch_ms, Vault};
+use crate::atomic_write::{content_hash, write_atomic};
+use crate::diff::{diff_lines, LineDiff};
+use crate::note_meta::{extract_note_id_from_frontmatter, normalize_note_id};
+use crate::paths::{join_inside, normalize_vault_rel_path};
+use anyhow::{anyhow, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const HISTORY_INDEX_VERSION_V1: u32 = 1;
+const HISTORY_BLOBS_DIR: &str = "blobs";
+const HISTORY_BY_ID_DIR: &str = "by-id";
+const HISTORY_BY_PATH_DIR: &str = "by-path";
+
+/// Who produced the content of a recorded version.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteSaveSource {
+    User,
+    AiTool,
+    Restore,
+    /// A vault-wide search and replace.
+    Replace,
+    /// Content found on disk that no vault save recorded, e.g. an edit by another program.
+    External,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryRetention {
+    /// Newest versions kept per note; `0` keeps every version.
+    pub max_versions_per_note: usize,
+    /// Versions older than this are dropped, except the newest one of each note.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self {
+            max_versions_per_note: 100,
+            max_age: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NoteVersion {
+    pub id: String,
+    #[serde(rename = "contentHash")]
+    pub content_hash: String,
+    #[serde(rename = "savedAtMs")]
+    pub saved_at_ms: u64,
+    /// Vault-relative path of the note when this version was saved.
+    #[serde(rename = "notePath")]
+    pub note_path: String,
+    #[serde(rename = "noteId", default)]
+    pub note_id: Option<String>,
+    pub source: NoteSaveSource,
+    pub len: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct HistoryIndexFile {
+    version: u32,
+    versions: Vec<NoteVersion>,
+}
+
+impl Vault {
+    pub fn history_dir(&self) -> PathBuf {
+        self.root.join(".xnote").join("history")
+    }
+
+    pub fn history_retention(&self) -> &HistoryRetention {
+        &self.history_retention
+    }
+
+    pub fn with_history_retention(mut self, retention: HistoryRetention) -> Self {
+        self.history_retention = retention;
+        self
+    }
+
+    /// Records `content` as the newest version of `note_path`.
+    ///
+    /// Returns `None` when the newest recorded version already has the same content hash.
+    pub fn record_note_version(
+        &self,
+        note_path: &str,
+        content: &str,
+        source: NoteSaveSource,
+    ) -> Result<Option<NoteVersion>> {
+        let rel = normalize_vault_rel_path(note_path)?;
+        let note_id = extract_note_id_from_frontmatter(content);
+        let index_path = self.history_index_path(&rel, note_id.as_deref())?;
+        let mut index = read_index(&index_path)?;
+
+        let hash = content_hash(content.as_bytes());
+        if index
+            .versions
+            .last()
+            .is_some_and(|latest| latest.content_hash == hash)
+        {
+            return Ok(None);
+        }
+
+        self.write_history_blob(&hash, content.as_bytes())?;
+
+        let saved_at_ms = now_epoch_ms().max(
+            index
+                .versions
+                .last()
+                .map(|latest| latest.saved_at_ms + 1)
+                .unwrap_or_default(),
+        );
+        let version = NoteVersion {
+            id: format!("V{saved_at_ms:013}-{}", &hash[..12]),
+            content_hash: hash,
+            saved_at_ms,
+            note_path: rel,
+            note_id,
+            source,
+            len: content.len() as u64,
+        };
+        index.versions.push(version.clone());
+        apply_retention(&mut index.versions, &self.history_retention, saved_at_ms);
+        write_index(&index_path, &index)?;
+        Ok(Some(version))
+    }
+
+    /// Versions of the note at `note_path`, newest first.
+    ///
+    /// Includes versions recorded under the note's current id as well as those recorded
+    /// under its path before it had one.
+    pub fn list_note_versions(&self, note_path: &str) -> Result<Vec<NoteVersion>> {
+        let rel = normalize_vault_rel_path(note_path)?;
+        let full = join_inside(&self.root, &rel)?;
+        let note_id = std::fs::read_to_string(&full)
+            .ok()
+            .and_then(|content| extract_note_id_from_frontmatter(&content));
+
+        let mut versions = read_index(&self.history_index_path(&rel, None)?)?.versions;
+        if let Some(note_id) = note_id.as_deref() {
+            versions.extend(read_index(&self.history_index_path(&rel, Some(note_id))?)?.versions);
+        }
+        Ok(sort_newest_first(versions))
+    }
+
+    /// Versions recorded for `note_id` across renames and moves, newest first.
+    pub fn list_note_versions_by_id(&self, note_id: &str) -> Result<Vec<NoteVersion>> {
+        let path = self.history_by_id_path(note_id)?;
+        Ok(sort_newest_first(read_index(&path)?.versions))
+    }
+
+    pub fn read_note_version(&self, version: &NoteVersion) -> Result<String> {
+        let path = self.history_blob_path(&version.content_hash)?;
+        std::fs::read_to_string(&path)
+            .with_context(|| format!("read note version {}: {:?}", version.id, path))
+    }
+
+    /// Line diff from `old` to `new`.
+    pub fn diff_note_versions(
+        &self,
+        old: &NoteVersion,
+        new: &NoteVersion,
+    ) -> Result<Vec<LineDiff>> {
+        let old_content = self.read_note_version(old)?;
+        let new_content = self.read_note_version(new)?;
+        Ok(diff_lines(&old_content, &new_content))
+    }
+
+    /// Writes `version` back to `note_path`; the replaced content stays in history.
+    pub fn restore_note_version(&self, note_path: &str, version: &NoteVersion) -> Result<()> {
+        let content = self.read_note_version(version)?;
+        self.write_note_with_source(note_path, &content, NoteSaveSource::Restore)?;
+        Ok(())
+    }
+
+    /// Applies the retention policy to every note and removes unreferenced blobs.
+    ///
+    /// Returns the number of versions dropped.
+    pub fn prune_note_history(&self) -> Result<usize> {
+        let now_ms = now_epoch_ms();
+        let mut dropped = 0usize;
+        let mut referenced = HashSet::new();
+        for dir in [HISTORY_BY_ID_DIR, HISTORY_BY_PATH_DIR] {
+            for index_path in list_json_files(&self.history_dir().join(dir))? {
+                let mut index = read_index(&index_path)?;
+                let before = index.versions.len();
+                apply_retention(&mut index.versions, &self.history_retention, now_ms);
+                if index.versions.len() != before {
+                    dropped += before - index.versions.len();
+                    write_index(&index_path, &index)?;
+                }
+                referenced.extend(index.versions.into_iter().map(|v| v.content_hash));
+            }
+        }
+
+        let blobs_dir = self.history_dir().join(HISTORY_BLOBS_DIR);
+        for shard in read_dir_paths(&blobs_dir)? {
+            for blob in read_dir_paths(&shard)? {
+                let Some(name) = blob.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if !referenced.contains(name) {
+                    std::fs::remove_file(&blob)
+                        .with_context(|| format!("remove history blob: {:?}", blob))?;
+                }
+            }
+        }
+        Ok(dropped)
+    }
+
+    fn history_index_path(&self, rel: &str, note_id: Option<&str>) -> Result<PathBuf> {
+        match note_id {
+            Some(note_id) => self.history_by_id_path(note_id),
+            None => Ok(self
+                .history_dir()
+                .join(HISTORY_BY_PATH_DIR)
+                .join(format!("{}.json", &content_hash(rel.as_bytes())[..32]))),
+        }
+    }
+
+    fn history_by_id_path(&self, note_id: &str) -> Result<PathBuf> {
+        let note_id = normalize_note_id(note_id)?;
+        Ok(self
+            .history_dir()
+            .join(HISTORY_BY_ID_DIR)
+            .join(format!("{note_id}.json")))
+    }
+
+    fn history_blob_path(&self, hash: &str) -> Result<PathBuf> {
+        if hash.len() < 3 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(anyhow!("invalid history content hash: {hash}"));
+        }
+        Ok(self
+            .history_dir()
+            .join(HISTORY_BLOBS_DIR)
+            .join(&hash[..2])
+            .join(hash))
+    }
+
+    fn write_history_blob(&self, hash: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.history_blob_path(hash)?;
+        if path.is_file() {
+            return Ok(());
+        }
+        write_atomic(&path, bytes).with_context(|| format!("write history blob: {hash}"))?;
+        Ok(())
+    }
+}
+
+/// Drops versions beyond the count limit and past the age limit, keeping the newest.
+fn apply_retention(versions: &mut Vec<NoteVersion>, retention: &HistoryRetention, now_ms: u64) {
+    if let Some(max_age) = retention.max_age {
+        let cutoff = now_ms.saturating_sub(max_age.as_millis() as u64);
+        let newest = versions.len().saturating_sub(1);
+        let mut ix = 0usize;
+        versions.retain(|v| {
+            let keep = ix == newest || v.saved_at_ms >= cutoff;
+            ix += 1;
+            keep
+        });
+    }
+    if retention.max_versions_per_note > 0 && versions.len() > retention.max_versions_per_note {
+        let excess = versions.len() - retention.max_versions_per_note;
+        versions.drain(..excess);
+    }
+}
+
+fn sort_newest_first(mut versions: Vec<NoteVersion>) -> Vec<NoteVersion> {
+    versions.sort_by(|a, b| {
+        b.saved_at_ms
+            .cmp(&a.saved_at_ms)
+            .then_with(|| b.id.cmp(&a.id))
+    });
+    versions.dedup_by(|a, b| a.id == b.id);
+    versions
+}
+
+fn read_index(path: &Path) -> Result<HistoryIndexFile> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(HistoryIndexFile {
+                version: HISTORY_INDEX_VERSION_V1,
+                versions: Vec::new(),
+            })
+        }
+        Err(err) => return Err(err).with_context(|| format!("read history index: {:?}", path)),
+    };
+    let index: HistoryIndexFile = serde_json::from_str(&content)
+        .with_context(|| format!("parse history index: {:?}", path))?;
+    if index.version != HISTORY_INDEX_VERSION_V1 {
+        return Err(anyhow!(
+            "unsupported history index version {} in {:?}",
+            index.version,
+            path
+        ));
+    }
+    Ok(index)
+}
+
+fn write_index(path: &Path, index: &HistoryIndexFile) -> Result<()> {
+    let mut content = serde_json::to_string_pretty(index)?;
+    content.push('\n');
+    write_atomic(path, content.as_bytes())
+        .with_context(|| format!("write history index: {:?}", path))?;
+    Ok(())
+}
+
+fn read_dir_paths(dir: &Path) -> Result<Vec<PathBuf>> {
+    match std::fs::read_dir(dir) {
+        Ok(entries) => Ok(entries.flatten().map(|e| e.path()).collect()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err).with_context(|| format!("read history dir: {:?}", dir)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::LineDiffOp;
+    use crate::vault::test_vault;
+    use std::fs;
+
+    #[test]
+    fn write_note_records_deduplicated_versions_and_restores() {
+        let (temp_dir, vault) = test_vault("vault_history_restore", &["notes"]);
+        fs::write(temp_dir.join("notes/A.md"), "external\n").expect("seed note");
+
+        vault
+            .write_note("notes/A.md", "one\ntwo\n")
+            .expect("write 1");
+        vault
+            .write_note("notes/A.md", "one\ntwo\n")
+            .expect("write same");
+        vault
+            .write_note_with_source("notes/A.md", "one\nthree\n", NoteSaveSource::AiTool)
+            .expect("write 2");
+
+        let versions = vault.list_note_versions("notes/A.md").expect("list");
+        let sources = versions.iter().map(|v| v.source).collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            vec![
+                NoteSaveSource::AiTool,
+                NoteSaveSource::User,
+                NoteSaveSource::External
+            ]
+        );
+
+        let diff = vault
+            .diff_note_versions(&versions[1], &versions[0])
+            .expect("diff");
+        let changed = diff
+            .iter()
+            .filter(|d| d.op != LineDiffOp::Equal)
+            .map(|d| (d.op, d.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changed,
+            vec![(LineDiffOp::Delete, "two"), (LineDiffOp::Insert, "three")]
+        );
+
+        vault
+            .restore_note_version("notes/A.md", &versions[2])
+            .expect("restore");
+        assert_eq!(vault.read_note("notes/A.md").expect("read"), "external\n");
+        let versions = vault.list_note_versions("notes/A.md").expect("list");
+        assert_eq!(versions.len(), 4);
+        assert_eq!(versions[0].source, NoteSaveSource::Restore);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn corrupt_history_index_does_not_block_saves() {
+        let (temp_dir, vault) = test_vault("vault_history_corrupt", &["notes"]);
+        vault.write_note("notes/D.md", "one\n").expect("write 1");
+        let index_path = vault
+            .history_index_path("notes/D.md", None)
+            .expect("index path");
+        fs::write(&index_path, "{ not json").expect("corrupt index");
+
+        vault.write_note("notes/D.md", "two\n").expect("write 2");
+        assert_eq!(vault.read_note("notes/D.md").expect("read"), "two\n");
+        assert!(vault.list_note_versions("notes/D.md").is_err());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn versions_follow_note_id_and_retention_prunes_blobs() {
+        let (temp_dir, vault) = test_vault("vault_history_retention", &["notes"]);
+        let vault = vault.with_history_retention(HistoryRetention {
+            max_versions_per_note: 2,
+            max_age: None,
+        });
+
+        for n in 0..4 {
+            vault
+                .write_note("notes/B.md", &format!("---\nid: N100\n---\nbody {n}\n"))
+                .expect("write");
+        }
+        fs::rename(temp_dir.join("notes/B.md"), temp_dir.join("notes/C.md")).expect("rename");
+
+        let by_path = vault
+            .list_note_versions("notes/C.md")
+            .expect("list by path");
+        let by_id = vault.list_note_versions_by_id("N100").expect("list by id");
+        assert_eq!(by_path, by_id);
+        assert_eq!(by_id.len(), 2);
+        assert_eq!(
+            vault.read_note_version(&by_id[0]).expect("read version"),
+            "---\nid: N100\n---\nbody 3\n"
+        );
+
+        vault.prune_note_history().expect("prune");
+        let blob_count = read_dir_paths(&vault.history_dir().join(HISTORY_BLOBS_DIR))
+            .expect("shards")
+            .into_iter()
+            .map(|shard| read_dir_paths(&shard).expect("blobs").len())
+            .sum::<usize>();
+        assert_eq!(blob_count, 2);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,189 @@
+//! Info records (captured snippets, bookmarks and quotes) under `.xnote/meta/infos/<id>.json`.
+
+use super::{now_epoch_ms, Vault};
+use crate::atomic_write::write_atomic;
+use crate::info_meta::{generate_info_id, normalize_info_id, InfoKind, InfoMetaV1, InfoSource};
+use crate::note_meta::NoteMetaTarget;
+use anyhow::{anyhow, Context as _, Result};
+use std::path::PathBuf;
+
+const INFO_META_DIR: &str = "infos";
+
+impl Vault {
+    pub fn info_meta_dir(&self) -> PathBuf {
+        self.root.join(".xnote").join("meta").join(INFO_META_DIR)
+    }
+
+    pub fn info_meta_file_path(&self, info_id: &str) -> Result<PathBuf> {
+        let info_id = normalize_info_id(info_id)?;
+        Ok(self.info_meta_dir().join(format!("{info_id}.json")))
+    }
+
+    pub fn load_info_meta(&self, info_id: &str) -> Result<Option<InfoMetaV1>> {
+        let path = self.info_meta_file_path(info_id)?;
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read info meta file: {:?}", path));
+            }
+        };
+
+        let meta: InfoMetaV1 = serde_json::from_str(&content)
+            .with_context(|| format!("parse info meta json: {:?}", path))?;
+        meta.validate()
+            .with_context(|| format!("validate info meta file: {:?}", path))?;
+        Ok(Some(meta))
+    }
+
+    pub fn save_info_meta(&self, info_meta: &InfoMetaV1) -> Result<()> {
+        info_meta.validate()?;
+        let path = self.info_meta_file_path(&info_meta.id)?;
+        let content = info_meta.canonical_json()?;
+        write_atomic(&path, content.as_bytes())
+            .with_context(|| format!("write info meta file: {:?}", path))?;
+        Ok(())
+    }
+
+    pub fn delete_info_meta(&self, info_id: &str) -> Result<()> {
+        let path = self.info_meta_file_path(info_id)?;
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).with_context(|| format!("delete info meta file: {:?}", path)),
+        }
+    }
+
+    /// Creates and saves a new info record with a fresh id, captured now.
+    pub fn capture_info(
+        &self,
+        kind: InfoKind,
+        body: &str,
+        source: InfoSource,
+        tags: Vec<String>,
+    ) -> Result<InfoMetaV1> {
+        let mut info = InfoMetaV1::new(generate_info_id(), kind)?;
+        info.body = body.to_string();
+        info.source = source;
+        info.tags = tags;
+        info.captured_at_ms = now_epoch_ms();
+        self.save_info_meta(&info)?;
+        Ok(info)
+    }
+
+    /// Every valid info record, newest capture first.
+    ///
+    /// Files that fail to parse or validate are skipped; [`Vault::check`] reports them.
+    pub fn list_info_metas(&self) -> Result<Vec<InfoMetaV1>> {
+        let dir = self.info_meta_dir();
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err).with_context(|| format!("read info meta dir: {:?}", dir)),
+        };
+
+        let mut out = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Some(info_id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if let Ok(Some(meta)) = self.load_info_meta(info_id) {
+                out.push(meta);
+            }
+        }
+        out.sort_by(|a, b| {
+            b.captured_at_ms
+                .cmp(&a.captured_at_ms)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(out)
+    }
+
+    /// Follows a `kind: info` relation target to its record.
+    pub fn resolve_info_target(&self, target: &NoteMetaTarget) -> Result<Option<InfoMetaV1>> {
+        if target.kind.trim() != "info" {
+            return Err(anyhow!("relation target kind is not info: {}", target.kind));
+        }
+        if normalize_info_id(&target.id).is_err() {
+            return Ok(None);
+        }
+        self.load_info_meta(&target.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+    use std::fs;
+
+    #[test]
+    fn info_meta_crud_and_target_resolution() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_vault_info_meta_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).expect("create vault");
+        let vault = Vault::open(&temp_dir).expect("open vault");
+
+        let quote = vault
+            .capture_info(
+                InfoKind::Quote,
+                "Premature optimization is the root of all evil.",
+                InfoSource {
+                    author: Some("Knuth".to_string()),
+                    ..InfoSource::default()
+                },
+                vec!["perf".to_string()],
+            )
+            .expect("capture quote");
+        assert!(quote.captured_at_ms > 0);
+        assert!(vault
+            .info_meta_file_path(&quote.id)
+            .expect("path")
+            .ends_with(format!(".xnote/meta/infos/{}.json", quote.id)));
+        assert!(vault
+            .capture_info(InfoKind::Bookmark, "", InfoSource::default(), Vec::new())
+            .is_err());
+
+        let mut older = InfoMetaV1::new("F0", InfoKind::Snippet).expect("new");
+        older.body = "cargo test --workspace".to_string();
+        older.captured_at_ms = 1;
+        vault.save_info_meta(&older).expect("save");
+        fs::write(vault.info_meta_dir().join("broken.json"), "{").expect("broken");
+
+        let ids = vault
+            .list_info_metas()
+            .expect("list")
+            .into_iter()
+            .map(|m| m.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![quote.id.clone(), "F0".to_string()]);
+
+        let mut target = NoteMetaTarget {
+            kind: "info".to_string(),
+            id: quote.id.clone(),
+            anchor: None,
+            extra: Map::new(),
+        };
+        assert_eq!(
+            vault.resolve_info_target(&target).expect("resolve"),
+            Some(quote.clone())
+        );
+        target.id = "F404".to_string();
+        assert!(vault
+            .resolve_info_target(&target)
+            .expect("resolve")
+            .is_none());
+        target.kind = "resource".to_string();
+        assert!(vault.resolve_info_target(&target).is_err());
+
+        vault.delete_info_meta(&quote.id).expect("delete");
+        assert!(vault.load_info_meta(&quote.id).expect("load").is_none());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,499 @@
+use super::{now_epoch_ms, Vault};
+use crate::atomic_write::write_atomic;
+use anyhow::{anyhow, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+pub const VAULT_LOCK_VERSION_V1: u32 = 1;
+const VAULT_LOCK_FILE: &str = "session.lock";
+const TAKEOVER_SUFFIX: &str = ".takeover";
+static INSTANCE_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultLockOptions {
+    /// A lock whose heartbeat is older than this may be taken over.
+    pub stale_after: Duration,
+}
+
+impl Default for VaultLockOptions {
+    fn default() -> Self {
+        Self {
+            stale_after: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VaultLockOwner {
+    pub version: u32,
+    #[serde(rename = "instanceId")]
+    pub instance_id: String,
+    pub pid: u32,
+    /// Machine the owner runs on; `pid` only identifies a process there. Empty in locks
+    /// written before it was recorded.
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(rename = "acquiredAtMs")]
+    pub acquired_at_ms: u64,
+    #[serde(rename = "heartbeatAtMs")]
+    pub heartbeat_at_ms: u64,
+}
+
+/// Returned (inside `anyhow::Error`) when another live instance owns the vault.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultLockHeld {
+    /// `None` when the lock file is recent but cannot be read, e.g. while another
+    /// instance is taking it over.
+    pub owner: Option<VaultLockOwner>,
+}
+
+impl std::fmt::Display for VaultLockHeld {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.owner {
+            Some(owner) => write!(
+                f,
+                "vault is locked by pid {} (instance {})",
+                owner.pid, owner.instance_id
+            ),
+            None => write!(f, "vault is locked by another instance"),
+        }
+    }
+}
+
+impl std::error::Error for VaultLockHeld {}
+
+/// Held session lock on `.xnote/session.lock`; released on drop.
+#[derive(Debug)]
+pub struct VaultSessionLock {
+    path: PathBuf,
+    owner: VaultLockOwner,
+    /// Whether an abandoned lock was replaced to acquire this one.
+    took_over: bool,
+}
+
+impl VaultSessionLock {
+    pub fn owner(&self) -> &VaultLockOwner {
+        &self.owner
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.owner.instance_id
+    }
+
+    pub fn took_over(&self) -> bool {
+        self.took_over
+    }
+
+    /// Refreshes the heartbeat; fails if another instance has taken the lock over.
+    pub fn heartbeat(&mut self) -> Result<()> {
+        let current = read_lock_file(&self.path)?;
+        if current.as_ref().map(|o| o.instance_id.as_str()) != Some(self.instance_id()) {
+            return Err(anyhow!(
+                "vault session lock was taken over by another instance"
+            ));
+        }
+        self.owner.heartbeat_at_ms = now_epoch_ms();
+        write_lock_file(&self.path, &self.owner)
+    }
+
+    pub fn release(self) {}
+}
+
+impl Drop for VaultSessionLock {
+    fn drop(&mut self) {
+        if let Ok(Some(current)) = read_lock_file(&self.path) {
+            if current.instance_id == self.owner.instance_id {
+                let _ = std::fs::remove_file(&self.path);
+            }
+        }
+    }
+}
+
+impl Vault {
+    pub fn session_lock_path(&self) -> PathBuf {
+        self.root.join(".xnote").join(VAULT_LOCK_FILE)
+    }
+
+    /// Acquires the vault session lock, taking over a lock whose owner is gone or silent.
+    ///
+    /// The owner record is written to a temporary file first and then linked into place,
+    /// so the lock file never exists half-written. A lock file that cannot be parsed
+    /// counts as held until its modification time is older than
+    /// [`VaultLockOptions::stale_after`].
+    ///
+    /// When a live instance holds the lock the error downcasts to [`VaultLockHeld`].
+    pub fn acquire_session_lock(&self, options: &VaultLockOptions) -> Result<VaultSessionLock> {
+        let path = self.session_lock_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| "create .xnote folder")?;
+        }
+
+        let now_ms = now_epoch_ms();
+        let owner = VaultLockOwner {
+            version: VAULT_LOCK_VERSION_V1,
+            instance_id: next_instance_id(now_ms),
+            pid: std::process::id(),
+            hostname: local_hostname(),
+            acquired_at_ms: now_ms,
+            heartbeat_at_ms: now_ms,
+        };
+        let staged = path.with_file_name(format!("{VAULT_LOCK_FILE}.{}", owner.instance_id));
+        write_lock_file(&staged, &owner)?;
+        let acquired = publish_session_lock(&staged, &path, &owner, options, now_ms);
+        let _ = std::fs::remove_file(&staged);
+
+        let took_over = acquired?;
+        Ok(VaultSessionLock {
+            path,
+            owner,
+            took_over,
+        })
+    }
+
+    /// Current lock owner, if the lock file exists and parses.
+    pub fn session_lock_owner(&self) -> Result<Option<VaultLockOwner>> {
+        read_lock_file(&self.session_lock_path())
+    }
+}
+
+/// Links `staged` into place as the lock file; returns whether a stale lock was
+/// replaced on the way.
+fn publish_session_lock(
+    staged: &Path,
+    path: &Path,
+    owner: &VaultLockOwner,
+    options: &VaultLockOptions,
+    now_ms: u64,
+) -> Result<bool> {
+    if link_new(staged, path, owner)? {
+        return Ok(false);
+    }
+    check_lock_stale(path, options, now_ms)?;
+
+    // Takeover. Only the instance that creates the takeover marker may remove the
+    // stale lock, and it checks staleness again once it holds the marker, so two
+    // instances never both replace the same abandoned lock.
+    let marker = path.with_file_name(format!("{VAULT_LOCK_FILE}{TAKEOVER_SUFFIX}"));
+    if !link_new(staged, &marker, owner)? {
+        let marker_age = file_age(&marker);
+        if marker_age.is_some_and(|age| age <= options.stale_after) {
+            return Err(VaultLockHeld { owner: None }.into());
+        }
+        // Left behind by an instance that died mid-takeover.
+        let _ = std::fs::remove_file(&marker);
+        if !link_new(staged, &marker, owner)? {
+            return Err(VaultLockHeld { owner: None }.into());
+        }
+    }
+
+    let result = check_lock_stale(path, options, now_ms).and_then(|()| {
+        match std::fs::remove_file(path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| format!("remove stale session lock: {:?}", path))
+            }
+        }
+        // A plain acquirer may have created the lock in the meantime.
+        if link_new(staged, path, owner)? {
+            Ok(true)
+        } else {
+            Err(VaultLockHeld {
+                owner: read_lock_file(path).ok().flatten(),
+            }
+            .into())
+        }
+    });
+    let _ = std::fs::remove_file(&marker);
+    result
+}
+
+/// Fails with [`VaultLockHeld`] unless the lock at `path` is missing or abandoned.
+fn check_lock_stale(path: &Path, options: &VaultLockOptions, now_ms: u64) -> Result<()> {
+    match read_lock_file(path) {
+        Ok(None) => Ok(()),
+        Ok(Some(current)) if is_lock_stale(&current, options, now_ms) => Ok(()),
+        Ok(Some(current)) => Err(VaultLockHeld {
+            owner: Some(current),
+        }
+        .into()),
+        // Unreadable: trust it as long as it was written recently.
+        Err(_) if file_age(path).is_some_and(|age| age <= options.stale_after) => {
+            Err(VaultLockHeld { owner: None }.into())
+        }
+        Err(_) => Ok(()),
+    }
+}
+
+/// Creates `target` with the content of `staged`; `false` when `target` already exists.
+///
+/// A hard link publishes the complete file in one step. Filesystems without hard links
+/// fall back to an exclusive create followed by a write.
+fn link_new(staged: &Path, target: &Path, owner: &VaultLockOwner) -> Result<bool> {
+    match std::fs::hard_link(staged, target) {
+        Ok(()) => return Ok(true),
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => return Ok(false),
+        Err(_) => {}
+    }
+    let mut content = serde_json::to_string_pretty(owner)?;
+    content.push('\n');
+    match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)
+    {
+        Ok(mut file) => {
+            use std::io::Write as _;
+            file.write_all(content.as_bytes())
+                .and_then(|_| file.sync_all())
+                .with_context(|| format!("write session lock: {:?}", target))?;
+            Ok(true)
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(err) => Err(err).with_context(|| format!("create session lock: {:?}", target)),
+    }
+}
+
+fn file_age(path: &Path) -> Option<Duration> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(
+        SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default(),
+    )
+}
+
+/// The owner's pid is only checked on its own machine; a lock from another machine, e.g.
+/// on a synced vault, goes stale by heartbeat age alone.
+fn is_lock_stale(owner: &VaultLockOwner, options: &VaultLockOptions, now_ms: u64) -> bool {
+    let silent_for = now_ms.saturating_sub(owner.heartbeat_at_ms);
+    let same_host = !owner.hostname.is_empty() && owner.hostname == local_hostname();
+    silent_for > options.stale_after.as_millis() as u64
+        || (same_host && !is_process_alive(owner.pid))
+}
+
+#[cfg(target_os = "linux")]
+fn local_hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn local_hostname() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "linux")]
+fn is_process_alive(pid: u32) -> bool {
+    std::path::Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_process_alive(_pid: u32) -> bool {
+    // Without a portable liveness probe the heartbeat age is the only staleness signal.
+    true
+}
+
+fn read_lock_file(path: &Path) -> Result<Option<VaultLockOwner>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("read session lock: {:?}", path)),
+    };
+    let owner = serde_json::from_str(&content)
+        .with_context(|| format!("parse session lock: {:?}", path))?;
+    Ok(Some(owner))
+}
+
+fn write_lock_file(path: &Path, owner: &VaultLockOwner) -> Result<()> {
+    let mut content = serde_json::to_string_pretty(owner)?;
+    content.push('\n');
+    write_atomic(path, content.as_bytes())
+        .with_context(|| format!("write session lock: {:?}", path))?;
+    Ok(())
+}
+
+pub(super) fn next_instance_id(now_ms: u64) -> String {
+    let seq = INSTANCE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("I{now_ms:013}-{:05X}-{seq:04X}", std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::test_vault;
+    use std::fs;
+
+    #[test]
+    fn session_lock_is_exclusive_and_released_on_drop() {
+        let (temp_dir, vault) = test_vault("vault_lock_exclusive", &[]);
+        let options = VaultLockOptions::default();
+
+        let mut lock = vault.acquire_session_lock(&options).expect("acquire");
+        assert!(!lock.took_over());
+        lock.heartbeat().expect("heartbeat");
+
+        let err = vault
+            .acquire_session_lock(&options)
+            .expect_err("second acquire");
+        let held = err.downcast_ref::<VaultLockHeld>().expect("typed held");
+        let owner = held.owner.as_ref().expect("held owner");
+        assert_eq!(owner.instance_id, lock.instance_id());
+
+        drop(lock);
+        assert!(vault.session_lock_owner().expect("owner").is_none());
+        vault.acquire_session_lock(&options).expect("reacquire");
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn stale_session_lock_is_taken_over() {
+        let (temp_dir, vault) = test_vault("vault_lock_stale", &[]);
+        fs::create_dir_all(temp_dir.join(".xnote")).expect("create .xnote");
+        let stale = VaultLockOwner {
+            version: VAULT_LOCK_VERSION_V1,
+            instance_id: "I-old".to_string(),
+            pid: std::process::id(),
+            hostname: local_hostname(),
+            acquired_at_ms: 1,
+            heartbeat_at_ms: 1,
+        };
+        write_lock_file(&vault.session_lock_path(), &stale).expect("write stale");
+
+        let mut lock = vault
+            .acquire_session_lock(&VaultLockOptions::default())
+            .expect("takeover");
+        assert!(lock.took_over());
+        assert_eq!(
+            vault
+                .session_lock_owner()
+                .expect("owner")
+                .expect("some")
+                .pid,
+            std::process::id()
+        );
+
+        write_lock_file(&vault.session_lock_path(), &stale).expect("steal back");
+        assert!(lock.heartbeat().is_err());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn dead_pid_is_only_trusted_on_the_same_host() {
+        let (temp_dir, vault) = test_vault("vault_lock_host", &[]);
+        fs::create_dir_all(temp_dir.join(".xnote")).expect("create .xnote");
+        let now_ms = now_epoch_ms();
+        let mut owner = VaultLockOwner {
+            version: VAULT_LOCK_VERSION_V1,
+            instance_id: "I-remote".to_string(),
+            pid: u32::MAX - 7,
+            hostname: "another-machine".to_string(),
+            acquired_at_ms: now_ms,
+            heartbeat_at_ms: now_ms,
+        };
+        let options = VaultLockOptions::default();
+
+        write_lock_file(&vault.session_lock_path(), &owner).expect("write remote");
+        let err = vault
+            .acquire_session_lock(&options)
+            .expect_err("remote lock is live");
+        assert!(err.downcast_ref::<VaultLockHeld>().is_some());
+
+        owner.hostname = String::new();
+        write_lock_file(&vault.session_lock_path(), &owner).expect("write legacy");
+        assert!(vault.acquire_session_lock(&options).is_err());
+
+        if cfg!(target_os = "linux") {
+            owner.hostname = local_hostname();
+            write_lock_file(&vault.session_lock_path(), &owner).expect("write local");
+            let lock = vault
+                .acquire_session_lock(&options)
+                .expect("dead local owner");
+            assert!(lock.took_over());
+        }
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn racing_acquirers_get_exactly_one_lock() {
+        let (temp_dir, vault) = test_vault("vault_lock_race", &[]);
+        let stale = VaultLockOwner {
+            version: VAULT_LOCK_VERSION_V1,
+            instance_id: "I-old".to_string(),
+            pid: std::process::id(),
+            hostname: local_hostname(),
+            acquired_at_ms: 1,
+            heartbeat_at_ms: 1,
+        };
+
+        // Once on a free vault, once racing to take over an abandoned lock.
+        for seed_stale in [false, true] {
+            if seed_stale {
+                fs::create_dir_all(temp_dir.join(".xnote")).expect("create .xnote");
+                write_lock_file(&vault.session_lock_path(), &stale).expect("write stale");
+            }
+            let barrier = std::sync::Arc::new(std::sync::Barrier::new(8));
+            let handles = (0..8)
+                .map(|_| {
+                    let vault = vault.clone();
+                    let barrier = barrier.clone();
+                    std::thread::spawn(move || {
+                        barrier.wait();
+                        vault.acquire_session_lock(&VaultLockOptions::default())
+                    })
+                })
+                .collect::<Vec<_>>();
+            let results = handles
+                .into_iter()
+                .map(|handle| handle.join().expect("join"))
+                .collect::<Vec<_>>();
+
+            let winners = results.iter().filter(|r| r.is_ok()).collect::<Vec<_>>();
+            assert_eq!(winners.len(), 1, "seed_stale={seed_stale}");
+            for err in results.iter().filter_map(|r| r.as_ref().err()) {
+                assert!(err.downcast_ref::<VaultLockHeld>().is_some(), "{err:#}");
+            }
+            let owner = vault.session_lock_owner().expect("owner").expect("some");
+            assert_eq!(
+                Some(owner.instance_id.as_str()),
+                winners[0].as_ref().ok().map(|lock| lock.instance_id())
+            );
+            drop(results);
+            assert!(vault.session_lock_owner().expect("owner").is_none());
+        }
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn unreadable_lock_counts_as_held_until_old() {
+        let (temp_dir, vault) = test_vault("vault_lock_unreadable", &[]);
+        fs::create_dir_all(temp_dir.join(".xnote")).expect("create .xnote");
+        fs::write(vault.session_lock_path(), "{\"version\": 1,").expect("write partial");
+        let options = VaultLockOptions::default();
+
+        let err = vault.acquire_session_lock(&options).expect_err("held");
+        let held = err.downcast_ref::<VaultLockHeld>().expect("typed held");
+        assert!(held.owner.is_none());
+
+        let old = SystemTime::now() - Duration::from_secs(120);
+        fs::File::options()
+            .write(true)
+            .open(vault.session_lock_path())
+            .and_then(|file| file.set_modified(old))
+            .expect("age lock file");
+        let lock = vault.acquire_session_lock(&options).expect("takeover");
+        assert!(lock.took_over());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,185 @@
+//! Bulk on-disk migration of `.xnote/meta/*.json` to the current note meta version.
+
+use super::{list_json_files, now_epoch_ms, Vault};
+use crate::atomic_write::write_atomic;
+use crate::note_meta::{NoteMetaV1, NOTE_META_MIGRATIONS};
+use anyhow::{Context as _, Result};
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetaMigrationEntry {
+    /// Vault-relative POSIX path of the meta file.
+    pub path: String,
+    pub from_version: u32,
+    pub to_version: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetaMigrationFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetaMigrationReport {
+    pub dry_run: bool,
+    pub files_scanned: usize,
+    /// Files that need (or, outside a dry run, received) an upgrade.
+    pub migrated: Vec<MetaMigrationEntry>,
+    /// Files left untouched because they cannot be read, upgraded or validated.
+    pub failed: Vec<MetaMigrationFailure>,
+    /// Vault-relative folder holding the original bytes of every migrated file.
+    pub backup_dir: Option<String>,
+}
+
+impl Vault {
+    /// Rewrites every outdated note meta file at the current version.
+    ///
+    /// Originals are copied to `.xnote/backups/meta-<ms>/` before the first rewrite;
+    /// `dry_run` only reports what would change.
+    pub fn migrate_note_meta_files(&self, dry_run: bool) -> Result<MetaMigrationReport> {
+        let mut report = MetaMigrationReport {
+            dry_run,
+            ..MetaMigrationReport::default()
+        };
+        let meta_dir = self.root.join(".xnote").join("meta");
+        let mut backup_dir: Option<PathBuf> = None;
+
+        for path in list_json_files(&meta_dir)? {
+            report.files_scanned += 1;
+            let file_name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let rel = format!(".xnote/meta/{file_name}");
+
+            let upgraded = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(serde_json::from_str(&content)?))
+                .and_then(|value| NOTE_META_MIGRATIONS.migrate(value))
+                .and_then(|outcome| {
+                    let meta: NoteMetaV1 = serde_json::from_value(outcome.value.clone())?;
+                    let json = meta.canonical_json()?;
+                    Ok((outcome, json))
+                });
+            let (outcome, json) = match upgraded {
+                Ok(upgraded) => upgraded,
+                Err(err) => {
+                    report.failed.push(MetaMigrationFailure {
+                        path: rel,
+                        error: format!("{err:#}"),
+                    });
+                    continue;
+                }
+            };
+            if !outcome.migrated() {
+                continue;
+            }
+
+            if !dry_run {
+                let backup = match backup_dir.as_ref() {
+                    Some(dir) => dir.clone(),
+                    None => {
+                        let dir = self
+                            .root
+                            .join(".xnote")
+                            .join("backups")
+                            .join(format!("meta-{}", now_epoch_ms()));
+                        std::fs::create_dir_all(&dir)
+                            .with_context(|| format!("create migration backup dir: {:?}", dir))?;
+                        backup_dir = Some(dir.clone());
+                        dir
+                    }
+                };
+                std::fs::copy(&path, backup.join(&file_name))
+                    .with_context(|| format!("back up meta file: {:?}", path))?;
+                write_atomic(&path, json.as_bytes())
+                    .with_context(|| format!("write migrated meta file: {:?}", path))?;
+            }
+            report.migrated.push(MetaMigrationEntry {
+                path: rel,
+                from_version: outcome.from_version,
+                to_version: outcome.to_version,
+            });
+        }
+
+        report.backup_dir = backup_dir
+            .as_deref()
+            .and_then(|dir| dir.strip_prefix(&self.root).ok())
+            .map(|dir| dir.to_string_lossy().replace('\\', "/"));
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_meta::NOTE_META_VERSION_CURRENT;
+    use std::fs;
+
+    const V0_FIXTURE: &str = r#"{
+  "id": "N1",
+  "updatedAt": "2025-12-01T08:00:00Z",
+  "pins": {"notes": ["N2"]},
+  "ext": {"plugin.demo": 1},
+  "legacyFlag": true
+}
+"#;
+
+    #[test]
+    fn migrate_note_meta_files_reports_backs_up_and_rewrites() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_vault_migrate_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join(".xnote/meta")).expect("create meta");
+        let vault = Vault::open(&temp_dir).expect("open vault");
+
+        let meta_dir = temp_dir.join(".xnote/meta");
+        fs::write(meta_dir.join("N1.json"), V0_FIXTURE).expect("v0 fixture");
+        vault
+            .save_note_meta(&NoteMetaV1::new("N2").expect("meta"))
+            .expect("current meta");
+        fs::write(meta_dir.join("N3.json"), r#"{"version": 7, "id": "N3"}"#).expect("newer");
+
+        // Old files load through the in-memory upgrade before any migration runs.
+        let loaded = vault.load_note_meta("N1").expect("load").expect("some");
+        assert_eq!(loaded.pins.notes, vec!["N2"]);
+
+        let dry = vault.migrate_note_meta_files(true).expect("dry run");
+        assert_eq!(dry.files_scanned, 3);
+        assert_eq!(
+            dry.migrated,
+            vec![MetaMigrationEntry {
+                path: ".xnote/meta/N1.json".to_string(),
+                from_version: 0,
+                to_version: NOTE_META_VERSION_CURRENT,
+            }]
+        );
+        assert_eq!(dry.failed.len(), 1);
+        assert!(dry.failed[0].error.contains("newer"));
+        assert!(dry.backup_dir.is_none());
+        assert_eq!(
+            fs::read_to_string(meta_dir.join("N1.json")).expect("read"),
+            V0_FIXTURE
+        );
+
+        let report = vault.migrate_note_meta_files(false).expect("migrate");
+        assert_eq!(report.migrated.len(), 1);
+        let backup_dir = report.backup_dir.expect("backup dir");
+        assert!(backup_dir.starts_with(".xnote/backups/meta-"));
+        assert_eq!(
+            fs::read_to_string(temp_dir.join(&backup_dir).join("N1.json")).expect("backup"),
+            V0_FIXTURE
+        );
+        let migrated = fs::read_to_string(meta_dir.join("N1.json")).expect("read");
+        assert!(migrated.contains("\"version\": 1"));
+        assert!(migrated.contains("\"legacyFlag\": true"));
+        assert!(migrated.contains("\"plugin.demo\": 1"));
+
+        let again = vault.migrate_note_meta_files(false).expect("rerun");
+        assert!(again.migrated.is_empty());
+        assert!(again.backup_dir.is_none());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
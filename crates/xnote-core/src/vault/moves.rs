@@ -0,0 +1,666 @@
+use super::Vault;
+use crate::knowledge::{rewrite_note_links, KnowledgeIndex};
+use crate::paths::{join_inside, normalize_folder_rel_path, normalize_vault_rel_path};
+use crate::watch::note_path_has_folder_prefix;
+use anyhow::{bail, Context as _, Result};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VaultMoveKind {
+    Note,
+    Folder,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteContentChange {
+    /// Path of the note before the move was applied.
+    pub path_before: String,
+    /// Path of the note after the move was applied (differs for moved notes).
+    pub path_after: String,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderFileChange {
+    pub folder: String,
+    /// `None` when the order file did not exist.
+    pub before: Option<Vec<String>>,
+    /// `None` when the order file is removed.
+    pub after: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultMoveChangeSet {
+    pub kind: VaultMoveKind,
+    pub from: String,
+    pub to: String,
+    /// Every note path that changed, sorted by old path.
+    pub moved_notes: Vec<(String, String)>,
+    pub content_changes: Vec<NoteContentChange>,
+    pub order_changes: Vec<OrderFileChange>,
+}
+
+impl VaultMoveChangeSet {
+    /// Note paths (post-move) whose content or location changed.
+    pub fn touched_note_paths(&self) -> Vec<String> {
+        let mut out = BTreeSet::new();
+        for (_, to) in &self.moved_notes {
+            out.insert(to.clone());
+        }
+        for change in &self.content_changes {
+            out.insert(change.path_after.clone());
+        }
+        out.into_iter().collect()
+    }
+}
+
+impl Vault {
+    /// Moves or renames a single note and rewrites every link that pointed at it.
+    pub fn move_note(
+        &self,
+        index: &KnowledgeIndex,
+        from: &str,
+        to: &str,
+    ) -> Result<VaultMoveChangeSet> {
+        let from = normalize_vault_rel_path(from)?;
+        let to = normalize_vault_rel_path(to)?;
+        if from == to {
+            bail!("move source and target are the same: {from}");
+        }
+        if !to.to_lowercase().ends_with(".md") {
+            bail!("move target must be a markdown note: {to}");
+        }
+
+        let from_full = join_inside(&self.root, &from)?;
+        let to_full = join_inside(&self.root, &to)?;
+        if !from_full.is_file() {
+            bail!("note does not exist: {from}");
+        }
+        if to_full.exists() {
+            bail!("move target already exists: {to}");
+        }
+
+        let moves = vec![(from.clone(), to.clone())];
+        let content_changes = self.plan_link_rewrites(index, &moves)?;
+        let order_changes = self.plan_note_order_changes(&from, &to)?;
+
+        if let Some(parent) = to_full.parent() {
+            std::fs::create_dir_all(parent).with_context(|| "create move target dir")?;
+        }
+        std::fs::rename(&from_full, &to_full)
+            .with_context(|| format!("move note: {from} -> {to}"))?;
+
+        let change_set = VaultMoveChangeSet {
+            kind: VaultMoveKind::Note,
+            from,
+            to,
+            moved_notes: moves,
+            content_changes,
+            order_changes,
+        };
+        self.apply_move_side_effects(&change_set)?;
+        Ok(change_set)
+    }
+
+    /// Moves or renames a folder and rewrites every link into the moved notes.
+    pub fn move_folder(
+        &self,
+        index: &KnowledgeIndex,
+        from: &str,
+        to: &str,
+    ) -> Result<VaultMoveChangeSet> {
+        let from = normalize_folder_rel_path(from)?;
+        let to = normalize_folder_rel_path(to)?;
+        if from == to {
+            bail!("move source and target are the same: {from}");
+        }
+        if note_path_has_folder_prefix(&to, &from) {
+            bail!("cannot move folder into itself: {from} -> {to}");
+        }
+
+        let from_full = join_inside(&self.root, &from)?;
+        let to_full = join_inside(&self.root, &to)?;
+        if !from_full.is_dir() {
+            bail!("folder does not exist: {from}");
+        }
+        if to_full.exists() {
+            bail!("move target already exists: {to}");
+        }
+
+        let from_prefix = format!("{from}/");
+        let moves = index
+            .all_paths_sorted()
+            .into_iter()
+            .filter_map(|path| {
+                let suffix = path.strip_prefix(&from_prefix)?;
+                Some((path.clone(), format!("{to}/{suffix}")))
+            })
+            .collect::<Vec<_>>();
+
+        let content_changes = self.plan_link_rewrites(index, &moves)?;
+        let order_changes = self.plan_folder_order_changes(&from, &to)?;
+
+        if let Some(parent) = to_full.parent() {
+            std::fs::create_dir_all(parent).with_context(|| "create move target dir")?;
+        }
+        std::fs::rename(&from_full, &to_full)
+            .with_context(|| format!("move folder: {from} -> {to}"))?;
+
+        let change_set = VaultMoveChangeSet {
+            kind: VaultMoveKind::Folder,
+            from,
+            to,
+            moved_notes: moves,
+            content_changes,
+            order_changes,
+        };
+        self.apply_move_side_effects(&change_set)?;
+        Ok(change_set)
+    }
+
+    /// Undoes a change set returned by [`Vault::move_note`] or [`Vault::move_folder`].
+    pub fn revert_move(&self, change_set: &VaultMoveChangeSet) -> Result<()> {
+        let from_full = join_inside(&self.root, &change_set.from)?;
+        let to_full = join_inside(&self.root, &change_set.to)?;
+        if from_full.exists() {
+            bail!(
+                "cannot revert move, source is occupied: {}",
+                change_set.from
+            );
+        }
+
+        for change in &change_set.order_changes {
+            self.write_order_state(&change.folder, change.before.as_deref())?;
+        }
+
+        if let Some(parent) = from_full.parent() {
+            std::fs::create_dir_all(parent).with_context(|| "create revert target dir")?;
+        }
+        std::fs::rename(&to_full, &from_full)
+            .with_context(|| format!("revert move: {} -> {}", change_set.to, change_set.from))?;
+
+        for change in &change_set.content_changes {
+            self.write_note(&change.path_before, &change.before)?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites links and order files once the rename is done. If a write fails, the
+    /// notes and order files already written are put back and the rename is undone.
+    fn apply_move_side_effects(&self, change_set: &VaultMoveChangeSet) -> Result<()> {
+        let mut notes_written = 0usize;
+        let mut orders_written = 0usize;
+        let mut apply = || -> Result<()> {
+            for change in &change_set.content_changes {
+                self.write_note(&change.path_after, &change.after)?;
+                notes_written += 1;
+            }
+            for change in &change_set.order_changes {
+                self.write_order_state(&change.folder, change.after.as_deref())?;
+                orders_written += 1;
+            }
+            Ok(())
+        };
+        let Err(err) = apply() else {
+            return Ok(());
+        };
+
+        let mut not_restored = Vec::new();
+        for change in change_set.order_changes[..orders_written].iter().rev() {
+            if self
+                .write_order_state(&change.folder, change.before.as_deref())
+                .is_err()
+            {
+                not_restored.push(format!("order of {}", change.folder));
+            }
+        }
+        for change in change_set.content_changes[..notes_written].iter().rev() {
+            if self.write_note(&change.path_after, &change.before).is_err() {
+                not_restored.push(change.path_after.clone());
+            }
+        }
+        let renamed_back = join_inside(&self.root, &change_set.to)
+            .and_then(|to_full| {
+                let from_full = join_inside(&self.root, &change_set.from)?;
+                std::fs::rename(&to_full, &from_full)?;
+                Ok(())
+            })
+            .is_ok();
+        if !renamed_back {
+            not_restored.push(format!("{} (still at {})", change_set.from, change_set.to));
+        }
+
+        Err(if not_restored.is_empty() {
+            err.context(format!(
+                "rolled back move: {} -> {}",
+                change_set.from, change_set.to
+            ))
+        } else {
+            err.context(format!(
+                "rollback failed, left moved: {}",
+                not_restored.join(", ")
+            ))
+        })
+    }
+
+    fn plan_link_rewrites(
+        &self,
+        index: &KnowledgeIndex,
+        moves: &[(String, String)],
+    ) -> Result<Vec<NoteContentChange>> {
+        let move_map = moves.iter().cloned().collect::<HashMap<_, _>>();
+
+        let mut referencing = BTreeSet::new();
+        for (from, _) in moves {
+            referencing.insert(from.clone());
+            referencing.extend(index.backlinks_for(from, usize::MAX));
+        }
+
+        let mut out = Vec::new();
+        for path_before in referencing {
+            let before = match self.read_note(&path_before) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            let after = rewrite_note_links(&before, |link| {
+                rewrite_moved_link_target(index, &move_map, link)
+            });
+            if after == before {
+                continue;
+            }
+            let path_after = move_map
+                .get(&path_before)
+                .cloned()
+                .unwrap_or_else(|| path_before.clone());
+            out.push(NoteContentChange {
+                path_before,
+                path_after,
+                before,
+                after,
+            });
+        }
+        Ok(out)
+    }
+
+    fn plan_note_order_changes(&self, from: &str, to: &str) -> Result<Vec<OrderFileChange>> {
+        let from_folder = parent_folder(from);
+        let to_folder = parent_folder(to);
+        let mut out = Vec::new();
+
+        if from_folder == to_folder {
+            if let Some(folder) = from_folder {
+                if let Some(before) = self.load_order_state(folder)? {
+                    let after = before
+                        .iter()
+                        .map(|p| if p == from { to.to_string() } else { p.clone() })
+                        .collect::<Vec<_>>();
+                    if after != before {
+                        out.push(OrderFileChange {
+                            folder: folder.to_string(),
+                            before: Some(before),
+                            after: Some(after),
+                        });
+                    }
+                }
+            }
+            return Ok(out);
+        }
+
+        if let Some(folder) = from_folder {
+            if let Some(before) = self.load_order_state(folder)? {
+                if before.iter().any(|p| p == from) {
+                    let after = before.iter().filter(|p| *p != from).cloned().collect();
+                    out.push(OrderFileChange {
+                        folder: folder.to_string(),
+                        before: Some(before),
+                        after: Some(after),
+                    });
+                }
+            }
+        }
+        if let Some(folder) = to_folder {
+            if let Some(before) = self.load_order_state(folder)? {
+                let mut after = before.clone();
+                after.push(to.to_string());
+                out.push(OrderFileChange {
+                    folder: folder.to_string(),
+                    before: Some(before),
+                    after: Some(after),
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    fn plan_folder_order_changes(&self, from: &str, to: &str) -> Result<Vec<OrderFileChange>> {
+        let order_root = self.root.join(".xnote").join("order");
+        let mut folders = BTreeSet::new();
+        collect_order_folders(&order_root, &order_root, &mut folders)?;
+
+        let mut out = Vec::new();
+        for folder in folders {
+            if !note_path_has_folder_prefix(&folder, from) {
+                continue;
+            }
+            let Some(before) = self.load_order_state(&folder)? else {
+                continue;
+            };
+            let new_folder = format!("{to}{}", &folder[from.len()..]);
+            let after = before
+                .iter()
+                .map(|p| match p.strip_prefix(from) {
+                    Some(rest) if rest.starts_with('/') => format!("{to}{rest}"),
+                    _ => p.clone(),
+                })
+                .collect::<Vec<_>>();
+
+            out.push(OrderFileChange {
+                folder: new_folder.clone(),
+                before: self.load_order_state(&new_folder)?,
+                after: Some(after),
+            });
+            out.push(OrderFileChange {
+                folder,
+                before: Some(before),
+                after: None,
+            });
+        }
+        Ok(out)
+    }
+
+    fn load_order_state(&self, folder: &str) -> Result<Option<Vec<String>>> {
+        if !self.order_file_path(folder)?.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(self.load_folder_order(folder)?))
+    }
+
+    fn write_order_state(&self, folder: &str, paths: Option<&[String]>) -> Result<()> {
+        match paths {
+            Some(paths) => self.save_folder_order(folder, paths),
+            None => {
+                let path = self.order_file_path(folder)?;
+                match std::fs::remove_file(&path) {
+                    Ok(()) => Ok(()),
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                    Err(err) => Err(err).with_context(|| format!("delete order file: {:?}", path)),
+                }
+            }
+        }
+    }
+}
+
+fn rewrite_moved_link_target(
+    index: &KnowledgeIndex,
+    move_map: &HashMap<String, String>,
+    link: &str,
+) -> Option<String> {
+    let old_path = index.resolve_link_target(link)?;
+    let new_path = move_map.get(&old_path)?;
+
+    let link_lower = link.trim().to_lowercase();
+    if link_lower.starts_with("id:") {
+        return None;
+    }
+    if let Some(note_id) = index.note_summary(&old_path).and_then(|s| s.note_id) {
+        if note_id.to_lowercase() == link_lower {
+            return None;
+        }
+    }
+
+    let keep_ext = link_lower.ends_with(".md");
+    let full_form = if keep_ext {
+        new_path.clone()
+    } else {
+        strip_md_ext(new_path).to_string()
+    };
+    if link_lower.contains('/') {
+        return Some(full_form);
+    }
+
+    let old_file = file_name(&old_path).to_lowercase();
+    let old_stem = strip_md_ext(&old_file);
+    if link_lower != old_file && link_lower != old_stem {
+        // Resolved through title or alias; those survive the move untouched.
+        return None;
+    }
+
+    let new_file = file_name(new_path);
+    let bare = if keep_ext {
+        new_file.to_string()
+    } else {
+        strip_md_ext(new_file).to_string()
+    };
+    match index.resolve_link_target(&bare) {
+        Some(existing) if existing != old_path && !move_map.contains_key(&existing) => {
+            Some(full_form)
+        }
+        _ => Some(bare),
+    }
+}
+
+fn collect_order_folders(
+    order_root: &std::path::Path,
+    dir: &std::path::Path,
+    out: &mut BTreeSet<String>,
+) -> Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).with_context(|| format!("read order dir: {:?}", dir)),
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_order_folders(order_root, &path, out)?;
+            continue;
+        }
+        let Ok(rel) = path.strip_prefix(order_root) else {
+            continue;
+        };
+        let rel = crate::paths::to_posix_path(rel)?;
+        if let Some(folder) = rel.strip_suffix(".order.md") {
+            out.insert(folder.to_string());
+        }
+    }
+    Ok(())
+}
+
+fn parent_folder(path: &str) -> Option<&str> {
+    path.rsplit_once('/').map(|(folder, _)| folder)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit_once('/').map(|(_, name)| name).unwrap_or(path)
+}
+
+fn strip_md_ext(path: &str) -> &str {
+    if path.len() >= 3 && path[path.len() - 3..].eq_ignore_ascii_case(".md") {
+        &path[..path.len() - 3]
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::test_vault;
+    use std::fs;
+
+    #[test]
+    fn move_note_rewrites_links_and_order_and_reverts() {
+        let (temp_dir, vault) = test_vault("vault_moves_note", &["notes/sub"]);
+        fs::write(temp_dir.join("notes/Beta.md"), "# Beta\n").expect("write Beta");
+        fs::write(
+            temp_dir.join("notes/Alpha.md"),
+            "# Alpha\n[[notes/Beta#Intro|Shown]] [[Beta]] [b](notes/Beta.md#H) [[Beta Title]]\n",
+        )
+        .expect("write Alpha");
+        vault
+            .save_folder_order(
+                "notes",
+                &["notes/Beta.md".to_string(), "notes/Alpha.md".to_string()],
+            )
+            .expect("save order");
+
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("index");
+        let change_set = vault
+            .move_note(&index, "notes/Beta.md", "notes/sub/Gamma.md")
+            .expect("move note");
+
+        assert_eq!(
+            change_set.moved_notes,
+            vec![(
+                "notes/Beta.md".to_string(),
+                "notes/sub/Gamma.md".to_string()
+            )]
+        );
+        let alpha = vault.read_note("notes/Alpha.md").expect("read Alpha");
+        assert_eq!(
+            alpha,
+            "# Alpha\n[[notes/sub/Gamma#Intro|Shown]] [[Gamma]] [b](notes/sub/Gamma.md#H) [[Beta Title]]\n"
+        );
+        assert_eq!(
+            vault.load_folder_order("notes").expect("order"),
+            vec!["notes/Alpha.md"]
+        );
+
+        vault.revert_move(&change_set).expect("revert");
+        assert!(temp_dir.join("notes/Beta.md").is_file());
+        assert!(!temp_dir.join("notes/sub/Gamma.md").exists());
+        assert!(vault
+            .read_note("notes/Alpha.md")
+            .expect("read Alpha")
+            .contains("[[notes/Beta#Intro|Shown]] [[Beta]]"));
+        assert_eq!(
+            vault.load_folder_order("notes").expect("order"),
+            vec!["notes/Beta.md", "notes/Alpha.md"]
+        );
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn failed_move_rolls_back_links_and_rename() {
+        let (temp_dir, vault) = test_vault("vault_moves_rollback", &["notes/sub"]);
+        fs::write(temp_dir.join("notes/sub/Beta.md"), "# Beta\n").expect("write Beta");
+        let alpha = "# Alpha\n[[notes/sub/Beta]]\n";
+        fs::write(temp_dir.join("notes/Alpha.md"), alpha).expect("write Alpha");
+        vault
+            .save_folder_order("notes/sub", &["notes/sub/Beta.md".to_string()])
+            .expect("save order");
+        // A directory where the moved folder's order file goes makes that write fail
+        // after the links were rewritten.
+        fs::create_dir_all(vault.order_file_path("archive/sub").expect("order path"))
+            .expect("block order file");
+
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("index");
+        let err = vault
+            .move_folder(&index, "notes/sub", "archive/sub")
+            .expect_err("order write fails");
+        assert!(format!("{err:#}").contains("rolled back move"), "{err:#}");
+
+        assert!(temp_dir.join("notes/sub/Beta.md").is_file());
+        assert!(!temp_dir.join("archive/sub").exists());
+        assert_eq!(
+            vault.read_note("notes/Alpha.md").expect("read Alpha"),
+            alpha
+        );
+        assert_eq!(
+            vault.load_folder_order("notes/sub").expect("order"),
+            vec!["notes/sub/Beta.md"]
+        );
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn move_note_keeps_id_and_alias_links() {
+        let (temp_dir, vault) = test_vault("vault_moves_note_ids", &["notes/sub"]);
+        fs::write(
+            temp_dir.join("notes/Beta.md"),
+            "---\nid: 01HBETA\naliases: [Guide]\n---\n# Beta\n",
+        )
+        .expect("write Beta");
+        fs::write(
+            temp_dir.join("notes/Alpha.md"),
+            "# Alpha\n[[id:01HBETA]] [[Guide]] [[01HBETA]]\n",
+        )
+        .expect("write Alpha");
+
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("index");
+        let change_set = vault
+            .move_note(&index, "notes/Beta.md", "notes/Renamed.md")
+            .expect("move note");
+
+        assert!(change_set.content_changes.is_empty());
+        assert!(change_set.order_changes.is_empty());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn move_folder_rewrites_nested_links_and_order_files() {
+        let (temp_dir, vault) = test_vault("vault_moves_folder", &["notes/sub"]);
+        fs::write(
+            temp_dir.join("notes/sub/Inner.md"),
+            "# Inner\n[[notes/sub/Other]]\n",
+        )
+        .expect("write Inner");
+        fs::write(temp_dir.join("notes/sub/Other.md"), "# Other\n").expect("write Other");
+        fs::write(
+            temp_dir.join("notes/Outer.md"),
+            "# Outer\n[[notes/sub/Inner.md]]\n",
+        )
+        .expect("write Outer");
+        vault
+            .save_folder_order(
+                "notes/sub",
+                &[
+                    "notes/sub/Other.md".to_string(),
+                    "notes/sub/Inner.md".to_string(),
+                ],
+            )
+            .expect("save order");
+
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("index");
+        let change_set = vault
+            .move_folder(&index, "notes/sub", "archive/moved")
+            .expect("move folder");
+
+        assert_eq!(change_set.moved_notes.len(), 2);
+        assert_eq!(
+            vault.read_note("notes/Outer.md").expect("read Outer"),
+            "# Outer\n[[archive/moved/Inner.md]]\n"
+        );
+        assert_eq!(
+            vault
+                .read_note("archive/moved/Inner.md")
+                .expect("read Inner"),
+            "# Inner\n[[archive/moved/Other]]\n"
+        );
+        assert_eq!(
+            vault.load_folder_order("archive/moved").expect("order"),
+            vec!["archive/moved/Other.md", "archive/moved/Inner.md"]
+        );
+        assert!(!vault
+            .order_file_path("notes/sub")
+            .expect("order path")
+            .exists());
+
+        vault.revert_move(&change_set).expect("revert");
+        assert!(temp_dir.join("notes/sub/Inner.md").is_file());
+        assert_eq!(
+            vault.read_note("notes/Outer.md").expect("read Outer"),
+            "# Outer\n[[notes/sub/Inner.md]]\n"
+        );
+        assert_eq!(
+            vault.load_folder_order("notes/sub").expect("order"),
+            vec!["notes/sub/Other.md", "notes/sub/Inner.md"]
+        );
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,633 @@
+use super::{
+    list_json_files, now_epoch_ms, NoteSaveSource, NoteWriteConflict, NoteWriteExpectation, Vault,
+};
+use crate::atomic_write::{content_hash, write_atomic};
+use crate::knowledge::{literal_pattern, KnowledgeIndex, SearchOptions};
+use anyhow::{anyhow, bail, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const REPLACE_OP_VERSION_V1: u32 = 1;
+const HISTORY_OPS_DIR: &str = "ops";
+static REPLACE_OP_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// One replacement of matched text, located in the note content it was previewed against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplaceEdit {
+    /// Unique within its [`ReplacePreview`]; selects the edit for [`Vault::apply_replace`].
+    pub id: usize,
+    /// 1-based line of the match.
+    pub line: usize,
+    /// Byte range of the match in the note content.
+    pub range: Range<usize>,
+    pub matched: String,
+    /// Text the match is replaced with, capture groups already expanded.
+    pub replacement: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteReplacePreview {
+    pub path: String,
+    /// Hash of the content the edits were computed against.
+    pub content_hash: String,
+    /// In content order, never overlapping.
+    pub edits: Vec<ReplaceEdit>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplacePreview {
+    pub query: String,
+    pub replacement: String,
+    /// Notes with at least one match, sorted by path.
+    pub notes: Vec<NoteReplacePreview>,
+}
+
+impl ReplacePreview {
+    pub fn edit_count(&self) -> usize {
+        self.notes.iter().map(|note| note.edits.len()).sum()
+    }
+
+    /// Ids of every edit, for applying the preview as a whole.
+    pub fn edit_ids(&self) -> BTreeSet<usize> {
+        self.notes
+            .iter()
+            .flat_map(|note| note.edits.iter().map(|edit| edit.id))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReplacedNote {
+    pub path: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// What [`Vault::apply_replace`] changed, for [`Vault::revert_replace`].
+///
+/// Kept as `.xnote/history/ops/<id>.json`, so a replace can still be reverted after the
+/// app restarts.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReplaceChangeSet {
+    pub version: u32,
+    pub id: String,
+    #[serde(rename = "appliedAtMs")]
+    pub applied_at_ms: u64,
+    pub query: String,
+    pub replacement: String,
+    #[serde(rename = "editCount")]
+    pub edit_count: usize,
+    /// Sorted by path.
+    pub notes: Vec<ReplacedNote>,
+}
+
+impl ReplaceChangeSet {
+    pub fn touched_note_paths(&self) -> Vec<String> {
+        self.notes.iter().map(|note| note.path.clone()).collect()
+    }
+}
+
+impl Vault {
+    /// Finds every match of `query` in the notes [`KnowledgeIndex::search`] returns and
+    /// previews replacing it with `replacement`.
+    ///
+    /// The search only picks the candidate notes. Outside regex mode the whole query is
+    /// then matched line by line as one literal, so `Knowledge Graph` replaces the phrase
+    /// and not each word. In regex mode `query` is the pattern and `replacement` may refer
+    /// to capture groups as `$1` or `${name}`; otherwise it is inserted as written.
+    pub fn preview_replace(
+        &self,
+        index: &KnowledgeIndex,
+        query: &str,
+        replacement: &str,
+        options: SearchOptions,
+    ) -> Result<ReplacePreview> {
+        let query = query.trim();
+        let pattern = if options.regex {
+            index.match_pattern(query, &options)?
+        } else {
+            literal_pattern(query, &options)
+        };
+        let Some(pattern) = pattern else {
+            return Ok(ReplacePreview {
+                query: query.to_string(),
+                replacement: replacement.to_string(),
+                notes: Vec::new(),
+            });
+        };
+
+        let regex_mode = options.regex;
+        let outcome = index.search(
+            self,
+            query,
+            SearchOptions {
+                max_files_with_matches: usize::MAX,
+                max_match_rows: usize::MAX,
+                max_preview_matches_per_file: 0,
+                max_matches_to_count_per_file: 0,
+                ..options
+            },
+        );
+        if let Some(err) = outcome.error {
+            return Err(err.into());
+        }
+
+        let mut paths = outcome
+            .hits
+            .into_iter()
+            .map(|hit| hit.path)
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let mut next_id = 0usize;
+        let mut notes = Vec::new();
+        for path in paths {
+            let content = self.read_note(&path)?;
+            let mut edits = Vec::new();
+            let mut line_start = 0usize;
+            for (line_ix, raw_line) in content.split_inclusive('\n').enumerate() {
+                let line = raw_line.trim_end_matches('\n').trim_end_matches('\r');
+                for caps in pattern.captures_iter(line) {
+                    let Some(m) = caps.get(0).filter(|m| !m.is_empty()) else {
+                        continue;
+                    };
+                    let mut expanded = String::new();
+                    if regex_mode {
+                        caps.expand(replacement, &mut expanded);
+                    } else {
+                        expanded.push_str(replacement);
+                    }
+                    edits.push(ReplaceEdit {
+                        id: next_id,
+                        line: line_ix + 1,
+                        range: line_start + m.start()..line_start + m.end(),
+                        matched: m.as_str().to_string(),
+                        replacement: expanded,
+                    });
+                    next_id += 1;
+                }
+                line_start += raw_line.len();
+            }
+            if !edits.is_empty() {
+                notes.push(NoteReplacePreview {
+                    path,
+                    content_hash: content_hash(content.as_bytes()),
+                    edits,
+                });
+            }
+        }
+
+        Ok(ReplacePreview {
+            query: query.to_string(),
+            replacement: replacement.to_string(),
+            notes,
+        })
+    }
+
+    /// Applies the `selected` edits of `preview`, all or none, and records the change set
+    /// under `.xnote/history/ops`.
+    ///
+    /// Each note is saved with [`NoteSaveSource::Replace`], so its history shows the
+    /// content before and after. A note changed since the preview fails with a
+    /// [`NoteWriteConflict`]; on any failure the notes already written get their previous
+    /// content back.
+    pub fn apply_replace(
+        &self,
+        preview: &ReplacePreview,
+        selected: &BTreeSet<usize>,
+    ) -> Result<ReplaceChangeSet> {
+        let planned = preview
+            .notes
+            .iter()
+            .filter_map(|note| {
+                let edits = note
+                    .edits
+                    .iter()
+                    .filter(|edit| selected.contains(&edit.id))
+                    .collect::<Vec<_>>();
+                (!edits.is_empty()).then_some((note, edits))
+            })
+            .collect::<Vec<_>>();
+        let notes = self.rewrite_notes_or_roll_back(
+            planned
+                .iter()
+                .map(|(note, edits)| (note.path.as_str(), (note, edits))),
+            NoteSaveSource::Replace,
+            |(note, edits), current| {
+                self.expect_content(&note.path, current, &note.content_hash)?;
+                let mut out = String::with_capacity(current.len());
+                let mut copied = 0usize;
+                for edit in edits {
+                    out.push_str(&current[copied..edit.range.start]);
+                    out.push_str(&edit.replacement);
+                    copied = edit.range.end;
+                }
+                out.push_str(&current[copied..]);
+                Ok(out)
+            },
+        )?;
+
+        let change_set = ReplaceChangeSet {
+            version: REPLACE_OP_VERSION_V1,
+            id: next_replace_op_id(),
+            applied_at_ms: now_epoch_ms(),
+            query: preview.query.clone(),
+            replacement: preview.replacement.clone(),
+            edit_count: planned.iter().map(|(_, edits)| edits.len()).sum(),
+            notes,
+        };
+        if let Err(err) = self.write_replace_op(&change_set) {
+            return Err(self.roll_back(&change_set.notes, err));
+        }
+        Ok(change_set)
+    }
+
+    /// Recorded replace change sets, newest first. Unreadable records are skipped.
+    pub fn list_replace_ops(&self) -> Result<Vec<ReplaceChangeSet>> {
+        let mut out = list_json_files(&self.replace_ops_dir())?
+            .iter()
+            .filter_map(|path| read_replace_op(path).ok())
+            .collect::<Vec<_>>();
+        out.sort_by(|a, b| {
+            b.applied_at_ms
+                .cmp(&a.applied_at_ms)
+                .then_with(|| b.id.cmp(&a.id))
+        });
+        Ok(out)
+    }
+
+    pub fn load_replace_op(&self, op_id: &str) -> Result<ReplaceChangeSet> {
+        read_replace_op(&self.replace_op_path(op_id)?)
+    }
+
+    /// Undoes a change set returned by [`Vault::apply_replace`], all or none.
+    ///
+    /// Fails with a [`NoteWriteConflict`] when a note was edited after the replace. The
+    /// recorded change set is kept; reverting it twice conflicts the same way.
+    pub fn revert_replace(&self, change_set: &ReplaceChangeSet) -> Result<()> {
+        self.rewrite_notes_or_roll_back(
+            change_set
+                .notes
+                .iter()
+                .map(|note| (note.path.as_str(), note)),
+            NoteSaveSource::Restore,
+            |note, current| {
+                self.expect_content(&note.path, current, &content_hash(note.after.as_bytes()))?;
+                Ok(note.before.clone())
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Rewrites each note in turn with `rewrite(item, current_content)`. If a read, rewrite
+    /// or write fails, the notes already written are put back before the error returns.
+    fn rewrite_notes_or_roll_back<'a, T>(
+        &self,
+        notes: impl IntoIterator<Item = (&'a str, T)>,
+        source: NoteSaveSource,
+        mut rewrite: impl FnMut(T, &str) -> Result<String>,
+    ) -> Result<Vec<ReplacedNote>> {
+        let mut written: Vec<ReplacedNote> = Vec::new();
+        for (path, item) in notes {
+            let step = self.read_note(path).and_then(|before| {
+                let after = rewrite(item, &before)?;
+                self.write_note_with_source(path, &after, source)?;
+                Ok(ReplacedNote {
+                    path: path.to_string(),
+                    before,
+                    after,
+                })
+            });
+            let Err(err) = step.map(|note| written.push(note)) else {
+                continue;
+            };
+            return Err(self.roll_back(&written, err));
+        }
+        Ok(written)
+    }
+
+    /// Puts back the previous content of `written` notes and adds the outcome to `err`.
+    ///
+    /// A note that no longer holds the content written to it was edited in the meantime
+    /// and is left alone.
+    fn roll_back(&self, written: &[ReplacedNote], err: anyhow::Error) -> anyhow::Error {
+        let mut changed = Vec::new();
+        let mut failed = Vec::new();
+        for note in written.iter().rev() {
+            let still_written = match self.note_disk_state(&note.path) {
+                Ok(state) => state
+                    .is_some_and(|state| state.content_hash == content_hash(note.after.as_bytes())),
+                Err(_) => {
+                    failed.push(note.path.as_str());
+                    continue;
+                }
+            };
+            if !still_written {
+                changed.push(note.path.as_str());
+            } else if self
+                .write_note_with_source(&note.path, &note.before, NoteSaveSource::Restore)
+                .is_err()
+            {
+                failed.push(note.path.as_str());
+            }
+        }
+
+        let mut outcome = format!(
+            "rolled back {} of {} written notes",
+            written.len() - changed.len() - failed.len(),
+            written.len()
+        );
+        if !changed.is_empty() {
+            outcome.push_str(&format!(
+                "; left edited notes alone: {}",
+                changed.join(", ")
+            ));
+        }
+        if !failed.is_empty() {
+            outcome.push_str(&format!("; rollback failed for: {}", failed.join(", ")));
+        }
+        err.context(outcome)
+    }
+
+    fn expect_content(&self, path: &str, content: &str, expected_hash: &str) -> Result<()> {
+        if content_hash(content.as_bytes()) == expected_hash {
+            return Ok(());
+        }
+        Err(anyhow!(NoteWriteConflict {
+            note_path: path.to_string(),
+            expected: NoteWriteExpectation::ContentHash(expected_hash.to_string()),
+            actual: self.note_disk_state(path)?,
+        }))
+    }
+
+    fn replace_ops_dir(&self) -> PathBuf {
+        self.history_dir().join(HISTORY_OPS_DIR)
+    }
+
+    fn replace_op_path(&self, op_id: &str) -> Result<PathBuf> {
+        let id = op_id.trim();
+        if id.is_empty()
+            || id
+                .chars()
+                .any(|ch| !(ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'))
+        {
+            return Err(anyhow!("invalid replace op id: {op_id}"));
+        }
+        Ok(self.replace_ops_dir().join(format!("{id}.json")))
+    }
+
+    fn write_replace_op(&self, change_set: &ReplaceChangeSet) -> Result<()> {
+        let path = self.replace_op_path(&change_set.id)?;
+        let mut content = serde_json::to_string_pretty(change_set)?;
+        content.push('\n');
+        write_atomic(&path, content.as_bytes())
+            .with_context(|| format!("write replace op: {:?}", path))?;
+        Ok(())
+    }
+}
+
+fn read_replace_op(path: &Path) -> Result<ReplaceChangeSet> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("read replace op: {:?}", path))?;
+    let change_set: ReplaceChangeSet =
+        serde_json::from_str(&content).with_context(|| format!("parse replace op: {:?}", path))?;
+    if change_set.version != REPLACE_OP_VERSION_V1 {
+        bail!("unsupported replace op version: {}", change_set.version);
+    }
+    Ok(change_set)
+}
+
+fn next_replace_op_id() -> String {
+    let seq = REPLACE_OP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!(
+        "R{:013}-{:05X}-{seq:04X}",
+        now_epoch_ms(),
+        std::process::id()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::test_vault;
+    use std::fs;
+
+    fn setup(name: &str) -> (PathBuf, Vault) {
+        let (temp_dir, vault) = test_vault(&format!("vault_replace_{name}"), &["notes"]);
+        fs::write(
+            temp_dir.join("notes/A.md"),
+            "# Crabs\nFerris the crab\r\nferris and ferris\n",
+        )
+        .expect("write A");
+        fs::write(temp_dir.join("notes/B.md"), "Ferris draft here\n").expect("write B");
+        fs::write(temp_dir.join("notes/C.md"), "nothing to see\n").expect("write C");
+        (temp_dir, vault)
+    }
+
+    #[test]
+    fn previews_applies_selected_edits_and_reverts() {
+        let (temp_dir, vault) = setup("apply");
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("index");
+
+        let preview = vault
+            .preview_replace(&index, "ferris", "Corro", SearchOptions::default())
+            .expect("preview");
+        assert_eq!(preview.edit_count(), 4);
+        let a = &preview.notes[0];
+        assert_eq!(a.path, "notes/A.md");
+        assert_eq!(
+            a.edits
+                .iter()
+                .map(|edit| (edit.line, edit.range.clone(), edit.matched.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (2, 8..14, "Ferris"),
+                (3, 25..31, "ferris"),
+                (3, 36..42, "ferris")
+            ]
+        );
+        assert_eq!(preview.notes[1].path, "notes/B.md");
+
+        let mut selected = preview.edit_ids();
+        selected.remove(&a.edits[1].id);
+        let change_set = vault.apply_replace(&preview, &selected).expect("apply");
+        assert_eq!(change_set.edit_count, 3);
+        assert_eq!(
+            change_set.touched_note_paths(),
+            vec!["notes/A.md", "notes/B.md"]
+        );
+        assert_eq!(
+            vault.read_note("notes/A.md").expect("read A"),
+            "# Crabs\nCorro the crab\r\nferris and Corro\n"
+        );
+        assert_eq!(
+            vault.read_note("notes/B.md").expect("read B"),
+            "Corro draft here\n"
+        );
+        let versions = vault.list_note_versions("notes/B.md").expect("versions");
+        assert_eq!(versions[0].source, NoteSaveSource::Replace);
+
+        let ops = vault.list_replace_ops().expect("list ops");
+        assert_eq!(ops, vec![change_set.clone()]);
+        let recorded = vault.load_replace_op(&change_set.id).expect("load op");
+        assert_eq!(recorded, change_set);
+        assert!(vault.load_replace_op("../escape").is_err());
+
+        vault.revert_replace(&recorded).expect("revert");
+        assert_eq!(
+            vault.read_note("notes/A.md").expect("read A"),
+            "# Crabs\nFerris the crab\r\nferris and ferris\n"
+        );
+        assert_eq!(
+            vault.read_note("notes/B.md").expect("read B"),
+            "Ferris draft here\n"
+        );
+
+        let regex = vault
+            .preview_replace(
+                &index,
+                r"(\w+) draft",
+                "$1-final",
+                SearchOptions {
+                    regex: true,
+                    ..SearchOptions::default()
+                },
+            )
+            .expect("regex preview");
+        assert_eq!(regex.edit_count(), 1);
+        assert_eq!(regex.notes[0].edits[0].replacement, "Ferris-final");
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn multi_word_query_replaces_the_phrase_only() {
+        let (temp_dir, vault) = setup("phrase");
+        fs::write(
+            temp_dir.join("notes/D.md"),
+            "A Knowledge Graph links notes.\nKnowledge is power. Graph theory.\nknowledge graphs\n",
+        )
+        .expect("write D");
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("index");
+
+        let edit_count = |query: &str, case_sensitive: bool, whole_word: bool| {
+            let options = SearchOptions {
+                case_sensitive,
+                whole_word,
+                ..SearchOptions::default()
+            };
+            vault
+                .preview_replace(&index, query, "KG", options)
+                .expect("preview")
+                .edit_count()
+        };
+        assert_eq!(edit_count("knowledge graph", true, false), 1);
+        assert_eq!(edit_count("knowledge graph", true, true), 0);
+
+        let preview = vault
+            .preview_replace(&index, "Knowledge Graph", "KG", SearchOptions::default())
+            .expect("preview");
+        assert_eq!(preview.edit_count(), 2);
+        vault
+            .apply_replace(&preview, &preview.edit_ids())
+            .expect("apply");
+        assert_eq!(
+            vault.read_note("notes/D.md").expect("read D"),
+            "A KG links notes.\nKnowledge is power. Graph theory.\nKGs\n"
+        );
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn apply_rolls_back_when_a_note_changed_since_preview() {
+        let (temp_dir, vault) = setup("rollback");
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("index");
+        let preview = vault
+            .preview_replace(&index, "ferris", "Corro", SearchOptions::default())
+            .expect("preview");
+
+        fs::write(temp_dir.join("notes/B.md"), "Ferris, edited elsewhere\n").expect("edit B");
+        let err = vault
+            .apply_replace(&preview, &preview.edit_ids())
+            .expect_err("conflict");
+        let conflict = err
+            .downcast_ref::<NoteWriteConflict>()
+            .expect("typed conflict");
+        assert_eq!(conflict.note_path, "notes/B.md");
+        assert_eq!(
+            vault.read_note("notes/A.md").expect("read A"),
+            "# Crabs\nFerris the crab\r\nferris and ferris\n"
+        );
+        assert_eq!(
+            vault.read_note("notes/B.md").expect("read B"),
+            "Ferris, edited elsewhere\n"
+        );
+        assert!(vault.list_replace_ops().expect("list ops").is_empty());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn apply_rolls_back_when_the_change_set_cannot_be_recorded() {
+        let (temp_dir, vault) = setup("record");
+        let index = KnowledgeIndex::rebuild_from_vault(&vault).expect("index");
+        let preview = vault
+            .preview_replace(&index, "ferris", "Corro", SearchOptions::default())
+            .expect("preview");
+
+        fs::create_dir_all(vault.history_dir()).expect("create history dir");
+        fs::write(vault.replace_ops_dir(), "not a dir").expect("block ops dir");
+        vault
+            .apply_replace(&preview, &preview.edit_ids())
+            .expect_err("ops dir is a file");
+        assert_eq!(
+            vault.read_note("notes/A.md").expect("read A"),
+            "# Crabs\nFerris the crab\r\nferris and ferris\n"
+        );
+        assert_eq!(
+            vault.read_note("notes/B.md").expect("read B"),
+            "Ferris draft here\n"
+        );
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn roll_back_leaves_notes_edited_since_the_write_alone() {
+        let (temp_dir, vault) = setup("edited");
+        let written = ["notes/A.md", "notes/B.md"]
+            .into_iter()
+            .map(|path| {
+                let before = vault.read_note(path).expect("read before");
+                let after = before.replace("Ferris", "Corro");
+                vault.write_note(path, &after).expect("write after");
+                ReplacedNote {
+                    path: path.to_string(),
+                    before,
+                    after,
+                }
+            })
+            .collect::<Vec<_>>();
+        fs::write(temp_dir.join("notes/B.md"), "Corro, edited elsewhere\n").expect("edit B");
+
+        let err = vault.roll_back(&written, anyhow!("write failed"));
+        assert_eq!(
+            err.to_string(),
+            "rolled back 1 of 2 written notes; left edited notes alone: notes/B.md"
+        );
+        assert_eq!(
+            vault.read_note("notes/A.md").expect("read A"),
+            "# Crabs\nFerris the crab\r\nferris and ferris\n"
+        );
+        assert_eq!(
+            vault.read_note("notes/B.md").expect("read B"),
+            "Corro, edited elsewhere\n"
+        );
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
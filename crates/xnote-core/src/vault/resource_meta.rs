@@ -0,0 +1,304 @@
+//! Resource meta records under `.xnote/meta/resources/<id>.json` and resolution of
+//! `kind: resource` relation targets.
+
+use super::Vault;
+use crate::atomic_write::write_atomic;
+use crate::note_meta::NoteMetaTarget;
+use crate::paths::{join_inside, normalize_vault_rel_path};
+use crate::resource_meta::{normalize_resource_id, ResourceAnnotation, ResourceMetaV1};
+use anyhow::{anyhow, Context as _, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const RESOURCE_META_DIR: &str = "resources";
+
+/// A resource relation target resolved to a file in the vault.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedResource {
+    /// Vault-relative POSIX path of the resource file.
+    pub path: String,
+    pub meta: Option<ResourceMetaV1>,
+    /// The annotation named by the target's anchor, when it exists.
+    pub annotation: Option<ResourceAnnotation>,
+}
+
+/// Resource meta and the attachment index, loaded once to resolve many ids.
+pub(super) struct ResourceLookup {
+    by_id: BTreeMap<String, ResourceMetaV1>,
+    id_by_path: BTreeMap<String, String>,
+    blob_path_by_hash: BTreeMap<String, String>,
+}
+
+impl ResourceLookup {
+    pub(super) fn load(vault: &Vault) -> Result<Self> {
+        let mut by_id = BTreeMap::new();
+        let mut id_by_path = BTreeMap::new();
+        for meta in vault.list_resource_metas()? {
+            id_by_path.insert(meta.path.clone(), meta.id.clone());
+            by_id.insert(meta.id.clone(), meta);
+        }
+        let blob_path_by_hash = vault
+            .load_attachment_index()?
+            .blobs
+            .into_values()
+            .map(|blob| (blob.hash, blob.path))
+            .collect();
+        Ok(Self {
+            by_id,
+            id_by_path,
+            blob_path_by_hash,
+        })
+    }
+
+    pub(super) fn metas(&self) -> impl Iterator<Item = &ResourceMetaV1> {
+        self.by_id.values()
+    }
+
+    /// Resolves a resource meta id, an attachment hash or a vault path to an existing resource.
+    pub(super) fn resolve(&self, vault: &Vault, id: &str) -> Option<ResolvedResource> {
+        let id = id.trim();
+        if let Some(meta) = self.by_id.get(id) {
+            return Some(ResolvedResource {
+                path: meta.path.clone(),
+                meta: Some(meta.clone()),
+                annotation: None,
+            });
+        }
+
+        let path = match self.blob_path_by_hash.get(id) {
+            Some(path) => path.clone(),
+            None => {
+                let path = normalize_vault_rel_path(id).ok()?;
+                if !join_inside(&vault.root, &path).ok()?.is_file() {
+                    return None;
+                }
+                path
+            }
+        };
+        let meta = self
+            .id_by_path
+            .get(&path)
+            .and_then(|meta_id| self.by_id.get(meta_id))
+            .cloned();
+        Some(ResolvedResource {
+            path,
+            meta,
+            annotation: None,
+        })
+    }
+}
+
+impl Vault {
+    pub fn resource_meta_dir(&self) -> PathBuf {
+        self.root
+            .join(".xnote")
+            .join("meta")
+            .join(RESOURCE_META_DIR)
+    }
+
+    pub fn resource_meta_file_path(&self, resource_id: &str) -> Result<PathBuf> {
+        let resource_id = normalize_resource_id(resource_id)?;
+        Ok(self.resource_meta_dir().join(format!("{resource_id}.json")))
+    }
+
+    pub fn load_resource_meta(&self, resource_id: &str) -> Result<Option<ResourceMetaV1>> {
+        let path = self.resource_meta_file_path(resource_id)?;
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read resource meta file: {:?}", path));
+            }
+        };
+
+        let meta: ResourceMetaV1 = serde_json::from_str(&content)
+            .with_context(|| format!("parse resource meta json: {:?}", path))?;
+        meta.validate()
+            .with_context(|| format!("validate resource meta file: {:?}", path))?;
+        Ok(Some(meta))
+    }
+
+    pub fn save_resource_meta(&self, resource_meta: &ResourceMetaV1) -> Result<()> {
+        resource_meta.validate()?;
+        let path = self.resource_meta_file_path(&resource_meta.id)?;
+        let content = resource_meta.canonical_json()?;
+        write_atomic(&path, content.as_bytes())
+            .with_context(|| format!("write resource meta file: {:?}", path))?;
+        Ok(())
+    }
+
+    pub fn delete_resource_meta(&self, resource_id: &str) -> Result<()> {
+        let path = self.resource_meta_file_path(resource_id)?;
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).with_context(|| format!("delete resource meta file: {:?}", path)),
+        }
+    }
+
+    /// Every valid resource meta record, sorted by id.
+    ///
+    /// Files that fail to parse or validate are skipped; [`Vault::check`] reports them.
+    pub fn list_resource_metas(&self) -> Result<Vec<ResourceMetaV1>> {
+        let dir = self.resource_meta_dir();
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read resource meta dir: {:?}", dir))
+            }
+        };
+
+        let mut out = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Some(resource_id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if let Ok(Some(meta)) = self.load_resource_meta(resource_id) {
+                out.push(meta);
+            }
+        }
+        out.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(out)
+    }
+
+    pub fn find_resource_meta_by_path(&self, path: &str) -> Result<Option<ResourceMetaV1>> {
+        let path = normalize_vault_rel_path(path)?;
+        Ok(self
+            .list_resource_metas()?
+            .into_iter()
+            .find(|meta| meta.path == path))
+    }
+
+    /// Resolves a resource id as used in relations and pins: a resource meta id, an
+    /// attachment hash or a vault path of an existing file.
+    pub fn resolve_resource(&self, resource_id: &str) -> Result<Option<ResolvedResource>> {
+        Ok(ResourceLookup::load(self)?.resolve(self, resource_id))
+    }
+
+    /// Follows a `kind: resource` relation target, including its annotation anchor.
+    pub fn resolve_resource_target(
+        &self,
+        target: &NoteMetaTarget,
+    ) -> Result<Option<ResolvedResource>> {
+        if target.kind.trim() != "resource" {
+            return Err(anyhow!(
+                "relation target kind is not resource: {}",
+                target.kind
+            ));
+        }
+        let Some(mut resolved) = self.resolve_resource(&target.id)? else {
+            return Ok(None);
+        };
+        if let (Some(anchor), Some(meta)) = (target.anchor.as_deref(), resolved.meta.as_ref()) {
+            resolved.annotation = meta.annotation(anchor).cloned();
+        }
+        Ok(Some(resolved))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::test_vault;
+    use serde_json::Map;
+    use std::fs;
+
+    #[test]
+    fn resource_meta_roundtrip_list_and_delete() {
+        let (temp_dir, vault) = test_vault("vault_resource_meta_roundtrip", &["attachments"]);
+        let mut meta = ResourceMetaV1::new("R2", "attachments/b.png").expect("meta");
+        meta.tags.push("scan".to_string());
+        vault.save_resource_meta(&meta).expect("save");
+        vault
+            .save_resource_meta(&ResourceMetaV1::new("R1", "attachments/a.png").expect("meta"))
+            .expect("save");
+        fs::write(vault.resource_meta_dir().join("broken.json"), "{").expect("broken");
+
+        assert!(vault
+            .resource_meta_file_path("R2")
+            .expect("path")
+            .ends_with(".xnote/meta/resources/R2.json"));
+        assert_eq!(vault.load_resource_meta("R2").expect("load"), Some(meta));
+        let ids = vault
+            .list_resource_metas()
+            .expect("list")
+            .into_iter()
+            .map(|m| m.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["R1", "R2"]);
+        assert_eq!(
+            vault
+                .find_resource_meta_by_path("./attachments/b.png")
+                .expect("find")
+                .map(|m| m.id),
+            Some("R2".to_string())
+        );
+
+        vault.delete_resource_meta("R2").expect("delete");
+        vault.delete_resource_meta("R2").expect("delete twice");
+        assert!(vault.load_resource_meta("R2").expect("load").is_none());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn resource_targets_resolve_by_meta_id_hash_and_path() {
+        let (temp_dir, vault) = test_vault("vault_resource_meta_resolve", &["attachments"]);
+        let imported = vault
+            .import_attachment_bytes("shot.png", b"png")
+            .expect("import");
+        let mut meta = ResourceMetaV1::new("R1", &imported.blob.path).expect("meta");
+        meta.annotations.push(ResourceAnnotation {
+            id: "a1".to_string(),
+            text: "legend".to_string(),
+            region: None,
+            page: None,
+            time_ms: None,
+            created_at: None,
+            extra: Map::new(),
+        });
+        vault.save_resource_meta(&meta).expect("save");
+        fs::write(temp_dir.join("attachments/plain.pdf"), b"%PDF").expect("pdf");
+
+        let by_id = vault.resolve_resource("R1").expect("resolve").expect("id");
+        assert_eq!(by_id.path, imported.blob.path);
+        let by_hash = vault
+            .resolve_resource(&imported.blob.hash)
+            .expect("resolve")
+            .expect("hash");
+        assert_eq!(by_hash.meta.map(|m| m.id), Some("R1".to_string()));
+        let by_path = vault
+            .resolve_resource("attachments/plain.pdf")
+            .expect("resolve")
+            .expect("path");
+        assert!(by_path.meta.is_none());
+        assert!(vault.resolve_resource("R404").expect("resolve").is_none());
+
+        let target = NoteMetaTarget {
+            kind: "resource".to_string(),
+            id: "R1".to_string(),
+            anchor: Some("a1".to_string()),
+            extra: Map::new(),
+        };
+        let followed = vault
+            .resolve_resource_target(&target)
+            .expect("follow")
+            .expect("resolved");
+        assert_eq!(
+            followed.annotation.map(|a| a.text),
+            Some("legend".to_string())
+        );
+        let knowledge = NoteMetaTarget {
+            kind: "knowledge".to_string(),
+            ..target
+        };
+        assert!(vault.resolve_resource_target(&knowledge).is_err());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
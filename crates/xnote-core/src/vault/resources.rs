@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceMediaKind {
+    Image,
+    Pdf,
+    Audio,
+    Video,
+    Archive,
+    Other,
+}
+
+impl ResourceMediaKind {
+    /// Media kind from a file extension, compared case-insensitively and without the dot.
+    pub fn from_extension(extension: &str) -> Self {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "svg" | "tif" | "tiff" | "ico"
+            | "heic" | "heif" | "avif" => Self::Image,
+            "pdf" => Self::Pdf,
+            "mp3" | "wav" | "ogg" | "oga" | "flac" | "m4a" | "aac" | "opus" | "wma" => Self::Audio,
+            "mp4" | "m4v" | "mov" | "webm" | "mkv" | "avi" | "wmv" | "ogv" => Self::Video,
+            "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" | "rar" | "zst" => Self::Archive,
+            _ => Self::Other,
+        }
+    }
+
+    pub const fn as_tag(self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Pdf => "pdf",
+            Self::Audio => "audio",
+            Self::Video => "video",
+            Self::Archive => "archive",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// A non-markdown file in the vault, typically under `attachments/`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceEntry {
+    /// Vault-relative POSIX path, e.g. `attachments/diagram.png`
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// Lowercase extension without the dot; empty when the file has none.
+    pub extension: String,
+    pub media_kind: ResourceMediaKind,
+}
+
+impl ResourceEntry {
+    pub(super) fn from_metadata(rel_posix: String, metadata: Option<std::fs::Metadata>) -> Self {
+        let extension = resource_extension(&rel_posix);
+        Self {
+            media_kind: ResourceMediaKind::from_extension(&extension),
+            size: metadata.as_ref().map(|m| m.len()).unwrap_or_default(),
+            modified: metadata.and_then(|m| m.modified().ok()),
+            extension,
+            path: rel_posix,
+        }
+    }
+
+    pub fn is_attachment(&self) -> bool {
+        self.path.starts_with("attachments/")
+    }
+}
+
+fn resource_extension(rel_posix: &str) -> String {
+    Path::new(rel_posix)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::Vault;
+    use std::fs;
+
+    #[test]
+    fn media_kind_is_detected_from_extension() {
+        assert_eq!(
+            ResourceMediaKind::from_extension("PNG"),
+            ResourceMediaKind::Image
+        );
+        assert_eq!(
+            ResourceMediaKind::from_extension("pdf"),
+            ResourceMediaKind::Pdf
+        );
+        assert_eq!(
+            ResourceMediaKind::from_extension("flac"),
+            ResourceMediaKind::Audio
+        );
+        assert_eq!(
+            ResourceMediaKind::from_extension("webm"),
+            ResourceMediaKind::Video
+        );
+        assert_eq!(
+            ResourceMediaKind::from_extension("7z"),
+            ResourceMediaKind::Archive
+        );
+        assert_eq!(
+            ResourceMediaKind::from_extension(""),
+            ResourceMediaKind::Other
+        );
+    }
+
+    #[test]
+    fn scan_with_resources_lists_non_markdown_files() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_vault_resources_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("attachments/img")).expect("create attachments");
+        fs::create_dir_all(temp_dir.join("notes")).expect("create notes");
+        fs::create_dir_all(temp_dir.join(".xnote/meta")).expect("create meta");
+        fs::write(temp_dir.join("notes/A.md"), "# A").expect("note");
+        fs::write(temp_dir.join("attachments/img/Shot.PNG"), [0u8; 12]).expect("png");
+        fs::write(temp_dir.join("attachments/paper.pdf"), b"%PDF").expect("pdf");
+        fs::write(temp_dir.join("attachments/.DS_Store"), b"x").expect("hidden");
+        fs::write(temp_dir.join(".xnote/meta/N1.json"), b"{}").expect("meta");
+
+        let vault = Vault::open(&temp_dir).expect("open vault");
+        let scan = vault.fast_scan_with_resources().expect("scan");
+        assert_eq!(scan.notes.len(), 1);
+
+        let resources = scan
+            .resources
+            .iter()
+            .map(|r| (r.path.as_str(), r.size, r.extension.as_str(), r.media_kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            resources,
+            vec![
+                (
+                    "attachments/img/Shot.PNG",
+                    12,
+                    "png",
+                    ResourceMediaKind::Image
+                ),
+                ("attachments/paper.pdf", 4, "pdf", ResourceMediaKind::Pdf),
+            ]
+        );
+        assert!(scan.resources.iter().all(|r| r.is_attachment()));
+        assert!(scan.resources.iter().all(|r| r.modified.is_some()));
+        assert!(vault
+            .fast_scan_notes_and_folders()
+            .expect("scan")
+            .resources
+            .is_empty());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -0,0 +1,409 @@
+use super::{now_epoch_ms, Vault};
+use crate::atomic_write::write_atomic;
+use crate::note_meta::extract_note_id_from_frontmatter;
+use crate::paths::{
+    join_inside, normalize_folder_rel_path, normalize_vault_rel_path, to_posix_path,
+};
+use anyhow::{anyhow, bail, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+pub const TRASH_MANIFEST_VERSION_V1: u32 = 1;
+const TRASH_MANIFEST_FILE: &str = "manifest.json";
+const TRASH_PAYLOAD_DIR: &str = "payload";
+const TRASH_META_DIR: &str = "meta";
+static TRASH_ENTRY_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashEntryKind {
+    Note,
+    Folder,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrashManifest {
+    pub version: u32,
+    pub id: String,
+    pub kind: TrashEntryKind,
+    #[serde(rename = "originalPath")]
+    pub original_path: String,
+    #[serde(rename = "deletedAtMs")]
+    pub deleted_at_ms: u64,
+    #[serde(rename = "metaIds", default)]
+    pub meta_ids: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrashRestoreOutcome {
+    pub entry_id: String,
+    /// Vault-relative path the entry was restored to; differs from the original on collision.
+    pub restored_path: String,
+    pub restored_meta_ids: Vec<String>,
+    /// Meta files left in the trash because a live meta file with the same id exists.
+    pub skipped_meta_ids: Vec<String>,
+}
+
+impl Vault {
+    pub fn trash_dir(&self) -> PathBuf {
+        self.root.join(".xnote").join("trash")
+    }
+
+    /// Moves a note and its `.xnote/meta/<id>.json` into the trash.
+    pub fn trash_note(&self, note_path: &str) -> Result<TrashManifest> {
+        let rel = normalize_vault_rel_path(note_path)?;
+        let full = join_inside(&self.root, &rel)?;
+        if !full.is_file() {
+            bail!("note does not exist: {rel}");
+        }
+        let meta_ids = note_id_of_file(&full).into_iter().collect();
+        self.move_into_trash(TrashEntryKind::Note, rel, &full, meta_ids)
+    }
+
+    /// Moves a folder, every note inside it and their meta files into the trash.
+    pub fn trash_folder(&self, folder: &str) -> Result<TrashManifest> {
+        let rel = normalize_folder_rel_path(folder)?;
+        let full = join_inside(&self.root, &rel)?;
+        if !full.is_dir() {
+            bail!("folder does not exist: {rel}");
+        }
+        let mut meta_ids = Vec::new();
+        collect_note_ids(&full, &mut meta_ids)?;
+        meta_ids.sort();
+        meta_ids.dedup();
+        self.move_into_trash(TrashEntryKind::Folder, rel, &full, meta_ids)
+    }
+
+    /// Lists trash entries, newest first. Entries with unreadable manifests are skipped.
+    pub fn list_trash(&self) -> Result<Vec<TrashManifest>> {
+        let trash_dir = self.trash_dir();
+        let entries = match std::fs::read_dir(&trash_dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read trash dir: {:?}", trash_dir))
+            }
+        };
+
+        let mut out = Vec::new();
+        for entry in entries.flatten() {
+            if let Ok(manifest) = read_manifest(&entry.path()) {
+                out.push(manifest);
+            }
+        }
+        out.sort_by(|a, b| {
+            b.deleted_at_ms
+                .cmp(&a.deleted_at_ms)
+                .then_with(|| b.id.cmp(&a.id))
+        });
+        Ok(out)
+    }
+
+    /// Restores a trash entry, picking a `name (restored N)` path when the original is taken.
+    pub fn restore_from_trash(&self, entry_id: &str) -> Result<TrashRestoreOutcome> {
+        let entry_dir = self.trash_entry_dir(entry_id)?;
+        let manifest = read_manifest(&entry_dir)?;
+        let payload = entry_dir
+            .join(TRASH_PAYLOAD_DIR)
+            .join(payload_name(&manifest.original_path));
+        if !payload.exists() {
+            bail!("trash entry payload is missing: {entry_id}");
+        }
+
+        let restored_path = self.free_restore_path(&manifest)?;
+        let target = join_inside(&self.root, &restored_path)?;
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).with_context(|| "create restore parent dir")?;
+        }
+        std::fs::rename(&payload, &target)
+            .with_context(|| format!("restore trash entry to: {restored_path}"))?;
+
+        let mut restored_meta_ids = Vec::new();
+        let mut skipped_meta_ids = Vec::new();
+        for note_id in &manifest.meta_ids {
+            let trashed = entry_dir
+                .join(TRASH_META_DIR)
+                .join(format!("{note_id}.json"));
+            if !trashed.is_file() {
+                continue;
+            }
+            let live = self.note_meta_file_path(note_id)?;
+            if live.exists() {
+                skipped_meta_ids.push(note_id.clone());
+                continue;
+            }
+            if let Some(parent) = live.parent() {
+                std::fs::create_dir_all(parent).with_context(|| "create note meta dir")?;
+            }
+            std::fs::rename(&trashed, &live)
+                .with_context(|| format!("restore note meta: {note_id}"))?;
+            restored_meta_ids.push(note_id.clone());
+        }
+
+        if skipped_meta_ids.is_empty() {
+            remove_dir_all_if_exists(&entry_dir)?;
+        } else {
+            // Keep the entry so the shadowed meta files are not lost.
+            let mut remaining = manifest.clone();
+            remaining.meta_ids = skipped_meta_ids.clone();
+            write_manifest(&entry_dir, &remaining)?;
+        }
+
+        Ok(TrashRestoreOutcome {
+            entry_id: manifest.id,
+            restored_path,
+            restored_meta_ids,
+            skipped_meta_ids,
+        })
+    }
+
+    /// Permanently deletes entries deleted more than `max_age` ago; returns purged ids.
+    pub fn purge_trash_older_than(&self, max_age: Duration) -> Result<Vec<String>> {
+        let cutoff = now_epoch_ms().saturating_sub(max_age.as_millis() as u64);
+        let mut purged = Vec::new();
+        for manifest in self.list_trash()? {
+            if manifest.deleted_at_ms > cutoff {
+                continue;
+            }
+            remove_dir_all_if_exists(&self.trash_entry_dir(&manifest.id)?)?;
+            purged.push(manifest.id);
+        }
+        Ok(purged)
+    }
+
+    /// Permanently deletes everything in the trash.
+    pub fn empty_trash(&self) -> Result<()> {
+        remove_dir_all_if_exists(&self.trash_dir())
+    }
+
+    fn move_into_trash(
+        &self,
+        kind: TrashEntryKind,
+        original_path: String,
+        full: &Path,
+        meta_ids: Vec<String>,
+    ) -> Result<TrashManifest> {
+        let id = next_trash_entry_id();
+        let entry_dir = self.trash_dir().join(&id);
+        let payload_dir = entry_dir.join(TRASH_PAYLOAD_DIR);
+        std::fs::create_dir_all(&payload_dir)
+            .with_context(|| format!("create trash entry dir: {:?}", payload_dir))?;
+
+        let manifest = TrashManifest {
+            version: TRASH_MANIFEST_VERSION_V1,
+            id,
+            kind,
+            original_path,
+            deleted_at_ms: now_epoch_ms(),
+            meta_ids,
+        };
+        write_manifest(&entry_dir, &manifest)?;
+
+        std::fs::rename(
+            full,
+            payload_dir.join(payload_name(&manifest.original_path)),
+        )
+        .with_context(|| format!("move into trash: {}", manifest.original_path))?;
+
+        for note_id in &manifest.meta_ids {
+            let live = self.note_meta_file_path(note_id)?;
+            if !live.is_file() {
+                continue;
+            }
+            let meta_dir = entry_dir.join(TRASH_META_DIR);
+            std::fs::create_dir_all(&meta_dir).with_context(|| "create trash meta dir")?;
+            std::fs::rename(&live, meta_dir.join(format!("{note_id}.json")))
+                .with_context(|| format!("move note meta into trash: {note_id}"))?;
+        }
+
+        Ok(manifest)
+    }
+
+    fn trash_entry_dir(&self, entry_id: &str) -> Result<PathBuf> {
+        let id = entry_id.trim();
+        if id.is_empty()
+            || id
+                .chars()
+                .any(|ch| !(ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'))
+        {
+            return Err(anyhow!("invalid trash entry id: {entry_id}"));
+        }
+        Ok(self.trash_dir().join(id))
+    }
+
+    fn free_restore_path(&self, manifest: &TrashManifest) -> Result<String> {
+        let original = &manifest.original_path;
+        if !join_inside(&self.root, original)?.exists() {
+            return Ok(original.clone());
+        }
+
+        let (stem, ext) = match manifest.kind {
+            TrashEntryKind::Note => match original.rsplit_once('.') {
+                Some((stem, ext)) if !ext.contains('/') => (stem, format!(".{ext}")),
+                _ => (original.as_str(), String::new()),
+            },
+            TrashEntryKind::Folder => (original.as_str(), String::new()),
+        };
+        for attempt in 1..=999usize {
+            let candidate = if attempt == 1 {
+                format!("{stem} (restored){ext}")
+            } else {
+                format!("{stem} (restored {attempt}){ext}")
+            };
+            if !join_inside(&self.root, &candidate)?.exists() {
+                return Ok(candidate);
+            }
+        }
+        bail!("no free restore path for: {original}")
+    }
+}
+
+fn payload_name(original_path: &str) -> &str {
+    original_path
+        .rsplit_once('/')
+        .map(|(_, name)| name)
+        .unwrap_or(original_path)
+}
+
+fn read_manifest(entry_dir: &Path) -> Result<TrashManifest> {
+    let path = entry_dir.join(TRASH_MANIFEST_FILE);
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("read trash manifest: {:?}", path))?;
+    let manifest: TrashManifest = serde_json::from_str(&content)
+        .with_context(|| format!("parse trash manifest: {:?}", path))?;
+    if manifest.version != TRASH_MANIFEST_VERSION_V1 {
+        bail!("unsupported trash manifest version: {}", manifest.version);
+    }
+    Ok(manifest)
+}
+
+fn write_manifest(entry_dir: &Path, manifest: &TrashManifest) -> Result<()> {
+    let mut content = serde_json::to_string_pretty(manifest)?;
+    content.push('\n');
+    write_atomic(&entry_dir.join(TRASH_MANIFEST_FILE), content.as_bytes())?;
+    Ok(())
+}
+
+fn note_id_of_file(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    extract_note_id_from_frontmatter(&content)
+}
+
+fn collect_note_ids(dir: &Path, out: &mut Vec<String>) -> Result<()> {
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("read folder for trash: {:?}", dir))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_note_ids(&path, out)?;
+            continue;
+        }
+        let is_note = to_posix_path(&path)
+            .map(|p| p.to_lowercase().ends_with(".md"))
+            .unwrap_or(false);
+        if is_note {
+            out.extend(note_id_of_file(&path));
+        }
+    }
+    Ok(())
+}
+
+fn remove_dir_all_if_exists(path: &Path) -> Result<()> {
+    match std::fs::remove_dir_all(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("remove dir: {:?}", path)),
+    }
+}
+
+fn next_trash_entry_id() -> String {
+    let seq = TRASH_ENTRY_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!(
+        "T{:013}-{:05X}-{seq:04X}",
+        now_epoch_ms(),
+        std::process::id()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_meta::NoteMetaV1;
+    use crate::vault::test_vault;
+    use std::fs;
+
+    #[test]
+    fn trash_note_moves_meta_and_restore_handles_collision() {
+        let (temp_dir, vault) = test_vault("vault_trash_note", &[]);
+        vault
+            .ensure_knowledge_structure()
+            .expect("ensure structure");
+        vault
+            .write_note("notes/A.md", "---\nid: 01HTRASHA\n---\n# A\n")
+            .expect("write note");
+        vault
+            .save_note_meta(&NoteMetaV1::new("01HTRASHA").expect("meta"))
+            .expect("save meta");
+
+        let manifest = vault.trash_note("notes/A.md").expect("trash note");
+        assert_eq!(manifest.original_path, "notes/A.md");
+        assert_eq!(manifest.meta_ids, vec!["01HTRASHA"]);
+        assert!(!temp_dir.join("notes/A.md").exists());
+        assert!(vault.load_note_meta("01HTRASHA").expect("load").is_none());
+
+        let scan = vault.fast_scan_notes().expect("scan");
+        assert!(scan.is_empty(), "trash contents must not be scanned");
+
+        vault
+            .write_note("notes/A.md", "# replacement\n")
+            .expect("write replacement");
+        let listed = vault.list_trash().expect("list");
+        assert_eq!(listed.len(), 1);
+
+        let restored = vault.restore_from_trash(&manifest.id).expect("restore");
+        assert_eq!(restored.restored_path, "notes/A (restored).md");
+        assert_eq!(restored.restored_meta_ids, vec!["01HTRASHA"]);
+        assert!(vault.load_note_meta("01HTRASHA").expect("load").is_some());
+        assert!(vault.list_trash().expect("list").is_empty());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn trash_folder_then_purge_and_empty() {
+        let (temp_dir, vault) = test_vault("vault_trash_folder", &[]);
+        vault
+            .ensure_knowledge_structure()
+            .expect("ensure structure");
+        vault
+            .write_note("notes/sub/B.md", "---\nid: 01HTRASHB\n---\n# B\n")
+            .expect("write note");
+        vault
+            .write_note("notes/keep.md", "# keep\n")
+            .expect("write note");
+
+        let folder = vault.trash_folder("notes/sub").expect("trash folder");
+        assert_eq!(folder.kind, TrashEntryKind::Folder);
+        assert_eq!(folder.meta_ids, vec!["01HTRASHB"]);
+        let scan = vault.fast_scan_notes_and_folders().expect("scan");
+        assert_eq!(scan.notes.len(), 1);
+        assert!(!scan.folders.iter().any(|f| f.contains("trash")));
+
+        let purged = vault
+            .purge_trash_older_than(Duration::from_secs(3600))
+            .expect("purge");
+        assert!(purged.is_empty());
+        let purged = vault
+            .purge_trash_older_than(Duration::ZERO)
+            .expect("purge all");
+        assert_eq!(purged, vec![folder.id]);
+
+        vault.trash_note("notes/keep.md").expect("trash note");
+        vault.empty_trash().expect("empty");
+        assert!(vault.list_trash().expect("list").is_empty());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::atomic_write::is_atomic_temp_file_name;
 use crate::paths::to_posix_path;
 use anyhow::Result;
 use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
@@ -15,6 +16,9 @@ pub enum VaultWatchChange {
     FolderCreated { path: String },
     FolderRemoved { path: String },
     FolderMoved { from: String, to: String },
+    ResourceChanged { path: String },
+    ResourceRemoved { path: String },
+    ResourceMoved { from: String, to: String },
     RescanRequired,
 }
 
@@ -107,6 +111,8 @@ impl VaultWatcher {
                 EventKind::Remove(_) => {
                     if let Some(rel) = self.to_vault_rel_note_path(path) {
                         out.push(VaultWatchChange::NoteRemoved { path: rel });
+                    } else if let Some(rel) = self.to_vault_rel_resource_path(path, false) {
+                        out.push(VaultWatchChange::ResourceRemoved { path: rel });
                     }
                 }
                 EventKind::Access(_) => continue,
@@ -116,6 +122,11 @@ impl VaultWatcher {
                         continue;
                     }
 
+                    if let Some(rel) = self.to_vault_rel_resource_path(path, true) {
+                        out.push(VaultWatchChange::ResourceChanged { path: rel });
+                        continue;
+                    }
+
                     if let Some(rel) = self.to_vault_rel_folder_path(path, true) {
                         out.push(VaultWatchChange::FolderCreated { path: rel });
                     }
@@ -132,20 +143,7 @@ impl VaultWatcher {
     ) {
         match rename_mode {
             RenameMode::Both if paths.len() >= 2 => {
-                if let (Some(from), Some(to)) = (
-                    self.to_vault_rel_note_path(&paths[0]),
-                    self.to_vault_rel_note_path(&paths[1]),
-                ) {
-                    out.push(VaultWatchChange::NoteMoved { from, to });
-                    return;
-                }
-
-                if let (Some(from), Some(to)) = (
-                    self.to_vault_rel_folder_path(&paths[0], false),
-                    self.to_vault_rel_folder_path(&paths[1], true),
-                ) {
-                    out.push(VaultWatchChange::FolderMoved { from, to });
-                }
+                self.push_rename_pair_changes(&paths[0], &paths[1], out);
             }
             RenameMode::From => {
                 if let Some(path) = paths.first().and_then(|p| self.to_vault_rel_note_path(p)) {
@@ -153,6 +151,14 @@ impl VaultWatcher {
                     return;
                 }
 
+                // The source is gone, so a dotted name may be either a file or a folder.
+                if let Some(path) = paths
+                    .first()
+                    .and_then(|p| self.to_vault_rel_resource_path(p, false))
+                {
+                    out.push(VaultWatchChange::ResourceRemoved { path });
+                }
+
                 if let Some(path) = paths
                     .first()
                     .and_then(|p| self.to_vault_rel_folder_path(p, false))
@@ -166,6 +172,14 @@ impl VaultWatcher {
                     return;
                 }
 
+                if let Some(path) = paths
+                    .first()
+                    .and_then(|p| self.to_vault_rel_resource_path(p, true))
+                {
+                    out.push(VaultWatchChange::ResourceChanged { path });
+                    return;
+                }
+
                 if let Some(path) = paths
                     .first()
                     .and_then(|p| self.to_vault_rel_folder_path(p, true))
@@ -175,26 +189,62 @@ impl VaultWatcher {
             }
             _ => {
                 if paths.len() >= 2 {
-                    if let (Some(from), Some(to)) = (
-                        self.to_vault_rel_note_path(&paths[0]),
-                        self.to_vault_rel_note_path(&paths[1]),
-                    ) {
-                        out.push(VaultWatchChange::NoteMoved { from, to });
-                        return;
-                    }
-
-                    if let (Some(from), Some(to)) = (
-                        self.to_vault_rel_folder_path(&paths[0], false),
-                        self.to_vault_rel_folder_path(&paths[1], true),
-                    ) {
-                        out.push(VaultWatchChange::FolderMoved { from, to });
-                    }
+                    self.push_rename_pair_changes(&paths[0], &paths[1], out);
                 }
             }
         }
     }
 
+    fn push_rename_pair_changes(&self, from: &Path, to: &Path, out: &mut Vec<VaultWatchChange>) {
+        let from_note = self.to_vault_rel_note_path(from);
+        let to_note = self.to_vault_rel_note_path(to);
+        if let (Some(from), Some(to)) = (from_note.clone(), to_note.clone()) {
+            out.push(VaultWatchChange::NoteMoved { from, to });
+            return;
+        }
+
+        if is_atomic_temp_path(from) {
+            if let Some(path) = to_note {
+                out.push(VaultWatchChange::NoteChanged { path });
+            } else if let Some(path) = self.to_vault_rel_resource_path(to, true) {
+                out.push(VaultWatchChange::ResourceChanged { path });
+            }
+            return;
+        }
+
+        let from_resource = self.to_vault_rel_resource_path(from, false);
+        let to_resource = self.to_vault_rel_resource_path(to, true);
+        match (from_note, to_note, from_resource, to_resource) {
+            (_, _, Some(from), Some(to)) => {
+                out.push(VaultWatchChange::ResourceMoved { from, to });
+                return;
+            }
+            // Renames across the note/resource boundary, e.g. `a.md` -> `a.txt`.
+            (Some(from), None, _, Some(to)) => {
+                out.push(VaultWatchChange::NoteRemoved { path: from });
+                out.push(VaultWatchChange::ResourceChanged { path: to });
+                return;
+            }
+            (None, Some(to), Some(from), None) => {
+                out.push(VaultWatchChange::ResourceRemoved { path: from });
+                out.push(VaultWatchChange::NoteChanged { path: to });
+                return;
+            }
+            _ => {}
+        }
+
+        if let (Some(from), Some(to)) = (
+            self.to_vault_rel_folder_path(from, false),
+            self.to_vault_rel_folder_path(to, true),
+        ) {
+            out.push(VaultWatchChange::FolderMoved { from, to });
+        }
+    }
+
     fn to_vault_rel_note_path(&self, abs_path: &Path) -> Option<String> {
+        if is_atomic_temp_path(abs_path) {
+            return None;
+        }
         let rel = abs_path.strip_prefix(&self.root).ok()?;
         let rel_posix = to_posix_path(rel).ok()?;
         if !rel_posix.ends_with(".md") {
@@ -206,11 +256,45 @@ impl VaultWatcher {
         Some(rel_posix)
     }
 
+    /// Vault-relative path of a non-markdown, non-hidden file.
+    ///
+    /// Without `require_file_metadata` (the file is already gone) only paths with an
+    /// extension qualify, to avoid reporting removed folders as resources.
+    fn to_vault_rel_resource_path(
+        &self,
+        abs_path: &Path,
+        require_file_metadata: bool,
+    ) -> Option<String> {
+        if is_atomic_temp_path(abs_path) {
+            return None;
+        }
+        let rel = abs_path.strip_prefix(&self.root).ok()?;
+        let rel_posix = to_posix_path(rel).ok()?;
+        if rel_posix.is_empty() || rel_posix.split('/').any(|part| part.starts_with('.')) {
+            return None;
+        }
+        let extension = Path::new(&rel_posix).extension();
+        if extension.is_some_and(|ext| ext.eq_ignore_ascii_case("md")) {
+            return None;
+        }
+        if require_file_metadata {
+            if !abs_path.is_file() {
+                return None;
+            }
+        } else if extension.is_none() {
+            return None;
+        }
+        Some(rel_posix)
+    }
+
     fn to_vault_rel_folder_path(
         &self,
         abs_path: &Path,
         require_dir_metadata: bool,
     ) -> Option<String> {
+        if is_atomic_temp_path(abs_path) {
+            return None;
+        }
         let rel = abs_path.strip_prefix(&self.root).ok()?;
         let rel_posix = to_posix_path(rel).ok()?;
         let rel_posix = rel_posix.trim_end_matches('/').to_string();
@@ -230,6 +314,13 @@ impl VaultWatcher {
     }
 }
 
+fn is_atomic_temp_path(abs_path: &Path) -> bool {
+    abs_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(is_atomic_temp_file_name)
+}
+
 pub fn collapse_move_pairs(moved_pairs: &[(String, String)]) -> Option<Vec<(String, String)>> {
     let mut moved = HashMap::<String, String>::new();
     for (from, to) in moved_pairs {
@@ -437,6 +528,9 @@ fn dedup_changes(changes: Vec<VaultWatchChange>) -> Result<Vec<VaultWatchChange>
     let mut folder_created = std::collections::HashSet::new();
     let mut folder_removed = std::collections::HashSet::new();
     let mut folder_moved = std::collections::HashMap::new();
+    let mut resource_removed = std::collections::HashSet::new();
+    let mut resource_changed = std::collections::HashSet::new();
+    let mut resource_moved = std::collections::HashMap::new();
     let mut requires_rescan = false;
 
     for change in changes {
@@ -490,6 +584,30 @@ fn dedup_changes(changes: Vec<VaultWatchChange>) -> Result<Vec<VaultWatchChange>
                     folder_created.insert(path);
                 }
             }
+            VaultWatchChange::ResourceMoved { from, to } => {
+                if from == to {
+                    continue;
+                }
+                resource_changed.remove(&from);
+                resource_changed.remove(&to);
+                resource_removed.remove(&from);
+                resource_removed.remove(&to);
+                resource_moved.insert(from, to);
+            }
+            VaultWatchChange::ResourceRemoved { path } => {
+                resource_changed.remove(&path);
+                resource_moved.remove(&path);
+                resource_moved.retain(|_, to| to != &path);
+                resource_removed.insert(path);
+            }
+            VaultWatchChange::ResourceChanged { path } => {
+                if !resource_removed.contains(&path)
+                    && !resource_moved.contains_key(&path)
+                    && !resource_moved.values().any(|to| to == &path)
+                {
+                    resource_changed.insert(path);
+                }
+            }
         }
     }
 
@@ -499,13 +617,17 @@ fn dedup_changes(changes: Vec<VaultWatchChange>) -> Result<Vec<VaultWatchChange>
 
     let collapsed_moved = collapse_moved_map(moved);
     let collapsed_folder_moved = collapse_moved_map(folder_moved);
+    let collapsed_resource_moved = collapse_moved_map(resource_moved);
     let mut out = Vec::with_capacity(
         collapsed_moved.len()
             + changed.len()
             + removed.len()
             + collapsed_folder_moved.len()
             + folder_created.len()
-            + folder_removed.len(),
+            + folder_removed.len()
+            + collapsed_resource_moved.len()
+            + resource_changed.len()
+            + resource_removed.len(),
     );
 
     let mut moved_sorted = collapsed_moved.into_iter().collect::<Vec<_>>();
@@ -544,6 +666,24 @@ fn dedup_changes(changes: Vec<VaultWatchChange>) -> Result<Vec<VaultWatchChange>
         out.push(VaultWatchChange::FolderRemoved { path });
     }
 
+    let mut resource_moved_sorted = collapsed_resource_moved.into_iter().collect::<Vec<_>>();
+    resource_moved_sorted.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    for (from, to) in resource_moved_sorted {
+        out.push(VaultWatchChange::ResourceMoved { from, to });
+    }
+
+    let mut resource_changed_sorted = resource_changed.into_iter().collect::<Vec<_>>();
+    resource_changed_sorted.sort();
+    for path in resource_changed_sorted {
+        out.push(VaultWatchChange::ResourceChanged { path });
+    }
+
+    let mut resource_removed_sorted = resource_removed.into_iter().collect::<Vec<_>>();
+    resource_removed_sorted.sort();
+    for path in resource_removed_sorted {
+        out.push(VaultWatchChange::ResourceRemoved { path });
+    }
+
     Ok(out)
 }
 
@@ -797,4 +937,75 @@ mod tests {
         assert!(note_path_has_folder_prefix("notes/a/x.md", "notes/a"));
         assert!(!note_path_has_folder_prefix("notes/ab/x.md", "notes/a"));
     }
+
+    #[test]
+    fn dedup_collapses_resource_events() {
+        let out = dedup_changes(vec![
+            VaultWatchChange::ResourceChanged {
+                path: "attachments/a.png".to_string(),
+            },
+            VaultWatchChange::ResourceMoved {
+                from: "attachments/a.png".to_string(),
+                to: "attachments/b.png".to_string(),
+            },
+            VaultWatchChange::ResourceMoved {
+                from: "attachments/b.png".to_string(),
+                to: "attachments/c.png".to_string(),
+            },
+            VaultWatchChange::ResourceChanged {
+                path: "attachments/d.pdf".to_string(),
+            },
+            VaultWatchChange::ResourceRemoved {
+                path: "attachments/d.pdf".to_string(),
+            },
+        ])
+        .expect("dedup");
+
+        assert_eq!(
+            out,
+            vec![
+                VaultWatchChange::ResourceMoved {
+                    from: "attachments/a.png".to_string(),
+                    to: "attachments/c.png".to_string(),
+                },
+                VaultWatchChange::ResourceMoved {
+                    from: "attachments/b.png".to_string(),
+                    to: "attachments/c.png".to_string(),
+                },
+                VaultWatchChange::ResourceRemoved {
+                    path: "attachments/d.pdf".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn resource_paths_skip_notes_hidden_and_internal_files() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("xnote_core_watch_resources_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        std::fs::create_dir_all(temp_dir.join("attachments/sub.dir")).expect("create dirs");
+        std::fs::write(temp_dir.join("attachments/a.png"), b"x").expect("png");
+
+        let watcher = VaultWatcher::new(&temp_dir).expect("watcher");
+        let rel =
+            |p: &str, require: bool| watcher.to_vault_rel_resource_path(&temp_dir.join(p), require);
+        assert_eq!(
+            rel("attachments/a.png", true).as_deref(),
+            Some("attachments/a.png")
+        );
+        assert_eq!(
+            rel("attachments/gone.pdf", false).as_deref(),
+            Some("attachments/gone.pdf")
+        );
+        assert_eq!(rel("attachments/gone.pdf", true), None);
+        assert_eq!(rel("attachments/sub.dir", true), None);
+        assert_eq!(rel("attachments/LICENSE", false), None);
+        assert_eq!(rel("notes/A.md", false), None);
+        assert_eq!(rel(".xnote/meta/N1.json", false), None);
+        assert_eq!(rel("attachments/.DS_Store", false), None);
+
+        drop(watcher);
+        let _ = std::fs::remove_dir_all(&temp_dir);
+    }
 }
@@ -1809,8 +1809,8 @@ impl XnoteWindow {
                             let vault = vault.clone();
                             async move {
                                 let started_at = Instant::now();
-                                let knowledge_index =
-                                    KnowledgeIndex::build_from_entries(&vault, &entries)?;
+                                let (knowledge_index, _cache_stats) =
+                                    KnowledgeIndex::build_with_cache(&vault, &entries)?;
                                 let duration_ms = started_at.elapsed().as_millis();
                                 Ok::<_, anyhow::Error>((knowledge_index, duration_ms))
                             }
@@ -12244,6 +12244,10 @@ impl XnoteWindow {
                     folder_moved_note_pairs.extend(moved);
                     bookmarks_changed |= folder_bookmarks_changed;
                 }
+                // The Resources module has no state to update yet.
+                VaultWatchChange::ResourceChanged { .. }
+                | VaultWatchChange::ResourceRemoved { .. }
+                | VaultWatchChange::ResourceMoved { .. } => {}
                 VaultWatchChange::RescanRequired => needs_rescan = true,
             }
         }
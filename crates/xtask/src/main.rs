@@ -7,6 +7,8 @@ use std::time::Instant;
 use sysinfo::{Pid, System};
 use xnote_core::ai::generate_default_ai_tool_descriptor_bundle_json_pretty;
 use xnote_core::knowledge::{KnowledgeIndex, SearchOptions};
+use xnote_core::settings::{migrate_settings_file, project_settings_path};
+use xnote_core::vault::check::{CheckOptions, CheckSeverity};
 use xnote_core::vault::Vault;
 use xnote_core::watch::{
     collapse_move_pairs, expand_folder_move_pairs_to_note_moves, note_path_has_folder_prefix,
@@ -25,6 +27,8 @@ fn main() -> Result<()> {
         "perf" => cmd_perf(args.collect()),
         "foundation-gate" => cmd_foundation_gate(args.collect()),
         "export-ai-tools" => cmd_export_ai_tools(args.collect()),
+        "check" => cmd_check(args.collect()),
+        "migrate" => cmd_migrate(args.collect()),
         "help" | "-h" | "--help" => {
             print_help();
             Ok(())
@@ -45,6 +49,8 @@ Commands:
   perf        Print basic perf metrics (open/scan/index/search/watch)
   foundation-gate  Run full baseline gate (tests/check/perf profiles)
   export-ai-tools  Export VCP/MCP tool descriptors from xnote-core registry
+  check       Check vault integrity (meta/order/ids); --repair fixes what it can
+  migrate     Upgrade vault meta and settings files to the current schema; --dry-run reports only
 
 Examples:
   cargo run -p xtask -- gen-vault --path .\\Knowledge.vault --notes 100000 --max-depth 200 --clean
@@ -52,6 +58,8 @@ Examples:
   cargo run -p xtask -- foundation-gate --path .\\Knowledge.vault --query note --iterations 10
   cargo run -p xtask -- export-ai-tools --out .\\docs\\ai_tools_bundle.json
   cargo run -p xtask -- export-ai-tools
+  cargo run -p xtask -- check --path .\\Knowledge.vault --repair
+  cargo run -p xtask -- migrate --path .\\Knowledge.vault --dry-run
 
 XNote UI:
   $env:XNOTE_VAULT = "C:\path\to\Knowledge.vault"
@@ -97,6 +105,131 @@ fn parse_export_ai_tools_args(args: Vec<String>) -> Result<Option<PathBuf>> {
     Ok(out)
 }
 
+struct CheckArgs {
+    path: PathBuf,
+    repair: bool,
+}
+
+fn cmd_check(args: Vec<String>) -> Result<()> {
+    let args = parse_check_args(args)?;
+    let vault = Vault::open(&args.path)?;
+    let report = vault.check(CheckOptions {
+        repair: args.repair,
+    })?;
+
+    for finding in &report.findings {
+        println!(
+            "{:<7} {:<24} {}: {}{}",
+            finding.severity.as_tag(),
+            finding.kind.as_tag(),
+            finding.path,
+            finding.detail,
+            if finding.repaired { " [repaired]" } else { "" }
+        );
+    }
+    eprintln!(
+        "check: notes={} meta_files={} order_files={} errors={} warnings={} info={}",
+        report.notes_scanned,
+        report.meta_files_scanned,
+        report.order_files_scanned,
+        report.count(CheckSeverity::Error),
+        report.count(CheckSeverity::Warning),
+        report.count(CheckSeverity::Info),
+    );
+
+    if !report.is_clean() {
+        bail!("vault check found unrepaired problems");
+    }
+    Ok(())
+}
+
+fn parse_check_args(args: Vec<String>) -> Result<CheckArgs> {
+    let mut path: Option<PathBuf> = None;
+    let mut repair = false;
+    let mut it = args.into_iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--path" | "--vault" => {
+                path = Some(PathBuf::from(it.next().context("--path requires a value")?))
+            }
+            "--repair" => repair = true,
+            other => bail!("unknown check arg: {other}"),
+        }
+    }
+    Ok(CheckArgs {
+        path: path.unwrap_or_else(|| PathBuf::from("Knowledge.vault")),
+        repair,
+    })
+}
+
+struct MigrateArgs {
+    path: PathBuf,
+    dry_run: bool,
+}
+
+fn cmd_migrate(args: Vec<String>) -> Result<()> {
+    let args = parse_migrate_args(args)?;
+    let vault = Vault::open(&args.path)?;
+    let report = vault.migrate_note_meta_files(args.dry_run)?;
+
+    for entry in &report.migrated {
+        println!(
+            "meta     {}: v{} -> v{}",
+            entry.path, entry.from_version, entry.to_version
+        );
+    }
+    for failure in &report.failed {
+        println!("failed   {}: {}", failure.path, failure.error);
+    }
+
+    let settings_path = project_settings_path(&args.path);
+    if let Some(migration) = migrate_settings_file(&settings_path, args.dry_run)? {
+        println!(
+            "settings {}: v{} -> v{}",
+            settings_path.display(),
+            migration.from_version,
+            migration.to_version
+        );
+    }
+
+    eprintln!(
+        "migrate: meta_files={} migrated={} failed={} dry_run={}{}",
+        report.files_scanned,
+        report.migrated.len(),
+        report.failed.len(),
+        args.dry_run,
+        report
+            .backup_dir
+            .as_deref()
+            .map(|dir| format!(" backup={dir}"))
+            .unwrap_or_default()
+    );
+
+    if !report.failed.is_empty() {
+        bail!("some meta files could not be migrated");
+    }
+    Ok(())
+}
+
+fn parse_migrate_args(args: Vec<String>) -> Result<MigrateArgs> {
+    let mut path: Option<PathBuf> = None;
+    let mut dry_run = false;
+    let mut it = args.into_iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--path" | "--vault" => {
+                path = Some(PathBuf::from(it.next().context("--path requires a value")?))
+            }
+            "--dry-run" => dry_run = true,
+            other => bail!("unknown migrate arg: {other}"),
+        }
+    }
+    Ok(MigrateArgs {
+        path: path.unwrap_or_else(|| PathBuf::from("Knowledge.vault")),
+        dry_run,
+    })
+}
+
 #[derive(Clone)]
 struct Rng(u64);
 
@@ -466,6 +599,22 @@ fn cmd_perf(args: Vec<String>) -> Result<()> {
     let mut knowledge_index = KnowledgeIndex::build_from_entries(&vault, &entries)?;
     let knowledge_build_ms = knowledge_build_start.elapsed().as_millis();
 
+    let cache_path = KnowledgeIndex::cache_path(&vault);
+    match fs::remove_file(&cache_path) {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err).with_context(|| format!("remove {:?}", cache_path)),
+    }
+    let cache_cold_start = Instant::now();
+    let (_, cache_cold_stats) = KnowledgeIndex::build_with_cache(&vault, &entries)?;
+    let cache_cold_ms = cache_cold_start.elapsed().as_millis();
+    let cache_warm_start = Instant::now();
+    let (_, cache_warm_stats) = KnowledgeIndex::build_with_cache(&vault, &entries)?;
+    let cache_warm_ms = cache_warm_start.elapsed().as_millis();
+    let cache_bytes = fs::metadata(&cache_path)
+        .map(|meta| meta.len())
+        .unwrap_or(0);
+
     let mut search_samples = Vec::with_capacity(args.iterations);
     let mut quick_open_samples = Vec::with_capacity(args.iterations);
     let mut watch_apply_samples = Vec::with_capacity(args.iterations);
@@ -540,6 +689,16 @@ fn cmd_perf(args: Vec<String>) -> Result<()> {
     println!("  scan_ms: {scan_ms}");
     println!("  index_ms: {index_ms}");
     println!("  knowledge_index_build_ms: {knowledge_build_ms}");
+    println!("  knowledge_cache_cold_open_ms: {cache_cold_ms}");
+    println!("  knowledge_cache_cold_parsed: {}", cache_cold_stats.parsed);
+    println!("  knowledge_cache_warm_open_ms: {cache_warm_ms}");
+    println!(
+        "  knowledge_cache_warm_status: {:?}",
+        cache_warm_stats.status
+    );
+    println!("  knowledge_cache_warm_reused: {}", cache_warm_stats.reused);
+    println!("  knowledge_cache_warm_parsed: {}", cache_warm_stats.parsed);
+    println!("  knowledge_cache_bytes: {cache_bytes}");
     println!("  note_count: {}", entries.len());
     println!("  folder_count_notes: {}", by_folder.len());
     println!("  folder_count_tree: {}", child_sets.len());
@@ -857,6 +1016,9 @@ fn apply_watch_changes_benchmark(
                     folder_moves.insert(from, to);
                 }
             }
+            VaultWatchChange::ResourceChanged { .. }
+            | VaultWatchChange::ResourceRemoved { .. }
+            | VaultWatchChange::ResourceMoved { .. } => {}
             VaultWatchChange::RescanRequired => {
                 needs_rebuild = true;
                 break;